@@ -16,9 +16,6 @@
 //! Test writing to port 514 on the local host.
 
 use tracing::{debug, error, info, trace, warn};
-use tracing_rfc_5424::{
-    layer::Layer, rfc5424::Rfc5424, tracing::TrivialTracingFormatter, transport::UdpTransport,
-};
 use tracing_subscriber::{
     layer::SubscriberExt, // Needed to get `with()`
     registry::Registry,
@@ -26,8 +23,7 @@ use tracing_subscriber::{
 
 pub fn main() {
     // Setup the real subsriber...
-    let subscriber = Registry::default()
-        .with(Layer::<tracing_subscriber::Registry, Rfc5424, TrivialTracingFormatter, UdpTransport>::try_default().unwrap());
+    let subscriber = Registry::default().with(tracing_rfc_5424::udp_layer().unwrap());
     // and install it.
     let _guard = tracing::subscriber::set_default(subscriber);
 
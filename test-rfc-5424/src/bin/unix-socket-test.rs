@@ -18,17 +18,13 @@
 #[cfg(unix)]
 mod test {
     use tracing::{debug, error, info, trace, warn};
-    use tracing_rfc_5424::{
-        layer::Layer, rfc3164::Rfc3164, tracing::TrivialTracingFormatter, transport::UnixSocket,
-    };
     use tracing_subscriber::{
         layer::SubscriberExt, // Needed to get `with()`
         registry::Registry,
     };
 
     pub fn run() {
-        let subscriber = Registry::default()
-        .with(Layer::<tracing_subscriber::Registry, Rfc3164, TrivialTracingFormatter, UnixSocket>::try_default().unwrap());
+        let subscriber = Registry::default().with(tracing_rfc_5424::unix_layer().unwrap());
         let _guard = tracing::subscriber::set_default(subscriber);
 
         trace!("你好, Unix domain socket.");
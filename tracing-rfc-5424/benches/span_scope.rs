@@ -0,0 +1,67 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Benchmark the per-event cost of the span context (root target & span fields) with spans nested
+//! to various depths, comparing the [`Layer`]'s cached span context against walking each event's
+//! scope. Run with `cargo bench --features test-util`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tracing_rfc_5424::{
+    layer::Layer, rfc5424::Rfc5424, tracing::TrivialTracingFormatter, transport::WriterTransport,
+};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+type BenchLayer = Layer<Registry, Rfc5424, TrivialTracingFormatter, WriterTransport<std::io::Sink>>;
+
+fn layer() -> BenchLayer {
+    Layer::new(
+        Rfc5424::builder()
+            .with_span_fields(true)
+            .with_root_span_target(true)
+            .build(),
+        TrivialTracingFormatter::default(),
+        WriterTransport::new(std::io::sink()),
+    )
+}
+
+fn span_scope(c: &mut Criterion) {
+    let mut group = c.benchmark_group("span_scope");
+    for depth in [1, 4, 16, 64] {
+        for (name, layer) in [("walk", layer().without_span_cache()), ("cached", layer())] {
+            let subscriber = Registry::default().with(layer);
+            tracing::subscriber::with_default(subscriber, || {
+                // Enter the spans once, and log repeatedly from within them; that's the case in
+                // which caching pays off.
+                fn nest(depth: usize, f: &mut dyn FnMut()) {
+                    if depth == 0 {
+                        f()
+                    } else {
+                        let _span = tracing::info_span!("span", depth, user = "frodo").entered();
+                        nest(depth - 1, f)
+                    }
+                }
+                nest(depth, &mut || {
+                    group.bench_function(BenchmarkId::new(name, depth), |b| {
+                        b.iter(|| tracing::info!("Hello, world!"))
+                    });
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, span_scope);
+criterion_main!(benches);
@@ -24,3 +24,43 @@ pub fn bytes_from_os_str(s: std::ffi::OsString) -> Vec<u8> {
 pub fn bytes_from_os_str(s: std::ffi::OsString) -> Vec<u8> {
     unimplemented!("bytes_from_os_str is not supported on non-Unix.");
 }
+
+/// Convert an internationalized hostname to its ASCII-compatible encoding (i.e. Punycode).
+///
+/// ASCII hostnames are returned unchanged. If the `idna` feature is disabled, or the conversion
+/// fails, the hostname is also returned unchanged (& will presumably be rejected downstream).
+#[cfg(feature = "idna")]
+pub fn ascii_hostname(bytes: Vec<u8>) -> Vec<u8> {
+    if bytes.is_ascii() {
+        return bytes;
+    }
+    match std::str::from_utf8(&bytes).map(idna::domain_to_ascii) {
+        Ok(Ok(ace)) => ace.into_bytes(),
+        _ => bytes,
+    }
+}
+
+#[cfg(not(feature = "idna"))]
+pub fn ascii_hostname(bytes: Vec<u8>) -> Vec<u8> {
+    bytes
+}
+
+#[cfg(all(test, feature = "idna"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ascii_hostname() {
+        assert_eq!(ascii_hostname(b"bree.local".to_vec()), b"bree.local");
+        assert_eq!(
+            ascii_hostname("bücher".as_bytes().to_vec()),
+            b"xn--bcher-kva"
+        );
+        assert_eq!(
+            ascii_hostname("münchen.example".as_bytes().to_vec()),
+            b"xn--mnchen-3ya.example"
+        );
+        // Not UTF-8: passed through untouched
+        assert_eq!(ascii_hostname(vec![0xff, 0xfe]), vec![0xff, 0xfe]);
+    }
+}
@@ -101,6 +101,85 @@ pub enum Facility {
     LOG_LOCAL7 = 23 << 3,
 }
 
+impl Facility {
+    /// Look-up a [`Facility`] by the keyword used to name it in `syslog.conf` & friends.
+    ///
+    /// Configuration files (and command-line flags modelled on them) traditionally name facilities
+    /// by lower-case keywords such as `kern`, `mail` or `local3` rather than by the `LOG_*`
+    /// constants. This method accepts those keywords (case-insensitively), as well as `security`,
+    /// the deprecated synonym for `auth` retained by glibc. It returns `None` if `keyword` is not
+    /// recognized.
+    pub fn from_keyword(keyword: &str) -> Option<Facility> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "kern" => Some(Facility::LOG_KERN),
+            "user" => Some(Facility::LOG_USER),
+            "mail" => Some(Facility::LOG_MAIL),
+            "daemon" => Some(Facility::LOG_DAEMON),
+            "auth" | "security" => Some(Facility::LOG_AUTH),
+            "syslog" => Some(Facility::LOG_SYSLOG),
+            "lpr" => Some(Facility::LOG_LPR),
+            "news" => Some(Facility::LOG_NEWS),
+            "uucp" => Some(Facility::LOG_UUCP),
+            "cron" => Some(Facility::LOG_CRON),
+            "authpriv" => Some(Facility::LOG_AUTHPRIV),
+            "ftp" => Some(Facility::LOG_FTP),
+            "ntp" => Some(Facility::LOG_NTP),
+            "audit" => Some(Facility::LOG_AUDIT),
+            "alert" => Some(Facility::LOG_ALERT),
+            "clock" => Some(Facility::LOG_CLOCK),
+            "local0" => Some(Facility::LOG_LOCAL0),
+            "local1" => Some(Facility::LOG_LOCAL1),
+            "local2" => Some(Facility::LOG_LOCAL2),
+            "local3" => Some(Facility::LOG_LOCAL3),
+            "local4" => Some(Facility::LOG_LOCAL4),
+            "local5" => Some(Facility::LOG_LOCAL5),
+            "local6" => Some(Facility::LOG_LOCAL6),
+            "local7" => Some(Facility::LOG_LOCAL7),
+            _ => None,
+        }
+    }
+    /// Look-up a [`Facility`] by its numeric code (0-23), as found in the PRI part of a syslog
+    /// message (i.e. *before* being multiplied by 8). Returns `None` if `code` is out of range.
+    pub fn from_code(code: u8) -> Option<Facility> {
+        match code {
+            0 => Some(Facility::LOG_KERN),
+            1 => Some(Facility::LOG_USER),
+            2 => Some(Facility::LOG_MAIL),
+            3 => Some(Facility::LOG_DAEMON),
+            4 => Some(Facility::LOG_AUTH),
+            5 => Some(Facility::LOG_SYSLOG),
+            6 => Some(Facility::LOG_LPR),
+            7 => Some(Facility::LOG_NEWS),
+            8 => Some(Facility::LOG_UUCP),
+            9 => Some(Facility::LOG_CRON),
+            10 => Some(Facility::LOG_AUTHPRIV),
+            11 => Some(Facility::LOG_FTP),
+            12 => Some(Facility::LOG_NTP),
+            13 => Some(Facility::LOG_AUDIT),
+            14 => Some(Facility::LOG_ALERT),
+            15 => Some(Facility::LOG_CLOCK),
+            16 => Some(Facility::LOG_LOCAL0),
+            17 => Some(Facility::LOG_LOCAL1),
+            18 => Some(Facility::LOG_LOCAL2),
+            19 => Some(Facility::LOG_LOCAL3),
+            20 => Some(Facility::LOG_LOCAL4),
+            21 => Some(Facility::LOG_LOCAL5),
+            22 => Some(Facility::LOG_LOCAL6),
+            23 => Some(Facility::LOG_LOCAL7),
+            _ => None,
+        }
+    }
+    /// This facility's numeric code (0-23), i.e. *before* being multiplied by 8
+    pub fn code(&self) -> u8 {
+        *self as u8 >> 3
+    }
+    /// The PRI value for a message at `level` in this facility: the facility code multiplied by
+    /// eight, plus the numeric severity
+    pub fn pri(&self, level: Level) -> u8 {
+        self.code() * 8 + level.severity()
+    }
+}
+
 impl std::default::Default for Facility {
     /// The default facility is `LOG_USER`.
     fn default() -> Self {
@@ -143,6 +222,42 @@ impl std::fmt::Display for Facility {
     }
 }
 
+/// A [`Facility`] that may be changed in place, through a shared reference
+///
+/// The formatters hold their facility in one of these so that it may be changed at runtime (via
+/// `set_facility()`) without rebuilding the subscriber of which they're a part. The facility is an
+/// independent value (no other state is published along with it), so relaxed ordering suffices:
+/// a message formatted concurrently with a change may carry either the old or the new facility,
+/// but every message formatted after `set_facility()` returns (on any thread that has synchronized
+/// with the setter by other means) will carry the new one.
+pub(crate) struct AtomicFacility(std::sync::atomic::AtomicU8);
+
+impl AtomicFacility {
+    pub(crate) fn new(facility: Facility) -> AtomicFacility {
+        AtomicFacility(std::sync::atomic::AtomicU8::new(facility.code()))
+    }
+    pub(crate) fn load(&self) -> Facility {
+        // Only ever stored from a valid `Facility`
+        Facility::from_code(self.0.load(std::sync::atomic::Ordering::Relaxed)).unwrap_or_default()
+    }
+    pub(crate) fn store(&self, facility: Facility) {
+        self.0
+            .store(facility.code(), std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+impl Clone for AtomicFacility {
+    fn clone(&self) -> Self {
+        AtomicFacility::new(self.load())
+    }
+}
+
+impl std::fmt::Debug for AtomicFacility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        write!(f, "{:?}", self.load())
+    }
+}
+
 /// Both RFCs [5424] & [3164] define eight severity levels for messages. The enumeration values
 /// duplicate the constants documented as per the `syslog()` manual [page] & defined in
 /// `<syslog.h>`.
@@ -171,6 +286,83 @@ pub enum Level {
     LOG_DEBUG,
 }
 
+impl Level {
+    /// Look-up a [`Level`] by the keyword used to name it in `syslog.conf` & friends.
+    ///
+    /// This method accepts the traditional lower-case severity keywords (`emerg`, `alert`, `crit`,
+    /// `err`, `warning`, `notice`, `info` & `debug`) case-insensitively, along with the
+    /// commonly-used aliases `panic` (for `emerg`), `error` (for `err`) and `warn` (for
+    /// `warning`). It returns `None` if `keyword` is not recognized.
+    pub fn from_keyword(keyword: &str) -> Option<Level> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "emerg" | "panic" => Some(Level::LOG_EMERG),
+            "alert" => Some(Level::LOG_ALERT),
+            "crit" => Some(Level::LOG_CRIT),
+            "err" | "error" => Some(Level::LOG_ERR),
+            "warning" | "warn" => Some(Level::LOG_WARNING),
+            "notice" => Some(Level::LOG_NOTICE),
+            "info" => Some(Level::LOG_INFO),
+            "debug" => Some(Level::LOG_DEBUG),
+            _ => None,
+        }
+    }
+    /// Look-up a [`Level`] by its numeric severity (0-7). Returns `None` if `code` is out of range.
+    pub fn from_code(code: u8) -> Option<Level> {
+        match code {
+            0 => Some(Level::LOG_EMERG),
+            1 => Some(Level::LOG_ALERT),
+            2 => Some(Level::LOG_CRIT),
+            3 => Some(Level::LOG_ERR),
+            4 => Some(Level::LOG_WARNING),
+            5 => Some(Level::LOG_NOTICE),
+            6 => Some(Level::LOG_INFO),
+            7 => Some(Level::LOG_DEBUG),
+            _ => None,
+        }
+    }
+    /// This level's numeric severity (0-7), as used in the PRI part of a syslog message
+    ///
+    /// This is spelled-out, rather than relying on the enum's discriminants, so that re-ordering
+    /// the variants can't silently corrupt the PRI.
+    pub fn severity(&self) -> u8 {
+        match self {
+            Level::LOG_EMERG => 0,
+            Level::LOG_ALERT => 1,
+            Level::LOG_CRIT => 2,
+            Level::LOG_ERR => 3,
+            Level::LOG_WARNING => 4,
+            Level::LOG_NOTICE => 5,
+            Level::LOG_INFO => 6,
+            Level::LOG_DEBUG => 7,
+        }
+    }
+}
+
+/// The standard mapping from [`tracing`] levels to syslog severities
+///
+/// `TRACE` & `DEBUG` map to [`LOG_DEBUG`](Level::LOG_DEBUG), `INFO` to
+/// [`LOG_INFO`](Level::LOG_INFO), `WARN` to [`LOG_WARNING`](Level::LOG_WARNING) and `ERROR` to
+/// [`LOG_ERR`](Level::LOG_ERR). This is the mapping used by the formatters in this crate unless
+/// configured otherwise.
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+impl From<&tracing::Level> for Level {
+    fn from(level: &tracing::Level) -> Self {
+        match *level {
+            tracing::Level::TRACE | tracing::Level::DEBUG => Level::LOG_DEBUG,
+            tracing::Level::INFO => Level::LOG_INFO,
+            tracing::Level::WARN => Level::LOG_WARNING,
+            tracing::Level::ERROR => Level::LOG_ERR,
+        }
+    }
+}
+
+impl From<tracing::Level> for Level {
+    fn from(level: tracing::Level) -> Self {
+        Level::from(&level)
+    }
+}
+
 impl std::fmt::Display for Level {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
         write!(
@@ -190,6 +382,123 @@ impl std::fmt::Display for Level {
     }
 }
 
+/// A [`Facility`] with no counterpart in the [`syslog`] crate
+///
+/// [`syslog`]: https://docs.rs/syslog
+#[cfg(feature = "syslog-compat")]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UnsupportedFacility(pub Facility);
+
+#[cfg(feature = "syslog-compat")]
+impl std::fmt::Display for UnsupportedFacility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        write!(f, "{} has no counterpart in the syslog crate", self.0)
+    }
+}
+
+#[cfg(feature = "syslog-compat")]
+impl std::error::Error for UnsupportedFacility {}
+
+/// Every facility known to the [`syslog`] crate is known to us
+///
+/// [`syslog`]: https://docs.rs/syslog
+#[cfg(feature = "syslog-compat")]
+impl From<syslog::Facility> for Facility {
+    fn from(facility: syslog::Facility) -> Self {
+        match facility {
+            syslog::Facility::LOG_KERN => Facility::LOG_KERN,
+            syslog::Facility::LOG_USER => Facility::LOG_USER,
+            syslog::Facility::LOG_MAIL => Facility::LOG_MAIL,
+            syslog::Facility::LOG_DAEMON => Facility::LOG_DAEMON,
+            syslog::Facility::LOG_AUTH => Facility::LOG_AUTH,
+            syslog::Facility::LOG_SYSLOG => Facility::LOG_SYSLOG,
+            syslog::Facility::LOG_LPR => Facility::LOG_LPR,
+            syslog::Facility::LOG_NEWS => Facility::LOG_NEWS,
+            syslog::Facility::LOG_UUCP => Facility::LOG_UUCP,
+            syslog::Facility::LOG_CRON => Facility::LOG_CRON,
+            syslog::Facility::LOG_AUTHPRIV => Facility::LOG_AUTHPRIV,
+            syslog::Facility::LOG_FTP => Facility::LOG_FTP,
+            syslog::Facility::LOG_LOCAL0 => Facility::LOG_LOCAL0,
+            syslog::Facility::LOG_LOCAL1 => Facility::LOG_LOCAL1,
+            syslog::Facility::LOG_LOCAL2 => Facility::LOG_LOCAL2,
+            syslog::Facility::LOG_LOCAL3 => Facility::LOG_LOCAL3,
+            syslog::Facility::LOG_LOCAL4 => Facility::LOG_LOCAL4,
+            syslog::Facility::LOG_LOCAL5 => Facility::LOG_LOCAL5,
+            syslog::Facility::LOG_LOCAL6 => Facility::LOG_LOCAL6,
+            syslog::Facility::LOG_LOCAL7 => Facility::LOG_LOCAL7,
+        }
+    }
+}
+
+/// The [`syslog`] crate doesn't know [`LOG_NTP`](Facility::LOG_NTP),
+/// [`LOG_AUDIT`](Facility::LOG_AUDIT), [`LOG_ALERT`](Facility::LOG_ALERT) or
+/// [`LOG_CLOCK`](Facility::LOG_CLOCK); converting those fails.
+///
+/// [`syslog`]: https://docs.rs/syslog
+#[cfg(feature = "syslog-compat")]
+impl TryFrom<Facility> for syslog::Facility {
+    type Error = UnsupportedFacility;
+    fn try_from(facility: Facility) -> StdResult<Self, Self::Error> {
+        match facility {
+            Facility::LOG_KERN => Ok(syslog::Facility::LOG_KERN),
+            Facility::LOG_USER => Ok(syslog::Facility::LOG_USER),
+            Facility::LOG_MAIL => Ok(syslog::Facility::LOG_MAIL),
+            Facility::LOG_DAEMON => Ok(syslog::Facility::LOG_DAEMON),
+            Facility::LOG_AUTH => Ok(syslog::Facility::LOG_AUTH),
+            Facility::LOG_SYSLOG => Ok(syslog::Facility::LOG_SYSLOG),
+            Facility::LOG_LPR => Ok(syslog::Facility::LOG_LPR),
+            Facility::LOG_NEWS => Ok(syslog::Facility::LOG_NEWS),
+            Facility::LOG_UUCP => Ok(syslog::Facility::LOG_UUCP),
+            Facility::LOG_CRON => Ok(syslog::Facility::LOG_CRON),
+            Facility::LOG_AUTHPRIV => Ok(syslog::Facility::LOG_AUTHPRIV),
+            Facility::LOG_FTP => Ok(syslog::Facility::LOG_FTP),
+            Facility::LOG_LOCAL0 => Ok(syslog::Facility::LOG_LOCAL0),
+            Facility::LOG_LOCAL1 => Ok(syslog::Facility::LOG_LOCAL1),
+            Facility::LOG_LOCAL2 => Ok(syslog::Facility::LOG_LOCAL2),
+            Facility::LOG_LOCAL3 => Ok(syslog::Facility::LOG_LOCAL3),
+            Facility::LOG_LOCAL4 => Ok(syslog::Facility::LOG_LOCAL4),
+            Facility::LOG_LOCAL5 => Ok(syslog::Facility::LOG_LOCAL5),
+            Facility::LOG_LOCAL6 => Ok(syslog::Facility::LOG_LOCAL6),
+            Facility::LOG_LOCAL7 => Ok(syslog::Facility::LOG_LOCAL7),
+            Facility::LOG_NTP | Facility::LOG_AUDIT | Facility::LOG_ALERT | Facility::LOG_CLOCK => {
+                Err(UnsupportedFacility(facility))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "syslog-compat")]
+impl From<syslog::Severity> for Level {
+    fn from(severity: syslog::Severity) -> Self {
+        match severity {
+            syslog::Severity::LOG_EMERG => Level::LOG_EMERG,
+            syslog::Severity::LOG_ALERT => Level::LOG_ALERT,
+            syslog::Severity::LOG_CRIT => Level::LOG_CRIT,
+            syslog::Severity::LOG_ERR => Level::LOG_ERR,
+            syslog::Severity::LOG_WARNING => Level::LOG_WARNING,
+            syslog::Severity::LOG_NOTICE => Level::LOG_NOTICE,
+            syslog::Severity::LOG_INFO => Level::LOG_INFO,
+            syslog::Severity::LOG_DEBUG => Level::LOG_DEBUG,
+        }
+    }
+}
+
+#[cfg(feature = "syslog-compat")]
+impl From<Level> for syslog::Severity {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::LOG_EMERG => syslog::Severity::LOG_EMERG,
+            Level::LOG_ALERT => syslog::Severity::LOG_ALERT,
+            Level::LOG_CRIT => syslog::Severity::LOG_CRIT,
+            Level::LOG_ERR => syslog::Severity::LOG_ERR,
+            Level::LOG_WARNING => syslog::Severity::LOG_WARNING,
+            Level::LOG_NOTICE => syslog::Severity::LOG_NOTICE,
+            Level::LOG_INFO => syslog::Severity::LOG_INFO,
+            Level::LOG_DEBUG => syslog::Severity::LOG_DEBUG,
+        }
+    }
+}
+
 #[cfg(test)]
 mod facility_level_tests {
     use super::*;
@@ -199,5 +508,148 @@ mod facility_level_tests {
         assert_eq!(14, (Facility::LOG_USER as u8) | (Level::LOG_INFO as u8));
         assert_eq!(format!("{}", Facility::LOG_FTP), "LOG_FTP".to_string());
         assert_eq!(format!("{:?}", Facility::LOG_FTP), "LOG_FTP".to_string());
+        for code in 0..24 {
+            assert_eq!(Facility::from_code(code).unwrap() as u8, code << 3);
+        }
+        assert_eq!(Facility::from_code(24), None);
+        for code in 0..8 {
+            assert_eq!(Level::from_code(code).unwrap() as u8, code);
+        }
+        assert_eq!(Level::from_code(8), None);
+    }
+    /// Check the PRI for every facility & level
+    #[test]
+    fn test_pri_exhaustive() {
+        for code in 0..24 {
+            let facility = Facility::from_code(code).unwrap();
+            assert_eq!(facility.code(), code);
+            for severity in 0..8 {
+                let level = Level::from_code(severity).unwrap();
+                assert_eq!(level.severity(), severity);
+                assert_eq!(facility.pri(level), code * 8 + severity);
+            }
+        }
+        assert_eq!(Facility::LOG_LOCAL7.pri(Level::LOG_DEBUG), 191);
+    }
+    /// Check the mapping from `tracing` levels
+    #[test]
+    fn test_from_tracing_level() {
+        for (level, expected) in [
+            (tracing::Level::TRACE, Level::LOG_DEBUG),
+            (tracing::Level::DEBUG, Level::LOG_DEBUG),
+            (tracing::Level::INFO, Level::LOG_INFO),
+            (tracing::Level::WARN, Level::LOG_WARNING),
+            (tracing::Level::ERROR, Level::LOG_ERR),
+        ] {
+            assert_eq!(Level::from(&level), expected);
+            assert_eq!(Level::from(level), expected);
+        }
+    }
+    /// Test the `syslog.conf` keywords
+    #[test]
+    fn test_keywords() {
+        let facilities = [
+            ("kern", Facility::LOG_KERN),
+            ("user", Facility::LOG_USER),
+            ("mail", Facility::LOG_MAIL),
+            ("daemon", Facility::LOG_DAEMON),
+            ("auth", Facility::LOG_AUTH),
+            ("security", Facility::LOG_AUTH),
+            ("syslog", Facility::LOG_SYSLOG),
+            ("lpr", Facility::LOG_LPR),
+            ("news", Facility::LOG_NEWS),
+            ("uucp", Facility::LOG_UUCP),
+            ("cron", Facility::LOG_CRON),
+            ("authpriv", Facility::LOG_AUTHPRIV),
+            ("ftp", Facility::LOG_FTP),
+            ("ntp", Facility::LOG_NTP),
+            ("audit", Facility::LOG_AUDIT),
+            ("alert", Facility::LOG_ALERT),
+            ("clock", Facility::LOG_CLOCK),
+            ("local0", Facility::LOG_LOCAL0),
+            ("local1", Facility::LOG_LOCAL1),
+            ("local2", Facility::LOG_LOCAL2),
+            ("local3", Facility::LOG_LOCAL3),
+            ("local4", Facility::LOG_LOCAL4),
+            ("local5", Facility::LOG_LOCAL5),
+            ("local6", Facility::LOG_LOCAL6),
+            ("local7", Facility::LOG_LOCAL7),
+        ];
+        for (keyword, facility) in facilities {
+            assert_eq!(Facility::from_keyword(keyword), Some(facility));
+        }
+        assert_eq!(Facility::from_keyword("LOCAL7"), Some(Facility::LOG_LOCAL7));
+        assert_eq!(Facility::from_keyword("local8"), None);
+        assert_eq!(Facility::from_keyword("LOG_USER"), None);
+        assert_eq!(Facility::from_keyword(""), None);
+
+        let levels = [
+            ("emerg", Level::LOG_EMERG),
+            ("panic", Level::LOG_EMERG),
+            ("alert", Level::LOG_ALERT),
+            ("crit", Level::LOG_CRIT),
+            ("err", Level::LOG_ERR),
+            ("error", Level::LOG_ERR),
+            ("warning", Level::LOG_WARNING),
+            ("warn", Level::LOG_WARNING),
+            ("notice", Level::LOG_NOTICE),
+            ("info", Level::LOG_INFO),
+            ("debug", Level::LOG_DEBUG),
+        ];
+        for (keyword, level) in levels {
+            assert_eq!(Level::from_keyword(keyword), Some(level));
+        }
+        assert_eq!(Level::from_keyword("Warn"), Some(Level::LOG_WARNING));
+        assert_eq!(Level::from_keyword("trace"), None);
+        assert_eq!(Level::from_keyword("LOG_INFO"), None);
+    }
+    /// Round-trip facilities & levels through the `syslog` crate's types
+    #[cfg(feature = "syslog-compat")]
+    #[test]
+    fn test_syslog_compat() {
+        for facility in [
+            Facility::LOG_KERN,
+            Facility::LOG_USER,
+            Facility::LOG_MAIL,
+            Facility::LOG_DAEMON,
+            Facility::LOG_AUTH,
+            Facility::LOG_SYSLOG,
+            Facility::LOG_CRON,
+            Facility::LOG_AUTHPRIV,
+            Facility::LOG_FTP,
+            Facility::LOG_LOCAL0,
+            Facility::LOG_LOCAL7,
+        ] {
+            let theirs = syslog::Facility::try_from(facility).unwrap();
+            // Same `<syslog.h>` values on both sides
+            assert_eq!(theirs as u8, facility as u8);
+            assert_eq!(Facility::from(theirs), facility);
+        }
+        for code in 0..8 {
+            let level = Level::from_code(code).unwrap();
+            let theirs = syslog::Severity::from(level);
+            assert_eq!(theirs as u8, code);
+            assert_eq!(Level::from(theirs), level);
+        }
+    }
+    /// Facilities the `syslog` crate doesn't know are rejected
+    #[cfg(feature = "syslog-compat")]
+    #[test]
+    fn test_syslog_compat_unsupported() {
+        for facility in [
+            Facility::LOG_NTP,
+            Facility::LOG_AUDIT,
+            Facility::LOG_ALERT,
+            Facility::LOG_CLOCK,
+        ] {
+            assert_eq!(
+                syslog::Facility::try_from(facility).err(),
+                Some(UnsupportedFacility(facility))
+            );
+        }
+        assert_eq!(
+            UnsupportedFacility(Facility::LOG_NTP).to_string(),
+            "LOG_NTP has no counterpart in the syslog crate"
+        );
     }
 }
@@ -17,12 +17,105 @@
 //!
 //! This module defines the [`SyslogFormatter`] trait.
 
-use crate::facility::Level;
+use crate::{facility::Level, rfc5424::SdElement};
 
 use chrono::prelude::*;
+use tracing_core::Metadata;
 
 use std::ops::Deref;
 
+/// A source of timestamps for messages that don't come with one
+///
+/// By default this is just [`Utc::now`], but a fixed (or otherwise controlled) clock may be
+/// installed for the sake of reproducible output in tests.
+#[derive(Clone)]
+pub struct Clock(std::sync::Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>);
+
+impl Clock {
+    pub fn new<F: Fn() -> DateTime<Utc> + Send + Sync + 'static>(f: F) -> Clock {
+        Clock(std::sync::Arc::new(f))
+    }
+    /// A [`Clock`] that always reads `timestamp`
+    pub fn fixed(timestamp: DateTime<Utc>) -> Clock {
+        Clock::new(move || timestamp)
+    }
+    pub fn now(&self) -> DateTime<Utc> {
+        (self.0)()
+    }
+}
+
+impl std::default::Default for Clock {
+    fn default() -> Self {
+        Clock::new(Utc::now)
+    }
+}
+
+impl std::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+/// Per-message values that supplement (or override) a [`SyslogFormatter`]'s configuration
+///
+/// These are typically taken from reserved fields on the [`tracing`] event being logged (see
+/// [`Layer`]). Each is optional; formatters fall back to their configured values for those not
+/// given (or for those given values they can't use).
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+/// [`Layer`]: crate::layer::Layer
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Extras<'a> {
+    /// Overrides the APP-NAME (RFC 5424) or TAG (RFC 3164)
+    pub appname: Option<&'a str>,
+    /// The target of the outermost span enclosing the event being formatted
+    pub root_span_target: Option<&'a str>,
+    /// Per-message SD-ELEMENTs (RFC 5424 only)
+    pub structured_data: Option<&'a [SdElement]>,
+    /// The key-value fields of the event being formatted, if the formatter
+    /// [wants them](SyslogFormatter::wants_fields)
+    pub fields: Option<&'a [(&'a str, String)]>,
+    /// The key-value fields of the spans enclosing the event being formatted, if the formatter
+    /// [wants them](SyslogFormatter::wants_span_fields)
+    pub span_fields: Option<&'a [(&'a str, String)]>,
+    /// `span_fields`, already rendered as an RFC 5424 SD-ELEMENT (which [`Rfc5424`] will use in
+    /// preference to rendering them itself)
+    ///
+    /// [`Rfc5424`]: crate::rfc5424::Rfc5424
+    pub span_sd: Option<&'a [u8]>,
+}
+
+impl<'a> Extras<'a> {
+    pub fn new() -> Extras<'a> {
+        Extras::default()
+    }
+    pub fn appname(mut self, appname: &'a str) -> Self {
+        self.appname = Some(appname);
+        self
+    }
+    pub fn root_span_target(mut self, root_span_target: &'a str) -> Self {
+        self.root_span_target = Some(root_span_target);
+        self
+    }
+    pub fn structured_data(mut self, structured_data: &'a [SdElement]) -> Self {
+        self.structured_data = Some(structured_data);
+        self
+    }
+    pub fn fields(mut self, fields: &'a [(&'a str, String)]) -> Self {
+        self.fields = Some(fields);
+        self
+    }
+    pub fn span_fields(mut self, span_fields: &'a [(&'a str, String)]) -> Self {
+        self.span_fields = Some(span_fields);
+        self
+    }
+    pub fn span_sd(mut self, span_sd: &'a [u8]) -> Self {
+        self.span_sd = Some(span_sd);
+        self
+    }
+}
+
 /// Operations all formatters must support
 /// ======================================
 ///
@@ -39,7 +132,11 @@ use std::ops::Deref;
 /// 3. transporting that packet to your daemon
 ///
 /// [`SyslogFormatter`] implements step 2 in this process: given the [`Level`], a textual message
-/// field, and an optional timestamp, produce a compliant syslog packet.
+/// field, an optional timestamp, and (if available) the [`Metadata`] of the event or span that gave
+/// rise to the message, produce a compliant syslog packet. Implementations are free to ignore the
+/// metadata.
+///
+/// [`Metadata`]: tracing_core::Metadata
 ///
 /// # Design
 ///
@@ -54,12 +151,90 @@ use std::ops::Deref;
 ///
 /// [`Transport`]: crate::transport::Transport
 pub trait SyslogFormatter {
-    type Error: std::error::Error;
+    type Error: std::error::Error + Send + Sync + 'static;
     type Output: Deref<Target = [u8]>;
     fn format(
         &self,
         level: Level,
         msg: &str,
         timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
     ) -> std::result::Result<Self::Output, Self::Error>;
+    /// Format a message into a caller-supplied buffer
+    ///
+    /// The formatted message is appended to `buf`. Callers formatting at high frequency can re-use
+    /// one buffer (clearing it between messages) & so avoid allocating on each one. The default
+    /// implementation just calls [`format`](SyslogFormatter::format) & copies the result;
+    /// implementations are encouraged to do better.
+    fn format_into(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+    ) -> std::result::Result<(), Self::Error> {
+        buf.extend_from_slice(&self.format(level, msg, timestamp, meta)?);
+        Ok(())
+    }
+    /// Format a message, taking into account any per-message [`Extras`]
+    ///
+    /// The default implementation ignores `extras` & just calls
+    /// [`format`](SyslogFormatter::format).
+    fn format_with(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> std::result::Result<Self::Output, Self::Error> {
+        let _ = extras;
+        self.format(level, msg, timestamp, meta)
+    }
+    /// Format a message into a caller-supplied buffer, taking into account any per-message
+    /// [`Extras`]
+    fn format_into_with(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> std::result::Result<(), Self::Error> {
+        buf.extend_from_slice(&self.format_with(level, msg, timestamp, meta, extras)?);
+        Ok(())
+    }
+    /// Return true if this formatter makes use of the event's key-value fields (in [`Extras`])
+    ///
+    /// Collecting the fields costs an allocation or two per event, so callers needn't bother
+    /// unless this returns true. The default implementation returns false.
+    fn wants_fields(&self) -> bool {
+        false
+    }
+    /// Return true if this formatter makes use of the fields of the spans enclosing each event
+    /// (in [`Extras`])
+    ///
+    /// As with [`wants_fields`](SyslogFormatter::wants_fields), callers needn't bother collecting
+    /// them unless this returns true. The default implementation returns false.
+    fn wants_span_fields(&self) -> bool {
+        false
+    }
+    /// The APP-NAME (RFC 5424) or TAG (RFC 3164) this formatter writes into each message, if any
+    ///
+    /// This is for reporting purposes (see [`Layer::with_startup_marker`]); the default
+    /// implementation returns `None`.
+    ///
+    /// [`Layer::with_startup_marker`]: crate::layer::Layer::with_startup_marker
+    fn app_name(&self) -> Option<String> {
+        None
+    }
+    /// The process ID this formatter writes into each message, if any
+    ///
+    /// As with [`app_name`](SyslogFormatter::app_name), this is for reporting purposes; the default
+    /// implementation returns `None`.
+    fn proc_id(&self) -> Option<String> {
+        None
+    }
 }
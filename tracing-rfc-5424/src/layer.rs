@@ -23,19 +23,42 @@
 //! their own implementations.
 
 use crate::{
-    formatter::SyslogFormatter,
+    facility::Level,
+    formatter::{Extras, SyslogFormatter},
     rfc3164::Rfc3164,
-    rfc5424::Rfc5424,
-    tracing::{TracingFormatter, TrivialTracingFormatter},
-    transport::{Transport, UdpTransport},
+    rfc5424::{IntoSdElements, Rfc5424, SdElement},
+    tracing::{MessageEventVisitor, TracingFormatter, TrivialTracingFormatter},
+    transport::{DebugTransport, Transport, UdpTransport, WriterTransport},
 };
 
 #[cfg(unix)]
 use crate::transport::UnixSocket;
 
 use backtrace::Backtrace;
+use chrono::prelude::*;
 use tracing::Event;
-use tracing_subscriber::layer::Context;
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_subscriber::{layer::Context, registry::SpanRef};
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+type StdResult<T, E> = std::result::Result<T, E>;
+
+thread_local! {
+    // Scratch space into which messages are formatted, re-used from one message to the next on
+    // each thread; see `Layer::format_and_send()`
+    static SCRATCH: std::cell::RefCell<Vec<u8>> = const { std::cell::RefCell::new(Vec::new()) };
+    // SD-ELEMENTs attached to every event on this thread; see `with_structured_data()`
+    static SCOPED_SD: std::cell::RefCell<Vec<SdElement>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Scratch buffers that have grown beyond this (on account of some unusually large message) are
+/// released rather than held for the life of the thread
+const MAX_SCRATCH_CAPACITY: usize = 64 * 1024;
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                       module error type                                        //
@@ -44,14 +67,18 @@ use tracing_subscriber::layer::Context;
 /// module error type
 #[non_exhaustive]
 pub enum Error {
-    /// Formatting layer error
+    /// Formatting layer error; `source` is the [`SyslogFormatter`] or [`TracingFormatter`] error
+    /// (and may be recovered via [`downcast_ref`])
+    ///
+    /// [`downcast_ref`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref
     Format {
-        source: Box<dyn std::error::Error>,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         back: Backtrace,
     },
-    /// Transport layer error
+    /// Transport layer error; `source` is the [`Transport`]'s error (e.g. `source.downcast_ref::<
+    /// transport::Error>()` for the transports in this crate)
     Transport {
-        source: Box<dyn std::error::Error>,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         back: Backtrace,
     },
 }
@@ -85,14 +112,387 @@ impl std::fmt::Debug for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl std::convert::From<crate::rfc5424::Error> for Error {
+    fn from(err: crate::rfc5424::Error) -> Self {
+        Error::Format {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        }
+    }
+}
+
+impl std::convert::From<crate::rfc3164::Error> for Error {
+    fn from(err: crate::rfc3164::Error) -> Self {
+        Error::Format {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        }
+    }
+}
+
+impl std::convert::From<crate::tracing::Error> for Error {
+    fn from(err: crate::tracing::Error) -> Self {
+        Error::Format {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        }
+    }
+}
+
+impl std::convert::From<crate::transport::Error> for Error {
+    fn from(err: crate::transport::Error) -> Self {
+        Error::Transport {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    #[allow(unreachable_patterns)]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Format { source, .. } => Some(source.as_ref()),
+            Error::Transport { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                        reserved fields                                         //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Name of the reserved [`Event`] field from which the syslog timestamp will be taken, if present
+///
+/// The value must be an RFC [3339] timestamp (e.g. `info!(syslog.timestamp = "2001-09-09T01:46:40Z",
+/// "...")`). This allows replayed or buffered events to keep their original time. Events without
+/// this field (or with a value that can't be parsed) are stamped with the current time.
+///
+/// [3339]: https://datatracker.ietf.org/doc/html/rfc3339
+pub const TIMESTAMP_FIELD: &str = "syslog.timestamp";
+
+/// Name of the reserved [`Event`] field that overrides the APP-NAME (RFC 5424) or TAG (RFC 3164)
+///
+/// This permits, e.g., a multi-tenant service to log each request under the tenant's name:
+/// `info!(syslog.appname = "tenant-x", "...")`. A value that isn't a legal APP-NAME or TAG will
+/// be ignored in favor of the formatter's configured value.
+pub const APPNAME_FIELD: &str = "syslog.appname";
+
+/// Name of the reserved [`Event`] field carrying per-message structured data (RFC 5424 only)
+///
+/// This requires the `valuable` feature (& `--cfg tracing_unstable`). The value must be an
+/// [`SdElement`] or a list of them, recorded as a [`valuable`] value; e.g.
+/// `info!(syslog.sd = order.sd_elements().as_value(), "...")`. Values of any other type are
+/// ignored. Absent `valuable`, see [`with_structured_data`].
+///
+/// [`valuable`]: https://docs.rs/valuable
+pub const SD_FIELD: &str = "syslog.sd";
+
+/// Attach the structured data described by `value` to every [`Event`] logged on this thread while
+/// running `f` (RFC 5424 only)
+///
+/// The elements follow any standard or `tracing` metadata elements in each message. Calls may be
+/// nested, in which case the inner elements follow the outer.
+///
+/// ```rust
+/// use tracing_rfc_5424::{layer::with_structured_data, rfc5424::SdElement};
+///
+/// let order = SdElement::new("order@32473")
+///     .and_then(|elt| elt.with_param("id", "11"))
+///     .unwrap();
+/// with_structured_data(&order, || tracing::info!("order placed"));
+/// ```
+pub fn with_structured_data<T, R, F>(value: &T, f: F) -> R
+where
+    T: IntoSdElements + ?Sized,
+    F: FnOnce() -> R,
+{
+    // Pop our elements on the way out, even if `f` panics
+    struct Restore(usize);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_SD.with(|sd| sd.borrow_mut().truncate(self.0));
+        }
+    }
+    let _restore = Restore(SCOPED_SD.with(|sd| {
+        let mut sd = sd.borrow_mut();
+        let len = sd.len();
+        sd.extend(value.sd_elements());
+        len
+    }));
+    f()
+}
+
+/// Values extracted from the reserved `syslog.*` fields of an [`Event`]
+#[derive(Default)]
+struct ReservedFields {
+    timestamp: Option<DateTime<Utc>>,
+    appname: Option<String>,
+    structured_data: Option<Vec<SdElement>>,
+    // Also not reserved, but collected only if the syslog formatter asks for them
+    fields: Vec<(&'static str, String)>,
+    span_fields: Option<SharedSpanFields>,
+    // Not a field, strictly speaking, but it's extracted alongside them
+    root_span_target: Option<&'static str>,
+}
+
+impl ReservedFields {
+    fn from_event(event: &Event<'_>) -> ReservedFields {
+        let mut fields = ReservedFields::default();
+        // Most events won't carry any reserved fields; don't bother visiting them if so.
+        let names = event.metadata().fields();
+        if [TIMESTAMP_FIELD, APPNAME_FIELD, SD_FIELD]
+            .iter()
+            .any(|name| names.field(name).is_some())
+        {
+            event.record(&mut fields);
+        }
+        SCOPED_SD.with(|sd| {
+            let sd = sd.borrow();
+            if !sd.is_empty() {
+                let mut elements = sd.clone();
+                elements.extend(fields.structured_data.take().unwrap_or_default());
+                fields.structured_data = Some(elements);
+            }
+        });
+        fields
+    }
+    fn extras(&self) -> Extras<'_> {
+        let mut extras = Extras::new();
+        if let Some(appname) = &self.appname {
+            extras = extras.appname(appname);
+        }
+        if let Some(root_span_target) = self.root_span_target {
+            extras = extras.root_span_target(root_span_target);
+        }
+        if let Some(structured_data) = &self.structured_data {
+            extras = extras.structured_data(structured_data);
+        }
+        if !self.fields.is_empty() {
+            extras = extras.fields(&self.fields);
+        }
+        if let Some(span_fields) = self.span_fields.as_deref().filter(|f| !f.fields.is_empty()) {
+            extras = extras
+                .span_fields(&span_fields.fields)
+                .span_sd(&span_fields.sd);
+        }
+        extras
+    }
+}
+
+/// A list of span fields, in which each name appears at most once
+#[derive(Default)]
+struct SpanFields(Vec<(&'static str, String)>);
+
+impl SpanFields {
+    /// Merge `fields` into ours, replacing any earlier values of the same name
+    fn merge(&mut self, fields: &[(&'static str, String)]) {
+        for (name, value) in fields {
+            match self.0.iter_mut().find(|(n, _)| n == name) {
+                Some((_, v)) => *v = value.clone(),
+                None => self.0.push((name, value.clone())),
+            }
+        }
+    }
+    /// Gather the fields of `span` & all its ancestors, outermost first, by walking its scope
+    fn walk<S>(span: &SpanRef<'_, S>) -> SpanFields
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        SpanFields::walk_with_lineage(span).0
+    }
+    /// As [`walk`](SpanFields::walk), but also note the generation of each span along the way
+    fn walk_with_lineage<S>(span: &SpanRef<'_, S>) -> (SpanFields, Lineage)
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        let mut fields = SpanFields::default();
+        let mut lineage = Lineage::new();
+        for span in span.scope().from_root() {
+            if let Some(context) = span.extensions().get::<SpanContext>() {
+                // Read the generation *before* the fields, so that a record racing with us leaves
+                // the result stale rather than wrong.
+                lineage.push((
+                    context.generation.clone(),
+                    context.generation.load(Ordering::Acquire),
+                ));
+                fields.merge(&context.own.0);
+            }
+        }
+        (fields, lineage)
+    }
+}
+
+/// A span's resolved fields, shared between its cached [`SpanContext`] & the events within it
+struct ResolvedSpanFields {
+    fields: Vec<(&'static str, String)>,
+    /// `fields`, already rendered as an RFC 5424 SD-ELEMENT
+    sd: Vec<u8>,
+}
+
+impl ResolvedSpanFields {
+    fn new(fields: SpanFields) -> SharedSpanFields {
+        Arc::new(ResolvedSpanFields {
+            sd: crate::rfc5424::render_span_fields(&fields.0),
+            fields: fields.0,
+        })
+    }
+}
+
+type SharedSpanFields = Arc<ResolvedSpanFields>;
+
+/// The generations of some spans, outermost first, each paired with the value it had when last
+/// consulted
+type Lineage = Vec<(Arc<AtomicU64>, u64)>;
+
+/// What an event needs to know about the spans enclosing it, cached in the extensions of each span
+///
+/// Walking an event's scope on each event costs time (and allocations) proportional to the depth
+/// of span nesting. Instead, each span's context is computed from its parent's when the span is
+/// created, so that an event needs only consult its immediate parent. Each span carries a
+/// generation, bumped whenever fields are [recorded](tracing::Span::record) on it; the resolved
+/// fields note the generations of the span & its ancestors from which they were computed, and are
+/// re-computed from scratch only if one of those has since moved on. Recording on a span thus
+/// invalidates only its own context & those of its descendants.
+///
+/// There may be more than one syslog [`Layer`] in a subscriber, which share one [`SpanContext`]
+/// per span; fields are captured if any of them wants them.
+struct SpanContext {
+    /// The target of the outermost span in this span's scope
+    root_target: &'static str,
+    /// Whether span fields are being captured at all
+    captured: bool,
+    /// The fields recorded on this span itself
+    own: SpanFields,
+    /// Bumped whenever fields are recorded on this span
+    generation: Arc<AtomicU64>,
+    /// The fields of this span & all its ancestors, outermost first, inner values shadowing outer
+    resolved: SharedSpanFields,
+    /// The generations of this span & its ancestors from which `resolved` was computed
+    lineage: Lineage,
+}
+
+impl SpanContext {
+    /// Return `span`'s root target & (if captured) its resolved fields, re-computing the latter if
+    /// they're stale
+    fn for_span<S>(span: &SpanRef<'_, S>) -> (&'static str, Option<SharedSpanFields>)
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        let root_target = match span.extensions().get::<SpanContext>() {
+            Some(context) if !context.captured => return (context.root_target, None),
+            Some(context) if context.is_fresh() => {
+                return (context.root_target, Some(context.resolved.clone()))
+            }
+            Some(context) => context.root_target,
+            // Created before any syslog `Layer` was around to see it
+            None => return (SpanContext::walk_root_target(span), None),
+        };
+        let (fields, lineage) = SpanFields::walk_with_lineage(span);
+        let resolved = ResolvedSpanFields::new(fields);
+        if let Some(context) = span.extensions_mut().get_mut::<SpanContext>() {
+            context.resolved = resolved.clone();
+            context.lineage = lineage;
+        }
+        (root_target, Some(resolved))
+    }
+    /// Return true if no span from which our resolved fields were computed has since changed
+    fn is_fresh(&self) -> bool {
+        self.lineage
+            .iter()
+            .all(|(generation, seen)| generation.load(Ordering::Acquire) == *seen)
+    }
+    /// Find `span`'s root target by walking its scope
+    fn walk_root_target<S>(span: &SpanRef<'_, S>) -> &'static str
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        // The scope iterates from the span itself outward
+        span.scope()
+            .last()
+            .map(|root| root.metadata().target())
+            .unwrap_or_else(|| span.metadata().target())
+    }
+}
+
+impl tracing::field::Visit for ReservedFields {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        match field.name() {
+            TIMESTAMP_FIELD => {
+                self.timestamp = DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|ts| ts.with_timezone(&Utc))
+            }
+            APPNAME_FIELD => self.appname = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        // Values recorded via `%` arrive here
+        self.record_str(field, &format!("{:?}", value))
+    }
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    fn record_value(&mut self, field: &tracing::field::Field, value: valuable::Value<'_>) {
+        if field.name() == SD_FIELD {
+            self.structured_data = Some(crate::rfc5424::sd_elements_from_value(value));
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                          struct Layer                                          //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Why a [`Layer`] didn't send a message for some event or span; see [`Layer::with_on_drop`]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// The event had no "message" field (& the [`TracingFormatter`] wasn't configured to
+    /// tolerate that)-- often a sign of a bug in the instrumentation
+    NoMessage,
+    /// The event or span was less severe than the [`Layer`]'s minimum level
+    BelowMinLevel,
+    /// The [`TracingFormatter`] or [`SyslogFormatter`] failed
+    FormatError,
+    /// The [`Transport`] failed
+    TransportError,
+}
+
+impl DropReason {
+    /// Classify the failure to send an event or span
+    fn from_error(err: &Error) -> DropReason {
+        match err {
+            Error::Format { source, .. } => match source.downcast_ref::<crate::tracing::Error>() {
+                Some(crate::tracing::Error::NoMessageField { .. }) => DropReason::NoMessage,
+                _ => DropReason::FormatError,
+            },
+            _ => DropReason::TransportError,
+        }
+    }
+}
+
+type DropHook = Box<dyn Fn(&tracing::Metadata<'_>, DropReason) + Send + Sync>;
+
+/// Name of the environment variable that disables syslog output at runtime
+///
+/// If this is set to anything other than the empty string or `0` when a [`Layer`] is
+/// constructed, that [`Layer`] will send nothing: events & spans are dropped before formatting,
+/// and [`send_record`](Layer::send_record) does nothing. The variable is consulted only at
+/// construction; changing it afterward has no effect on existing [`Layer`]s. This is handy in
+/// local development, where one may not want to spam the host's syslog.
+pub const DISABLE_ENV_VAR: &str = "SYSLOG_TRACING_DISABLE";
+
+/// Return true if [`DISABLE_ENV_VAR`] asks us to send nothing
+fn disabled_by_env() -> bool {
+    match std::env::var_os(DISABLE_ENV_VAR) {
+        Some(val) => !val.is_empty() && val != "0",
+        None => false,
+    }
+}
+
 /// A [`tracing-subscriber`]-compliant [`Layer`] implementation that will send [`Event`]s &
 /// [`Span`]s to a syslog daemon.
 ///
@@ -106,12 +506,69 @@ where
 {
     syslog_formatter: F1,
     tracing_formatter: F2,
-    transport: T,
+    // Behind a lock so that the destination can be changed in place; see `set_transport()`
+    transport: std::sync::RwLock<T>,
+    min_level: Option<tracing::Level>,
+    // Set if `DISABLE_ENV_VAR` was set at construction
+    disabled: bool,
+    on_drop: Option<DropHook>,
+    // Set once our transport has declined `send_slice()`, so we don't keep formatting messages
+    // twice; see `format_and_send()`
+    slices_unsupported: std::sync::atomic::AtomicBool,
+    // Delivery statistics; see `messages_sent()` & friends
+    messages_sent: std::sync::atomic::AtomicU64,
+    messages_failed: std::sync::atomic::AtomicU64,
+    bytes_sent: std::sync::atomic::AtomicU64,
+    // Cleared only for benchmarking; see `without_span_cache()`
+    span_cache: bool,
     // I need the Subscriber implementation type as a type parameter to transmit it to the
     // TracingFormatter trait. 👇 gets the compiler to shut-up about unused type parameters.
     subscriber_type: std::marker::PhantomData<S>,
 }
 
+/// The [`Layer`] produced by [`udp_layer`]: RFC 5424 over UDP, via the "trivial" formatter
+pub type DefaultUdpLayer<S> = Layer<S, Rfc5424, TrivialTracingFormatter, UdpTransport>;
+
+/// The [`Layer`] produced by [`unix_layer`]: RFC 3164 over a Unix socket, via the "trivial"
+/// formatter
+#[cfg(unix)]
+pub type DefaultUnixLayer<S> = Layer<S, Rfc3164, TrivialTracingFormatter, UnixSocket>;
+
+/// Attempt to construct a [`Layer`] that will send RFC5424-compliant syslog messages via UDP to
+/// port 514 on localhost
+///
+/// This is just [`DefaultUdpLayer::try_default`], minus the type annotations; the subscriber
+/// type is inferred from context:
+///
+/// ```rust
+/// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+/// let subscriber = Registry::default().with(tracing_rfc_5424::udp_layer().unwrap());
+/// ```
+pub fn udp_layer<S>() -> Result<DefaultUdpLayer<S>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    DefaultUdpLayer::try_default()
+}
+
+/// Attempt to construct a [`Layer`] that will send RFC3164-compliant syslog messages via datagrams
+/// to the Unix socket at `/dev/log` on localhost
+///
+/// This is just [`DefaultUnixLayer::try_default`], minus the type annotations; the subscriber
+/// type is inferred from context:
+///
+/// ```no_run
+/// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+/// let subscriber = Registry::default().with(tracing_rfc_5424::unix_layer().unwrap());
+/// ```
+#[cfg(unix)]
+pub fn unix_layer<S>() -> Result<DefaultUnixLayer<S>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    DefaultUnixLayer::try_default()
+}
+
 /// A [`Layer`] implementation with the following characteristics:
 ///
 /// - Uses the "trivial" formatter for mapping from Tracing evengs to messages
@@ -130,15 +587,11 @@ where
     /// Attempt to construct a [`Layer`] that will send RFC5424-compliant syslog messages via UDP to
     /// port 514 on localhost
     pub fn try_default() -> Result<Self> {
-        Ok(Layer {
-            syslog_formatter: Rfc5424::default(),
-            tracing_formatter: TrivialTracingFormatter::default(),
-            transport: UdpTransport::local().map_err(|err| Error::Transport {
-                source: Box::new(err),
-                back: Backtrace::new(),
-            })?,
-            subscriber_type: std::marker::PhantomData,
-        })
+        Ok(Layer::from_parts(
+            Rfc5424::default(),
+            TrivialTracingFormatter::default(),
+            UdpTransport::local()?,
+        ))
     }
 }
 
@@ -161,18 +614,58 @@ where
     /// Attempt to construct a Layer that will send RFC3164-compliant syslog messages via datagrams
     /// to the Unix socket at `/dev/log` on localhost
     pub fn try_default() -> Result<Self> {
-        Ok(Layer {
-            syslog_formatter: Rfc3164::try_default().map_err(|err| Error::Format {
-                source: Box::new(err),
-                back: Backtrace::new(),
-            })?,
-            tracing_formatter: TrivialTracingFormatter::default(),
-            transport: UnixSocket::try_default().map_err(|err| Error::Transport {
+        Ok(Layer::from_parts(
+            Rfc3164::try_default()?,
+            TrivialTracingFormatter::default(),
+            UnixSocket::try_default()?,
+        ))
+    }
+}
+
+impl<S, F1: SyslogFormatter, F2: TracingFormatter<S>> Layer<S, F1, F2, UdpTransport>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    /// Wait up to `timeout` for the kernel to transmit any messages still queued on our socket;
+    /// see [`UdpTransport::drain`]
+    pub fn drain(&self, timeout: std::time::Duration) -> Result<bool> {
+        self.transport()
+            .drain(timeout)
+            .map_err(|err| Error::Transport {
                 source: Box::new(err),
                 back: Backtrace::new(),
-            })?,
-            subscriber_type: std::marker::PhantomData,
-        })
+            })
+    }
+}
+
+/// A [`Layer`] implementation with the following characteristics:
+///
+/// - Uses the "trivial" formatter for mapping from Tracing events to messages
+/// - Speaks RFC 5424 for syslog
+/// - Sends nothing; the resulting messages are written to stderr instead (see [`DebugTransport`])
+impl<S> Layer<S, Rfc5424, TrivialTracingFormatter, DebugTransport>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    /// Construct a [`Layer`] that writes the RFC5424-compliant syslog messages it would have sent
+    /// to stderr, each prefixed with `[would-send]`
+    ///
+    /// This is handy for checking one's configuration without a syslog daemon running:
+    ///
+    /// ```rust
+    /// use tracing_rfc_5424::layer::Layer;
+    /// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+    /// let subscriber = Registry::default().with(Layer::dry_run());
+    /// tracing::subscriber::with_default(subscriber, || {
+    ///     tracing::info!("Hello, world!");
+    /// });
+    /// ```
+    pub fn dry_run() -> Self {
+        Layer::from_parts(
+            Rfc5424::default(),
+            TrivialTracingFormatter::default(),
+            WriterTransport::debug(),
+        )
     }
 }
 
@@ -182,12 +675,7 @@ where
 {
     /// construct Layer with custom inners
     pub fn new(syslog_formatter: Rfc5424, tracing_formatter: TF, transport: T) -> Self {
-        Layer {
-            syslog_formatter,
-            tracing_formatter,
-            transport,
-            subscriber_type: std::marker::PhantomData,
-        }
+        Layer::from_parts(syslog_formatter, tracing_formatter, transport)
     }
 }
 
@@ -208,23 +696,358 @@ where
 {
     /// Construct a Layer that will send RFC5424-compliant messages via transport `transport`
     pub fn with_transport(transport: T) -> Self {
-        Layer {
-            syslog_formatter: Rfc5424::default(),
-            tracing_formatter: TrivialTracingFormatter::default(),
+        Layer::from_parts(
+            Rfc5424::default(),
+            TrivialTracingFormatter::default(),
             transport,
-            subscriber_type: std::marker::PhantomData,
-        }
+        )
     }
 
     /// Construct a Layer that will send RFC5424-compliant messages via transport `transport`
     pub fn with_transport_and_syslog_formatter(transport: T, formatter: Rfc5424) -> Self {
+        Layer::from_parts(formatter, TrivialTracingFormatter::default(), transport)
+    }
+}
+
+impl<S, F1: SyslogFormatter, F2: TracingFormatter<S>, T: Transport<F1>> Layer<S, F1, F2, T>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    /// The one place in which a [`Layer`] is actually assembled; all the public constructors go
+    /// through here
+    fn from_parts(syslog_formatter: F1, tracing_formatter: F2, transport: T) -> Self {
         Layer {
-            syslog_formatter: formatter,
-            tracing_formatter: TrivialTracingFormatter::default(),
-            transport,
+            syslog_formatter,
+            tracing_formatter,
+            transport: std::sync::RwLock::new(transport),
+            min_level: None,
+            disabled: disabled_by_env(),
+            on_drop: None,
+            slices_unsupported: std::sync::atomic::AtomicBool::new(false),
+            messages_sent: std::sync::atomic::AtomicU64::new(0),
+            messages_failed: std::sync::atomic::AtomicU64::new(0),
+            bytes_sent: std::sync::atomic::AtomicU64::new(0),
+            span_cache: true,
             subscriber_type: std::marker::PhantomData,
         }
     }
+    /// Only send [`Event`]s & [`Span`]s at `level` or above (i.e. at least as severe) to syslog
+    ///
+    /// Everything below `level` is discarded before any formatting takes place. Unlike a filter
+    /// applied via [`with_filter`], this doesn't affect any other layers in the subscriber (e.g.
+    /// one may send `warn!` & above to syslog while still printing `debug!` to stdout).
+    ///
+    /// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
+    /// [`Span`]: https://docs.rs/tracing/0.1.35/tracing/struct.Span.html
+    /// [`with_filter`]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/layer/trait.Layer.html#method.with_filter
+    pub fn with_min_level(mut self, level: tracing::Level) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+    /// Invoke `on_drop` whenever an event or span is dropped, with the reason
+    ///
+    /// This permits auditing one's instrumentation: an event dropped with
+    /// [`DropReason::NoMessage`], for instance, usually indicates a logging bug. It's not invoked
+    /// for records sent via [`send_record`](Layer::send_record) (whose failures are returned to
+    /// the caller), nor when output has been disabled via [`DISABLE_ENV_VAR`].
+    pub fn with_on_drop<H>(mut self, on_drop: H) -> Self
+    where
+        H: Fn(&tracing::Metadata<'_>, DropReason) + Send + Sync + 'static,
+    {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+    /// If `marker` is true, immediately send a single message announcing that logging is online
+    ///
+    /// The message ("syslog-tracing initialized; appname=... pid=...", naming the APP-NAME or TAG
+    /// & process ID configured on our [`SyslogFormatter`], or "-" where it has none) is sent at
+    /// INFO level, with no `tracing` metadata, through this [`Layer`]'s formatter & transport; its
+    /// purpose is to confirm that syslog is actually wired-up at startup, rather than upon the
+    /// first real event. [`with_min_level`](Layer::with_min_level) isn't applied to it (though
+    /// [`DISABLE_ENV_VAR`] is).
+    ///
+    /// Accordingly, a failure to format or send the marker is returned as an error, failing the
+    /// construction of the [`Layer`]: a misconfigured transport surfaces here, where the
+    /// application can decide what to do about it, rather than as events silently dropped later.
+    /// Since the marker goes out at once, this should be the last setter called:
+    ///
+    /// ```rust
+    /// use tracing_rfc_5424::{layer::Layer, transport::VecTransport};
+    /// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+    ///
+    /// let layer = Layer::with_transport(VecTransport::new())
+    ///     .with_startup_marker(true)
+    ///     .unwrap();
+    /// let subscriber = Registry::default().with(layer);
+    /// ```
+    pub fn with_startup_marker(self, marker: bool) -> Result<Self> {
+        if marker && !self.disabled {
+            self.send_startup_marker()?;
+        }
+        Ok(self)
+    }
+    /// Send the startup marker; see [`with_startup_marker`](Layer::with_startup_marker)
+    fn send_startup_marker(&self) -> Result<()> {
+        let msg = format!(
+            "syslog-tracing initialized; appname={} pid={}",
+            self.syslog_formatter
+                .app_name()
+                .unwrap_or_else(|| "-".to_string()),
+            self.syslog_formatter
+                .proc_id()
+                .unwrap_or_else(|| "-".to_string())
+        );
+        self.format_and_send(Level::LOG_INFO, &msg, None, None, &Extras::default())
+    }
+    /// Walk each event's scope, rather than consulting the context cached on its parent span
+    ///
+    /// This is only useful for benchmarking the span cache (see `benches/span_scope.rs`).
+    #[cfg(any(test, feature = "test-util"))]
+    #[doc(hidden)]
+    pub fn without_span_cache(mut self) -> Self {
+        self.span_cache = false;
+        self
+    }
+    /// Borrow our [`SyslogFormatter`]
+    ///
+    /// This permits adjusting formatters that support changes at runtime, such as
+    /// [`Rfc5424::set_facility`].
+    pub fn syslog_formatter(&self) -> &F1 {
+        &self.syslog_formatter
+    }
+    /// Return true if events & spans described by `meta` should be sent
+    fn wants(&self, meta: &tracing::Metadata<'_>) -> bool {
+        if self.disabled {
+            return false;
+        }
+        // `tracing::Level`s compare by verbosity: TRACE > DEBUG > ... > ERROR
+        match self.min_level {
+            Some(min) if *meta.level() > min => {
+                self.dropped(Some(meta), DropReason::BelowMinLevel);
+                false
+            }
+            _ => true,
+        }
+    }
+    /// Report that the event or span described by `meta` wasn't sent
+    fn dropped(&self, meta: Option<&tracing::Metadata<'_>>, reason: DropReason) {
+        if let (Some(on_drop), Some(meta)) = (&self.on_drop, meta) {
+            on_drop(meta, reason);
+        }
+    }
+    /// Replace this [`Layer`]'s [`Transport`], returning the old one
+    ///
+    /// This permits pointing the [`Layer`] at a new collector at runtime, without having to
+    /// rebuild (or even have access to the concrete type of) the subscriber of which it's a
+    /// part-- for instance via a [`reload::Handle`]:
+    /// `handle.modify(|layer| { layer.set_transport(new_transport); })`. Messages already sent on
+    /// the old transport are not re-sent, and the old transport is *not* flushed; that's up to the
+    /// caller.
+    ///
+    /// This is safe to call while other threads are logging: the transport sits behind a
+    /// [`RwLock`], so sends in progress will complete on the old transport & subsequent ones will
+    /// go to the new.
+    ///
+    /// [`reload::Handle`]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/reload/struct.Handle.html
+    /// [`RwLock`]: std::sync::RwLock
+    pub fn set_transport(&self, transport: T) -> T {
+        let mut guard = self
+            .transport
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.slices_unsupported
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        std::mem::replace(&mut *guard, transport)
+    }
+    /// Borrow our [`Transport`]
+    fn transport(&self) -> std::sync::RwLockReadGuard<'_, T> {
+        // A panic while holding the lock can't have left the transport in an inconsistent state
+        // (we only ever replace it wholesale), so just carry on.
+        self.transport
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// Flush any messages buffered by our [`Transport`]
+    ///
+    /// Errors encountered while sending individual messages are swallowed (there's not much else a
+    /// [`Layer`] can do with them), so short-lived processes that want some assurance that their
+    /// last few messages have been handed-off before they exit can call this. It will also be
+    /// invoked when the [`Layer`] is dropped.
+    pub fn flush(&self) -> Result<()> {
+        self.transport().flush().map_err(|err| Error::Transport {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        })
+    }
+    /// The number of messages successfully handed-off to our [`Transport`] so far
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+    /// The number of messages that couldn't be formatted, or that our [`Transport`] failed to
+    /// send, so far
+    pub fn messages_failed(&self) -> u64 {
+        self.messages_failed
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+    /// The total size, in bytes, of the messages successfully handed-off to our [`Transport`] so
+    /// far
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    /// Format & send a single, pre-captured record
+    ///
+    /// This performs the same formatting & transport steps as the [`Layer`] would for an event, but
+    /// without any involvement on the part of `tracing`: the message, level, timestamp & metadata
+    /// are all supplied by the caller. It's useful for replaying events captured earlier (with their
+    /// original timestamps), or for sending messages from some other source through the same
+    /// pipeline. Note that [`with_min_level`](Layer::with_min_level) is *not* applied here
+    /// (though [`DISABLE_ENV_VAR`] is).
+    pub fn send_record(
+        &self,
+        level: Level,
+        msg: &str,
+        ts: DateTime<Utc>,
+        meta: &tracing::Metadata<'_>,
+    ) -> Result<()> {
+        if self.disabled {
+            return Ok(());
+        }
+        self.format_and_send(level, msg, Some(ts), Some(meta), &Extras::default())
+    }
+    /// Format a message & send it on our [`Transport`], updating our delivery statistics
+    fn format_and_send(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing::Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<()> {
+        match self.try_format_and_send(level, msg, timestamp, meta, extras) {
+            Ok(n) => {
+                self.messages_sent.fetch_add(1, Ordering::Relaxed);
+                self.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(err) => {
+                self.messages_failed.fetch_add(1, Ordering::Relaxed);
+                Err(err)
+            }
+        }
+    }
+    /// Format a message & send it on our [`Transport`]; return the number of bytes sent
+    ///
+    /// If the transport can send from a slice (see [`Transport::send_slice`]), the message is
+    /// formatted into a thread-local buffer that's re-used from one message to the next, sparing
+    /// an allocation per message. Otherwise, we fall back to [`SyslogFormatter::format_with`] &
+    /// [`Transport::send`].
+    fn try_format_and_send(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing::Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<usize> {
+        let format_err = |err: F1::Error| Error::Format {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        };
+        let transport_err = |err: T::Error| Error::Transport {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        };
+        let transport = self.transport();
+        if !self.slices_unsupported.load(Ordering::Relaxed) {
+            // `None` means the scratch buffer wasn't available; `Some(None)` that the transport
+            // declined to send from a slice.
+            let sent = SCRATCH
+                .try_with(|scratch| {
+                    // If we've been re-entered (by a transport that logs, say), the buffer is
+                    // already in use; just take the allocating path below.
+                    let mut buf = scratch.try_borrow_mut().ok()?;
+                    buf.clear();
+                    let rsp = match self
+                        .syslog_formatter
+                        .format_into_with(&mut buf, level, msg, timestamp, meta, extras)
+                    {
+                        Ok(()) => transport
+                            .send_slice(&buf)
+                            .map(|rsp| rsp.map(|_| buf.len()).map_err(transport_err)),
+                        Err(err) => Some(Err(format_err(err))),
+                    };
+                    if buf.capacity() > MAX_SCRATCH_CAPACITY {
+                        *buf = Vec::new();
+                    }
+                    Some(rsp)
+                })
+                .ok()
+                .flatten();
+            match sent {
+                Some(Some(rsp)) => return rsp,
+                Some(None) => self.slices_unsupported.store(true, Ordering::Relaxed),
+                None => (),
+            }
+        }
+        let buf = self
+            .syslog_formatter
+            .format_with(level, msg, timestamp, meta, extras)
+            .map_err(format_err)?;
+        let n = buf.len();
+        transport.send(buf).map_err(transport_err)?;
+        Ok(n)
+    }
+}
+
+impl<S, F1: SyslogFormatter, F2: TracingFormatter<S>, T: Transport<F1>> Drop for Layer<S, F1, F2, T>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fn drop(&mut self) {
+        // There's no one to whom we can report a failure at this point.
+        let _ = self.transport().flush();
+    }
+}
+
+impl<S, F1, F2, T> Layer<S, F1, F2, T>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    F1: SyslogFormatter + 'static,
+    F2: TracingFormatter<S> + 'static,
+    T: Transport<F1> + 'static,
+{
+    /// Report a failure to send the event or span described by `meta`
+    fn failed(&self, err: &Error, meta: Option<&tracing::Metadata<'_>>) {
+        self.dropped(meta, DropReason::from_error(err));
+        ::tracing::error!("tracing-subscriber failed");
+    }
+    /// Format the result of one of our [`TracingFormatter`]'s callbacks as a syslog message &
+    /// send it on our [`Transport`] (if the callback produced a message at all).
+    fn dispatch(
+        &self,
+        x: StdResult<Option<(String, Level)>, F2::Error>,
+        meta: Option<&tracing::Metadata<'_>>,
+        reserved: &ReservedFields,
+    ) -> Result<()> {
+        x.map_err(|err| {
+            // `format_and_send()` counts its own failures, but we never got that far.
+            self.messages_failed
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Error::Format {
+                source: Box::new(err),
+                back: Backtrace::new(),
+            }
+        }) // 👈:=> StdResult<Option<(String, Level)>, Error>
+        .and_then(|x| {
+            // x is an Option<(String, Level)>
+            if let Some((msg, level)) = x {
+                self.format_and_send(level, &msg, reserved.timestamp, meta, &reserved.extras())
+            } else {
+                Ok(())
+            }
+        })
+    }
 }
 
 /// This is the Big Tuna-- the [`Layer`] implementation.
@@ -238,36 +1061,132 @@ where
     T: Transport<F1> + 'static,
 {
     fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
-        self.tracing_formatter
-            .on_event(event, ctx) // :=> StdResult<Option<(String, Level)>, <F1 as SyslogFormatter>::Error>
-            .map_err(|err| Error::Format {
-                source: Box::new(err),
-                back: Backtrace::new(),
-            }) // 👈:=> StdResult<Option<(String, Level)>, Error>
-            .and_then(|x| {
-                // x is an Option<(String, Level)>
-                if let Some((msg, level)) = x {
-                    Ok(self
-                        .transport
-                        .send(
-                            self.syslog_formatter
-                                .format(level, &msg, None)
-                                .map_err(|err| Error::Format {
-                                    source: Box::new(err),
-                                    back: Backtrace::new(),
-                                })?,
-                        )
-                        .map_err(|err| Error::Transport {
-                            source: Box::new(err),
-                            back: Backtrace::new(),
-                        })?)
-                } else {
-                    Ok(())
+        if !self.wants(event.metadata()) {
+            return;
+        }
+        let mut reserved = ReservedFields::from_event(event);
+        if let Some(span) = ctx.event_span(event) {
+            if self.span_cache {
+                let (root_target, span_fields) = SpanContext::for_span(&span);
+                reserved.root_span_target = Some(root_target);
+                reserved.span_fields = span_fields;
+            } else {
+                reserved.root_span_target = Some(SpanContext::walk_root_target(&span));
+                if self.syslog_formatter.wants_span_fields() {
+                    reserved.span_fields = Some(ResolvedSpanFields::new(SpanFields::walk(&span)));
                 }
+            }
+        }
+        let rsp = if self.syslog_formatter.wants_fields() {
+            // Collect the fields in the same pass over the event that formats it
+            self.tracing_formatter
+                .on_event_with_fields(event, ctx)
+                .map(|(rsp, fields)| {
+                    reserved.fields = fields;
+                    rsp
+                })
+        } else {
+            self.tracing_formatter.on_event(event, ctx)
+        };
+        let meta = Some(event.metadata());
+        self.dispatch(rsp, meta, &reserved)
+            .unwrap_or_else(|err| self.failed(&err, meta))
+    }
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if self.disabled {
+            return;
+        }
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let wants_fields = self.syslog_formatter.wants_span_fields();
+        // Another syslog `Layer` in this subscriber may have gotten here first
+        if matches!(span.extensions().get::<SpanContext>(), Some(context) if context.captured || !wants_fields)
+        {
+            return;
+        }
+        let parent = span.parent().and_then(|parent| {
+            parent.extensions().get::<SpanContext>().map(|context| {
+                (
+                    context.root_target,
+                    Some((context.resolved.clone(), context.lineage.clone()))
+                        .filter(|_| context.captured),
+                )
             })
-            .unwrap_or_else(|_err| {
-                ::tracing::error!("tracing-subscriber failed");
-            })
+        });
+        let root_target = match &parent {
+            Some((root_target, _)) => root_target,
+            None => SpanContext::walk_root_target(&span),
+        };
+        let generation = Arc::new(AtomicU64::new(0));
+        let mut own = SpanFields::default();
+        let mut resolved = SpanFields::default();
+        let mut lineage = Lineage::new();
+        if wants_fields {
+            let mut visitor = MessageEventVisitor::new();
+            attrs.record(&mut visitor);
+            own.merge(visitor.fields());
+            match parent {
+                // If our parent's fields are stale, its lineage will say so, & so will ours.
+                Some((_, Some((fields, parent_lineage)))) => {
+                    resolved.merge(&fields.fields);
+                    lineage = parent_lineage;
+                }
+                // An ancestor may have been created before we were around, so walk its scope.
+                _ => {
+                    if let Some(parent) = span.parent() {
+                        (resolved, lineage) = SpanFields::walk_with_lineage(&parent);
+                    }
+                }
+            }
+            resolved.merge(&own.0);
+            lineage.push((generation.clone(), 0));
+        }
+        span.extensions_mut().replace(SpanContext {
+            root_target,
+            captured: wants_fields,
+            own,
+            generation,
+            resolved: ResolvedSpanFields::new(resolved),
+            lineage,
+        });
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(context) = span.extensions_mut().get_mut::<SpanContext>() {
+                if context.captured {
+                    let mut visitor = MessageEventVisitor::new();
+                    values.record(&mut visitor);
+                    context.own.merge(visitor.fields());
+                    context.generation.fetch_add(1, Ordering::AcqRel);
+                }
+            }
+        }
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let meta = ctx.metadata(id);
+        if matches!(meta, Some(meta) if !self.wants(meta)) {
+            return;
+        }
+        self.dispatch(
+            self.tracing_formatter.on_enter(id, ctx),
+            meta,
+            &ReservedFields::default(),
+        )
+        .unwrap_or_else(|err| self.failed(&err, meta))
+    }
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let meta = ctx.metadata(id);
+        if matches!(meta, Some(meta) if !self.wants(meta)) {
+            return;
+        }
+        self.dispatch(
+            self.tracing_formatter.on_exit(id, ctx),
+            meta,
+            &ReservedFields::default(),
+        )
+        .unwrap_or_else(|err| self.failed(&err, meta))
     }
 }
 
@@ -276,33 +1195,11 @@ mod smoke {
 
     use super::*;
 
-    use crate::facility::Level;
-
-    use tracing::Callsite;
-
-    // I confess, `tracing` internals are a bit opaque to me, yet. In addition, they are explicitly
-    // unstable. For that reason, I don't want to do too much work, here; just enough to easily give
-    // myself Events against which I can test.
+    use crate::{tracing::SpanLifecycleFormatter, transport::VecTransport};
 
-    struct TestCallsite {
-        metadata: &'static tracing::Metadata<'static>,
-    }
-    impl tracing_core::callsite::Callsite for TestCallsite {
-        fn set_interest(&self, _interest: tracing_core::subscriber::Interest) {}
-        fn metadata(&self) -> &tracing::Metadata<'static> {
-            self.metadata
-        }
-    }
-    // I *wish* I could deal in TestCallsite instances of arbitrary lifetime, but Identifier
-    // needs a reference with 'static duration.
-    impl TestCallsite {
-        pub const fn new(metadata: &'static tracing::Metadata<'static>) -> TestCallsite {
-            TestCallsite { metadata }
-        }
-    }
+    use tracing_subscriber::layer::SubscriberExt;
 
     #[test]
-    #[allow(clippy::redundant_closure_call)]
     fn test_rfc_5424_impl() {
         // Just exercise `default()`; be sure it compiles & returns something sane.
         let _f = Rfc5424::default();
@@ -318,68 +1215,47 @@ mod smoke {
 
         let _fmtr = TrivialTracingFormatter::default();
 
-        // Non-macro replication of the logic of `event!()`-- will need to wrap this up in a macro
-        // at some point.
-        static CALLSITE: TestCallsite = {
-            static METADATA: tracing::Metadata = tracing::Metadata::new(
-                "test event metadata",
-                "test-target",
-                tracing::Level::INFO,
-                Some(file!()),
-                Some(line!()),
-                Some(module_path!()),
-                // fieldset,
-                tracing::field::FieldSet::new(
-                    &["message"],
-                    tracing_core::callsite::Identifier(&CALLSITE),
-                ),
-                tracing_core::metadata::Kind::EVENT,
-            );
-            TestCallsite::new(&METADATA)
-        };
-
-        // Would love to wrap this up into a utility function, but the Event takes the ValueSet _by
-        // reference_, so we need a way to keep it alive for the lifetime of the Event. Might be
-        // time to wrap this up in a macro.
-        (|value_set: ::tracing::field::ValueSet| {
-            let _event = Event::new(CALLSITE.metadata(), &value_set);
-            let rsp: Vec<u8> = f
-                .format(
-                    Level::LOG_INFO,
-                    "Hello, world!",
-                    Some(std::time::UNIX_EPOCH.into()),
-                )
-                .unwrap();
+        crate::test_event!(
+            tracing::Level::INFO,
+            "test-target",
+            "Hello, world!",
+            |_event| {
+                let rsp: Vec<u8> = f
+                    .format(
+                        Level::LOG_INFO,
+                        "Hello, world!",
+                        Some(std::time::UNIX_EPOCH.into()),
+                        None,
+                    )
+                    .unwrap();
 
-            assert_eq!(
-                std::str::from_utf8(&rsp).unwrap(),
-                "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello, world!"
-            );
-        })(tracing::valueset!(
-            CALLSITE.metadata().fields(),
-            "{}",
-            "Hello, world!"
-        ));
+                assert_eq!(
+                    std::str::from_utf8(&rsp).unwrap(),
+                    "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello, world!"
+                );
+            }
+        );
 
-        (|value_set: ::tracing::field::ValueSet| {
-            let _event = Event::new(CALLSITE.metadata(), &value_set);
-            let rsp: Vec<u8> = f
-                .format(
-                    Level::LOG_INFO,
-                    "Hello, 世界!",
-                    Some(std::time::UNIX_EPOCH.into()),
-                )
-                .unwrap();
+        crate::test_event!(
+            tracing::Level::INFO,
+            "test-target",
+            "Hello, 世界!",
+            |_event| {
+                let rsp: Vec<u8> = f
+                    .format(
+                        Level::LOG_INFO,
+                        "Hello, 世界!",
+                        Some(std::time::UNIX_EPOCH.into()),
+                        None,
+                    )
+                    .unwrap();
 
-            assert_eq!(
-                std::str::from_utf8(&rsp).unwrap(),
-                "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello, 世界!"
-            );
-        })(tracing::valueset!(
-            CALLSITE.metadata().fields(),
-            "{}",
-            "Hello, 世界!"
-        ));
+                assert_eq!(
+                    std::str::from_utf8(&rsp).unwrap(),
+                    "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello, 世界!"
+                );
+            }
+        );
 
         let f = Rfc5424::builder()
             .hostname_as_string("bree.local".to_string())
@@ -391,28 +1267,1032 @@ mod smoke {
             .with_bom(true)
             .build();
 
-        (|value_set: ::tracing::field::ValueSet| {
-            let _event = Event::new(CALLSITE.metadata(), &value_set);
-            let rsp: Vec<u8> = f
-                .format(
-                    Level::LOG_INFO,
-                    "Hello, world!",
-                    Some(std::time::UNIX_EPOCH.into()),
-                )
-                .unwrap();
+        crate::test_event!(
+            tracing::Level::INFO,
+            "test-target",
+            "Hello, world!",
+            |_event| {
+                let rsp: Vec<u8> = f
+                    .format(
+                        Level::LOG_INFO,
+                        "Hello, world!",
+                        Some(std::time::UNIX_EPOCH.into()),
+                        None,
+                    )
+                    .unwrap();
+
+                let mut golden =
+                    Vec::from("<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - ");
+                golden.push(0xef_u8);
+                golden.push(0xbb_u8);
+                golden.push(0xbf_u8);
+                golden.extend_from_slice("Hello, world!".as_bytes());
+
+                assert_eq!(rsp, golden);
+            }
+        );
+    }
+
+    #[test]
+    fn test_span_lifecycle() {
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .build();
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            f,
+            SpanLifecycleFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("handle_request");
+            let _guard = span.enter();
+        });
 
-            let mut golden =
-                Vec::from("<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - ");
-            golden.push(0xef_u8);
-            golden.push(0xbb_u8);
-            golden.push(0xbf_u8);
-            golden.extend_from_slice("Hello, world!".as_bytes());
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 2);
+        let enter = std::str::from_utf8(&msgs[0]).unwrap();
+        assert!(enter.starts_with("<14>1 "));
+        assert!(enter.ends_with(" bree.local prototyping 123 - - → handle_request"));
+        let exit = std::str::from_utf8(&msgs[1]).unwrap();
+        assert!(exit.ends_with(" bree.local prototyping 123 - - ← handle_request"));
+    }
+
+    #[test]
+    fn test_no_span_lifecycle_by_default() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default()
+            .with(Layer::with_transport(transport.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("handle_request");
+            let _guard = span.enter();
+            tracing::info!("Hello, world!");
+        });
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 1);
+        assert!(msgs[0].ends_with(b"Hello, world!"));
+    }
+
+    #[test]
+    fn test_min_level() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(
+            Layer::new(
+                Rfc5424::builder()
+                    .hostname_as_string("bree.local".to_string())
+                    .unwrap()
+                    .build(),
+                SpanLifecycleFormatter::default(),
+                transport.clone(),
+            )
+            .with_min_level(tracing::Level::WARN),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("handle_request");
+            let _guard = span.enter();
+            tracing::debug!("Hello, world!");
+            tracing::info!("Hello, world!");
+            tracing::warn!("Uh-oh");
+            tracing::error!("Oh, no!");
+        });
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 2);
+        assert!(msgs[0].ends_with(b"Uh-oh"));
+        assert!(msgs[1].ends_with(b"Oh, no!"));
+    }
+
+    #[test]
+    fn test_timestamp_field() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                syslog.timestamp = "2001-09-09T03:46:40+02:00",
+                "Hello, world!"
+            );
+            tracing::info!(syslog.timestamp = "not a timestamp", "Hello, world!");
+        });
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(
+            std::str::from_utf8(&msgs[0]).unwrap(),
+            "<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - - Hello, world!"
+        );
+        // An unparseable timestamp is ignored in favor of the current time
+        assert!(!msgs[1].starts_with(b"<14>1 2001-09-09"));
+    }
+
+    #[test]
+    fn test_appname_field() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(syslog.appname = "tenant-a", "Hello, world!");
+            tracing::info!(syslog.appname = "tenant-b", "Hello, world!");
+            tracing::info!("Hello, world!");
+            tracing::info!(syslog.appname = "not a valid app-name", "Hello, world!");
+        });
+
+        let appnames = transport
+            .messages()
+            .iter()
+            .map(|msg| {
+                String::from_utf8(msg.clone())
+                    .unwrap()
+                    .split(' ')
+                    .nth(3)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            appnames,
+            vec!["tenant-a", "tenant-b", "prototyping", "prototyping"]
+        );
+    }
+
+    #[test]
+    fn test_structured_data() {
+        struct Order {
+            id: u64,
+            item: &'static str,
+        }
+        impl IntoSdElements for Order {
+            fn sd_elements(&self) -> Vec<SdElement> {
+                vec![SdElement::new("order@32473")
+                    .unwrap()
+                    .with_param("id", self.id.to_string())
+                    .unwrap()
+                    .with_param("item", self.item)
+                    .unwrap()]
+            }
+        }
+
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::minimal(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        let order = Order {
+            id: 11,
+            item: "a \"quoted\" widget",
+        };
+        let rush = SdElement::new("rush@32473").unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            with_structured_data(&order, || {
+                tracing::info!("order placed");
+                with_structured_data(&rush, || tracing::info!("order rushed"));
+            });
+            tracing::info!("Hello, world!");
+        });
 
-            assert_eq!(rsp, golden);
-        })(tracing::valueset!(
-            CALLSITE.metadata().fields(),
+        let msgs = transport
+            .messages()
+            .into_iter()
+            .map(|msg| String::from_utf8(msg).unwrap())
+            .collect::<Vec<String>>();
+        assert!(msgs[0].ends_with(
+            " - - - [order@32473 id=\"11\" item=\"a \\\"quoted\\\" widget\"] order placed"
+        ));
+        assert!(msgs[1].ends_with("widget\"][rush@32473] order rushed"));
+        assert!(msgs[2].ends_with(" - - - - Hello, world!"));
+    }
+
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    #[test]
+    fn test_sd_field() {
+        use valuable::Valuable;
+
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::minimal(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        let order = SdElement::new("order@32473")
+            .and_then(|elt| elt.with_param("id", "11"))
+            .unwrap();
+        let elements = vec![order.clone(), SdElement::new("rush@32473").unwrap()];
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(syslog.sd = order.as_value(), "order placed");
+            tracing::info!(syslog.sd = elements.as_value(), "order rushed");
+            // Not an `SdElement`
+            tracing::info!(syslog.sd = (1u8, "two").as_value(), "Hello, world!");
+        });
+
+        let msgs = transport
+            .messages()
+            .into_iter()
+            .map(|msg| String::from_utf8(msg).unwrap())
+            .collect::<Vec<String>>();
+        assert!(msgs[0].ends_with(" - - - [order@32473 id=\"11\"] order placed"));
+        assert!(msgs[1].ends_with(" - - - [order@32473 id=\"11\"][rush@32473] order rushed"));
+        assert!(msgs[2].ends_with(" - - - - Hello, world!"));
+    }
+
+    #[test]
+    fn test_all_fields() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder().with_all_fields(true).build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                user = "frodo",
+                count = 3,
+                path = "a \"b\" ]c",
+                syslog.appname = "shire",
+                "Hello, world!"
+            );
+        });
+
+        let parsed = crate::rfc5424::parse(&transport.messages()[0]).unwrap();
+        assert_eq!(parsed.msg, "Hello, world!");
+        let fields = &parsed.structured_data[crate::rfc5424::FIELDS_SDID];
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields["user"], "frodo");
+        assert_eq!(fields["count"], "3");
+        assert_eq!(fields["path"], "a \"b\" ]c");
+    }
+
+    #[test]
+    fn test_all_fields_single_pass() {
+        // A field value that counts the times it's formatted
+        struct Counted(std::sync::atomic::AtomicUsize);
+        impl std::fmt::Debug for Counted {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                f.write_str("counted")
+            }
+        }
+        let counted = Counted(std::sync::atomic::AtomicUsize::new(0));
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder().with_all_fields(true).build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(value = ?counted, "Hello, world!");
+        });
+        let parsed = crate::rfc5424::parse(&transport.messages()[0]).unwrap();
+        assert_eq!(
+            parsed.structured_data[crate::rfc5424::FIELDS_SDID]["value"],
+            "counted"
+        );
+        assert_eq!(counted.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_root_span_target() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .with_root_span_target(true)
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!(target: "billing", "outer");
+            let _outer = outer.enter();
+            let inner = tracing::info_span!(target: "billing::invoices", "inner");
+            let _inner = inner.enter();
+            tracing::info!(target: "db", "Hello, world!");
+        });
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry::Registry::default().with(Layer::new(
+                Rfc5424::builder().with_root_span_target(true).build(),
+                TrivialTracingFormatter::default(),
+                transport.clone(),
+            )),
+            || {
+                tracing::info!("Hello, world!");
+            },
+        );
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 2);
+        let text = std::str::from_utf8(&msgs[0]).unwrap();
+        assert!(
+            text.ends_with(r#"[tracing-meta@64700 root-target="billing"] Hello, world!"#),
             "{}",
-            "Hello, world!"
+            text
+        );
+        // No enclosing span means no SD
+        assert!(msgs[1].ends_with(b" - Hello, world!"));
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .with_clock(crate::formatter::Clock::fixed(
+                    Utc.timestamp_opt(1_000_000_000, 0).unwrap(),
+                ))
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
         ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+            tracing::info!("Hello, again!");
+        });
+
+        let msgs = transport.messages();
+        assert_eq!(
+            msgs,
+            vec![
+                b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - - Hello, world!"
+                    .to_vec(),
+                b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - - Hello, again!"
+                    .to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_send_record() {
+        let ts = Utc.timestamp_opt(1_000_000_000, 0).unwrap();
+        let formatter = || {
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .with_clock(crate::formatter::Clock::fixed(ts))
+                .build()
+        };
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+        });
+
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        );
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: module_path!(), |event| event.metadata());
+        layer
+            .send_record(Level::LOG_INFO, "Hello, world!", ts, meta)
+            .unwrap();
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0], msgs[1]);
+    }
+
+    #[test]
+    fn test_scratch_reuse() {
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .with_clock(crate::formatter::Clock::fixed(
+                    Utc.timestamp_opt(1_000_000_000, 0).unwrap(),
+                ))
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("A considerably longer message than the one that follows");
+            tracing::info!("Short");
+        });
+        assert_eq!(
+            transport.messages()[1],
+            b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - - Short".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_flush() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::with_transport(
+            crate::transport::TcpTransport::new(listener.local_addr().unwrap()).unwrap(),
+        );
+        let (peer, _) = listener.accept().unwrap();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+            // Flush explicitly, while the connection is still open...
+            tracing::dispatcher::get_default(|dispatch| {
+                dispatch
+                    .downcast_ref::<Layer<
+                        tracing_subscriber::Registry,
+                        Rfc5424,
+                        TrivialTracingFormatter,
+                        crate::transport::TcpTransport,
+                    >>()
+                    .unwrap()
+                    .flush()
+                    .unwrap()
+            });
+            // & the message should be there for the reading.
+            let mut line = String::new();
+            std::io::BufRead::read_line(&mut std::io::BufReader::new(&peer), &mut line).unwrap();
+            assert!(line.ends_with("Hello, world!\n"));
+        });
+        // The subscriber (and hence the Layer) has been dropped, so the connection is closed.
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut &peer, &mut text).unwrap();
+        assert!(text.is_empty());
+    }
+
+    #[test]
+    fn test_set_transport() {
+        let first = VecTransport::new();
+        let second = VecTransport::new();
+        let (layer, handle) =
+            tracing_subscriber::reload::Layer::new(Layer::with_transport(first.clone()));
+        let subscriber = tracing_subscriber::registry::Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, first!");
+            handle
+                .modify(|layer| {
+                    layer.set_transport(second.clone());
+                })
+                .unwrap();
+            tracing::info!("Hello, second!");
+        });
+
+        let first = first.messages();
+        assert_eq!(first.len(), 1);
+        assert!(first[0].ends_with(b"Hello, first!"));
+        let second = second.messages();
+        assert_eq!(second.len(), 1);
+        assert!(second[0].ends_with(b"Hello, second!"));
+    }
+
+    #[test]
+    fn test_boxed_transport() {
+        type BoxedTransport =
+            Box<dyn Transport<Rfc5424, Error = crate::transport::Error> + Send + Sync>;
+
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = peer.local_addr().unwrap();
+        let make_transport = |kind: &str| -> BoxedTransport {
+            match kind {
+                "udp" => Box::new(UdpTransport::new(addr).unwrap()),
+                _ => Box::new(VecTransport::new()),
+            }
+        };
+
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            Rfc5424::builder().build(),
+            TrivialTracingFormatter::default(),
+            make_transport("udp"),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+        });
+
+        let mut buf = [0u8; 512];
+        let n = peer.recv(&mut buf).unwrap();
+        assert!(buf[..n].ends_with(b"Hello, world!"));
+    }
+
+    #[test]
+    fn test_on_drop() {
+        let drops = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let transport = VecTransport::new();
+        let layer = Layer::new(
+            Rfc5424::builder().build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        )
+        .with_min_level(tracing::Level::INFO)
+        .with_on_drop({
+            let drops = drops.clone();
+            move |meta, reason| {
+                drops
+                    .lock()
+                    .unwrap()
+                    .push((meta.level().to_owned(), reason))
+            }
+        });
+        let subscriber = tracing_subscriber::registry::Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(count = 5);
+            tracing::debug!("Hello, world!");
+            tracing::info!("Hello, world!");
+        });
+
+        assert_eq!(transport.messages().len(), 1);
+        assert_eq!(
+            *drops.lock().unwrap(),
+            vec![
+                (tracing::Level::WARN, DropReason::NoMessage),
+                (tracing::Level::DEBUG, DropReason::BelowMinLevel)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_transport() {
+        let transport = std::sync::Arc::new(VecTransport::new());
+        let subscriber = tracing_subscriber::registry::Registry::default()
+            .with(
+                Layer::new(
+                    Rfc5424::builder().build(),
+                    TrivialTracingFormatter::default(),
+                    transport.clone(),
+                )
+                .with_min_level(tracing::Level::ERROR),
+            )
+            .with(Layer::new(
+                Rfc5424::builder().build(),
+                TrivialTracingFormatter::default(),
+                transport.clone(),
+            ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+            tracing::error!("Goodbye, world!");
+        });
+
+        let msgs = transport.messages();
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].ends_with(b"Hello, world!"));
+        assert!(msgs[1].ends_with(b"Goodbye, world!"));
+        assert!(msgs[2].ends_with(b"Goodbye, world!"));
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        fn format() -> Result<()> {
+            Err(crate::rfc5424::Error::BadIpAddress {
+                source: local_ip_address::Error::LocalIpAddressNotFound,
+                back: backtrace::Backtrace::new(),
+            })?
+        }
+        fn transport() -> Result<()> {
+            Err(crate::transport::Error::from(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "gone",
+            )))?
+        }
+
+        match format() {
+            Err(Error::Format { source, .. }) => assert!(matches!(
+                source.downcast_ref::<crate::rfc5424::Error>(),
+                Some(crate::rfc5424::Error::BadIpAddress { .. })
+            )),
+            rsp => panic!("expected a format error, got {:?}", rsp),
+        }
+        match Error::from(
+            Rfc3164::builder()
+                .unwrap()
+                .tag_as_string("not a tag!".to_string())
+                .unwrap_err(),
+        ) {
+            Error::Format { source, .. } => {
+                assert!(source.downcast_ref::<crate::rfc3164::Error>().is_some())
+            }
+            err => panic!("expected a format error, got {:?}", err),
+        }
+        match Error::from(crate::tracing::Error::NoMessageField {
+            name: "test",
+            back: Backtrace::new(),
+        }) {
+            Error::Format { source, .. } => {
+                assert!(source.downcast_ref::<crate::tracing::Error>().is_some())
+            }
+            err => panic!("expected a format error, got {:?}", err),
+        }
+        match transport() {
+            Err(Error::Transport { source, .. }) => {
+                assert!(source.downcast_ref::<crate::transport::Error>().is_some())
+            }
+            rsp => panic!("expected a transport error, got {:?}", rsp),
+        }
+    }
+
+    #[test]
+    fn test_transport_error_downcast() {
+        struct BrokenTransport;
+        impl Transport<Rfc5424> for BrokenTransport {
+            type Error = crate::transport::Error;
+            fn send(&self, _buf: Vec<u8>) -> StdResult<(), Self::Error> {
+                Ok(())
+            }
+            fn flush(&self) -> StdResult<(), Self::Error> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone").into())
+            }
+        }
+
+        fn assert_send_sync<E: Send + Sync + 'static>(_: &E) {}
+
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::new(
+            Rfc5424::builder().build(),
+            TrivialTracingFormatter::default(),
+            BrokenTransport,
+        );
+        let err = layer.flush().unwrap_err();
+        assert_send_sync(&err);
+        let source = match &err {
+            Error::Transport { source, .. } => source,
+            _ => panic!("expected a transport error, got {:?}", err),
+        };
+        match source.downcast_ref::<crate::transport::Error>() {
+            Some(crate::transport::Error::Io { source, .. }) => {
+                assert_eq!(source.kind(), std::io::ErrorKind::BrokenPipe)
+            }
+            _ => panic!("couldn't downcast {:?}", source),
+        }
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_metrics() {
+        struct FailingTransport;
+        impl Transport<Rfc5424> for FailingTransport {
+            type Error = crate::transport::Error;
+            fn send(&self, _buf: Vec<u8>) -> StdResult<(), Self::Error> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone").into())
+            }
+            fn flush(&self) -> StdResult<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        let formatter = || {
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .with_clock(crate::formatter::Clock::fixed(
+                    Utc.timestamp_opt(1_000_000_000, 0).unwrap(),
+                ))
+                .build()
+        };
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: module_path!(), |event| event.metadata());
+        let ts = Utc.timestamp_opt(1_000_000_000, 0).unwrap();
+
+        let transport = VecTransport::new();
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        );
+        layer
+            .send_record(Level::LOG_INFO, "Hello, world!", ts, meta)
+            .unwrap();
+        layer
+            .send_record(Level::LOG_WARNING, "Goodbye, world!", ts, meta)
+            .unwrap();
+        assert_eq!(layer.messages_sent(), 2);
+        assert_eq!(layer.messages_failed(), 0);
+        assert_eq!(
+            layer.bytes_sent(),
+            transport
+                .messages()
+                .iter()
+                .map(|msg| msg.len() as u64)
+                .sum::<u64>()
+        );
+
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            FailingTransport,
+        );
+        assert!(layer
+            .send_record(Level::LOG_INFO, "Hello, world!", ts, meta)
+            .is_err());
+        assert_eq!(layer.messages_sent(), 0);
+        assert_eq!(layer.messages_failed(), 1);
+        assert_eq!(layer.bytes_sent(), 0);
+
+        // An event the `TracingFormatter` can't handle (one with no message) is a failure, too
+        let transport = VecTransport::new();
+        let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(count = 5);
+            tracing::info!("Hello, world!");
+            tracing::dispatcher::get_default(|dispatch| {
+                let layer = dispatch
+                    .downcast_ref::<Layer<
+                        tracing_subscriber::Registry,
+                        Rfc5424,
+                        TrivialTracingFormatter,
+                        VecTransport,
+                    >>()
+                    .unwrap();
+                assert_eq!(layer.messages_sent(), 1);
+                assert_eq!(layer.messages_failed(), 1);
+            });
+        });
+        assert_eq!(transport.messages().len(), 1);
+    }
+
+    #[test]
+    fn span_cache() {
+        use crate::rfc5424::{parse, SPAN_FIELDS_SDID};
+
+        let first = VecTransport::new();
+        let second = VecTransport::new();
+        fn layer<S>(
+            transport: &VecTransport,
+        ) -> Layer<S, Rfc5424, TrivialTracingFormatter, VecTransport>
+        where
+            S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+        {
+            Layer::new(
+                Rfc5424::builder()
+                    .with_span_fields(true)
+                    .with_root_span_target(true)
+                    .build(),
+                TrivialTracingFormatter::default(),
+                transport.clone(),
+            )
+        }
+        // Two syslog layers share each span's cached context
+        let subscriber = tracing_subscriber::registry::Registry::default()
+            .with(layer(&first))
+            .with(layer(&second));
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!(target: "billing", "outer", user = "frodo", role = "ringbearer", tries = tracing::field::Empty);
+            let _outer = outer.enter();
+            let inner = tracing::info_span!("inner", user = "sam");
+            let _inner = inner.enter();
+            tracing::info!("first");
+            // Recorded on the outer span *after* the inner span cached its context
+            outer.record("tries", 2);
+            outer.record("role", "burglar");
+            tracing::info!("second");
+            inner.record("user", "gollum");
+            tracing::info!("third");
+        });
+
+        for transport in [first, second] {
+            let msgs = transport.messages();
+            assert_eq!(msgs.len(), 3);
+            let sds = msgs
+                .iter()
+                .map(|msg| parse(msg).unwrap().structured_data[SPAN_FIELDS_SDID].clone())
+                .collect::<Vec<_>>();
+            // The inner span's `user` shadows the outer's
+            assert_eq!(sds[0]["user"], "sam");
+            assert_eq!(sds[0]["role"], "ringbearer");
+            assert!(!sds[0].contains_key("tries"));
+            assert_eq!(sds[1]["user"], "sam");
+            assert_eq!(sds[1]["role"], "burglar");
+            assert_eq!(sds[1]["tries"], "2");
+            assert_eq!(sds[2]["user"], "gollum");
+            assert_eq!(sds[2]["role"], "burglar");
+            assert!(msgs.iter().all(|msg| std::str::from_utf8(msg)
+                .unwrap()
+                .contains(r#"root-target="billing""#)));
+        }
+    }
+
+    #[test]
+    fn span_cache_invalidation() {
+        use tracing_subscriber::registry::{LookupSpan, Registry};
+
+        let cached = VecTransport::new();
+        let uncached = VecTransport::new();
+        let formatter = || Rfc5424::builder().with_span_fields(true).build();
+        let subscriber = Registry::default()
+            .with(Layer::new(
+                formatter(),
+                TrivialTracingFormatter::default(),
+                cached.clone(),
+            ))
+            .with(
+                Layer::new(
+                    formatter(),
+                    TrivialTracingFormatter::default(),
+                    uncached.clone(),
+                )
+                .without_span_cache(),
+            );
+        // Return the resolved fields `span` has cached
+        fn resolved(span: &tracing::Span) -> SharedSpanFields {
+            tracing::dispatcher::get_default(|dispatch| {
+                dispatch
+                    .downcast_ref::<Registry>()
+                    .and_then(|registry| registry.span(&span.id().unwrap()))
+                    .and_then(|span| {
+                        span.extensions()
+                            .get::<SpanContext>()
+                            .map(|context| context.resolved.clone())
+                    })
+                    .unwrap()
+            })
+        }
+        tracing::subscriber::with_default(subscriber, || {
+            let root = tracing::info_span!("root", user = "frodo");
+            let _root = root.enter();
+            let left = tracing::info_span!("left", side = tracing::field::Empty);
+            let right = tracing::info_span!("right", side = "right");
+            right.in_scope(|| tracing::info!("first"));
+            let before = resolved(&right);
+            // Recording on a sibling leaves our cache alone...
+            left.record("side", "left");
+            right.in_scope(|| tracing::info!("second"));
+            assert!(Arc::ptr_eq(&before, &resolved(&right)));
+            // but recording on an ancestor doesn't.
+            root.record("user", "sam");
+            right.in_scope(|| tracing::info!("third"));
+            let after = resolved(&right);
+            assert!(!Arc::ptr_eq(&before, &after));
+            assert_eq!(
+                after.fields,
+                vec![("user", "sam".to_string()), ("side", "right".to_string())]
+            );
+            assert_eq!(after.sd, br#"[span@64700 user="sam" side="right"]"#);
+        });
+
+        // The cached SD elements are just what we'd have rendered from scratch
+        let sd = |msg: &Vec<u8>| {
+            let msg = String::from_utf8(msg.clone()).unwrap();
+            msg[msg.find('[').unwrap()..msg.rfind(']').unwrap() + 1].to_string()
+        };
+        let (cached, uncached) = (cached.messages(), uncached.messages());
+        assert_eq!(cached.len(), 3);
+        assert_eq!(
+            cached.iter().map(sd).collect::<Vec<_>>(),
+            uncached.iter().map(sd).collect::<Vec<_>>()
+        );
+        assert_eq!(sd(&cached[2]), r#"[span@64700 user="sam" side="right"]"#);
+    }
+
+    /// Not so much a test as a compile-time check that the common configurations can be shared
+    /// across threads (and so, for instance, boxed up as `dyn Layer<S> + Send + Sync`)
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+        use tracing_subscriber::Registry;
+
+        assert_send_sync::<Error>();
+        assert_send_sync::<crate::rfc5424::Error>();
+        assert_send_sync::<crate::rfc3164::Error>();
+        assert_send_sync::<crate::tracing::Error>();
+        assert_send_sync::<crate::transport::Error>();
+
+        assert_send_sync::<Rfc5424>();
+        assert_send_sync::<Rfc3164>();
+        assert_send_sync::<TrivialTracingFormatter>();
+        assert_send_sync::<SpanLifecycleFormatter>();
+        assert_send_sync::<UdpTransport>();
+        assert_send_sync::<crate::transport::TcpTransport>();
+        assert_send_sync::<VecTransport>();
+
+        assert_send_sync::<DefaultUdpLayer<Registry>>();
+        assert_send_sync::<Layer<Registry, Rfc5424, TrivialTracingFormatter, UdpTransport>>();
+        assert_send_sync::<Layer<Registry, Rfc5424, SpanLifecycleFormatter, VecTransport>>();
+        #[cfg(unix)]
+        {
+            assert_send_sync::<crate::transport::UnixSocket>();
+            assert_send_sync::<crate::transport::UnixSocketStream>();
+            assert_send_sync::<DefaultUnixLayer<Registry>>();
+        }
+
+        // & for good measure, actually box one up & use it
+        let transport = VecTransport::new();
+        let layer: Box<dyn tracing_subscriber::Layer<Registry> + Send + Sync> =
+            Box::new(Layer::with_transport(transport.clone()));
+        let subscriber = Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+        });
+        assert_eq!(transport.messages().len(), 1);
+    }
+
+    #[test]
+    fn startup_marker() {
+        let transport = VecTransport::new();
+        let layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::new(
+            Rfc5424::builder()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        )
+        .with_startup_marker(true)
+        .unwrap();
+        // Sent on construction
+        let messages = transport.messages();
+        assert_eq!(messages.len(), 1);
+        let msg = String::from_utf8_lossy(&messages[0]);
+        assert!(msg.starts_with("<14>1 "));
+        assert!(msg.ends_with(
+            " prototyping 123 - - syslog-tracing initialized; appname=prototyping pid=123"
+        ));
+        assert_eq!(layer.messages_sent(), 1);
+
+        // Off by default, & when asked
+        let transport = VecTransport::new();
+        let _layer: Layer<tracing_subscriber::Registry, _, _, _> =
+            Layer::with_transport(transport.clone())
+                .with_startup_marker(false)
+                .unwrap();
+        assert!(transport.messages().is_empty());
+
+        // RFC 3164 reports its TAG, & the absence of a PID
+        let transport = VecTransport::new();
+        let _layer: Layer<tracing_subscriber::Registry, _, _, _> = Layer::from_parts(
+            crate::rfc3164::Rfc3164::builder()
+                .unwrap()
+                .tag_as_string("prototyping".to_string())
+                .unwrap()
+                .pid(None)
+                .build(),
+            TrivialTracingFormatter::default(),
+            transport.clone(),
+        )
+        .with_startup_marker(true)
+        .unwrap();
+        assert!(transport.messages()[0]
+            .ends_with(b" prototyping: syslog-tracing initialized; appname=prototyping pid=-"));
+
+        // A marker that can't be sent fails construction
+        struct FailingTransport;
+        impl Transport<Rfc5424> for FailingTransport {
+            type Error = crate::transport::Error;
+            fn send(&self, _buf: Vec<u8>) -> StdResult<(), Self::Error> {
+                Err(
+                    std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "nobody home")
+                        .into(),
+                )
+            }
+            fn flush(&self) -> StdResult<(), Self::Error> {
+                Ok(())
+            }
+        }
+        let result: Result<Layer<tracing_subscriber::Registry, _, _, _>> =
+            Layer::with_transport(FailingTransport).with_startup_marker(true);
+        assert!(matches!(result, Err(Error::Transport { .. })));
     }
 }
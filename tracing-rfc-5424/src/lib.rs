@@ -74,19 +74,12 @@
 //!
 //! ```rust
 //! use tracing::info;
-//! use tracing_rfc_5424::{
-//!     layer::Layer,
-//!     rfc5424::Rfc5424,
-//!     tracing::TrivialTracingFormatter,
-//!     transport::UdpTransport,
-//! };
 //! use tracing_subscriber::registry::Registry;
 //! use tracing_subscriber::layer::SubscriberExt; // Needed to get `with()`
 //!
 //! // The default configuration is to format syslog messages as per RFC 5424
 //! // and to send them via UDP to port 514 on the localhost.
-//! let subscriber = Registry::default()
-//!     .with(Layer::<tracing_subscriber::Registry, Rfc5424, TrivialTracingFormatter, UdpTransport>::try_default().unwrap());
+//! let subscriber = Registry::default().with(tracing_rfc_5424::udp_layer().unwrap());
 //!
 //! info!("Hello, world!");
 //! ```
@@ -128,5 +121,12 @@ pub mod formatter;
 pub mod layer;
 pub mod rfc3164;
 pub mod rfc5424;
+#[cfg(any(test, feature = "test-util"))]
+#[path = "test-util.rs"]
+pub mod test_util;
 pub mod tracing;
 pub mod transport;
+
+pub use layer::{udp_layer, DefaultUdpLayer};
+#[cfg(unix)]
+pub use layer::{unix_layer, DefaultUnixLayer};
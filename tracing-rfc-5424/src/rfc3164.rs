@@ -36,9 +36,9 @@
 //!
 
 use crate::{
-    byte_utils::bytes_from_os_str,
-    facility::{Facility, Level},
-    formatter::SyslogFormatter,
+    byte_utils::{ascii_hostname, bytes_from_os_str},
+    facility::{AtomicFacility, Facility, Level},
+    formatter::{Clock, Extras, SyslogFormatter},
 };
 
 use backtrace::Backtrace;
@@ -62,9 +62,11 @@ pub enum Error {
     },
     /// Non-compliant tag provided
     BadTag { name: Vec<u8>, back: Backtrace },
+    /// Unusable TAG/CONTENT separator provided
+    BadTagSeparator { sep: String, back: Backtrace },
     /// Failed to format the `tracing` Event
     BadTracingFormat {
-        source: Box<dyn std::error::Error>,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         back: Backtrace,
     },
     /// I/O error
@@ -77,6 +79,11 @@ pub enum Error {
         pathb: std::path::PathBuf,
         back: Backtrace,
     },
+    /// A formatted message was not valid UTF-8
+    NotUtf8 {
+        source: std::string::FromUtf8Error,
+        back: Backtrace,
+    },
 }
 
 impl std::convert::From<std::io::Error> for Error {
@@ -104,6 +111,13 @@ impl std::fmt::Display for Error {
                 source
             ),
             Error::BadTag { name, .. } => write!(f, "{:?} is not an RFC3164-compliant tag", name),
+            Error::BadTagSeparator { sep, .. } => {
+                write!(
+                    f,
+                    "{:?} can't separate an RFC3164 tag from its content",
+                    sep
+                )
+            }
             Error::BadTracingFormat { source, .. } => write!(
                 f,
                 "While attempting to format an Event or Span, got {}",
@@ -113,6 +127,9 @@ impl std::fmt::Display for Error {
             Error::NoTag { pathb, .. } => {
                 write!(f, "{:#?} does not yield an RFC3164-compliant tag", pathb)
             }
+            Error::NotUtf8 { source, .. } => {
+                write!(f, "The formatted message was not UTF-8: {}", source)
+            }
             _ => write!(f, "syslog transport layer error"),
         }
     }
@@ -125,9 +142,11 @@ impl std::fmt::Debug for Error {
             Error::BadHostname { name: _, back } => write!(f, "{}\n{:#?}", self, back),
             Error::BadIpAddress { source: _, back } => write!(f, "{}\n{:#?}", self, back),
             Error::BadTag { name: _, back } => write!(f, "{}\n{:#?}", self, back),
+            Error::BadTagSeparator { sep: _, back } => write!(f, "{}\n{:#?}", self, back),
             Error::BadTracingFormat { source: _, back } => write!(f, "{}\n{:#?}", self, back),
             Error::Io { source: _, back } => write!(f, "{}\n{:#?}", self, back),
             Error::NoTag { pathb: _, back } => write!(f, "{}\n{:#?}", self, back),
+            Error::NotUtf8 { source: _, back } => write!(f, "{}\n{:#?}", self, back),
             _ => write!(f, "{}", self),
         }
     }
@@ -143,8 +162,23 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 /// A `Vec<u8>` instance with the additional constraint that its contents be ASCII above the value
 /// 32 (space)
+#[derive(Clone)]
 pub struct Rfc3164Hostname(Vec<u8>);
 
+impl std::fmt::Debug for Rfc3164Hostname {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.debug_tuple("Rfc3164Hostname")
+            .field(&String::from_utf8_lossy(&self.0))
+            .finish()
+    }
+}
+
+impl std::fmt::Display for Rfc3164Hostname {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
 impl Rfc3164Hostname {
     /// An RFC 3164-compliant hostname is made-up of ASCII above 32/space. The RFC states "The
     /// Domain Name MUST NOT be included in the HOSTNAME field" which I interpret to mean that _if_
@@ -173,6 +207,29 @@ impl Rfc3164Hostname {
         }
         bytes
     }
+    /// Build an [`Rfc3164Hostname`] from a name that may be either a DNS name or an IP literal
+    ///
+    /// The domain is stripped from DNS names only; an IP literal (IPv4 or IPv6, including
+    /// IPv4-mapped IPv6 addresses like `::ffff:192.0.2.1`) is kept whole, since truncating it at
+    /// the first `.` would produce nonsense.
+    fn from_name(bytes: Vec<u8>) -> Result<Rfc3164Hostname> {
+        let is_ip_literal = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| text.parse::<std::net::IpAddr>().ok())
+            .is_some();
+        if is_ip_literal {
+            Rfc3164Hostname::new(bytes)
+        } else {
+            Rfc3164Hostname::new(Rfc3164Hostname::strip_domain(bytes))
+        }
+    }
+    /// Build an [`Rfc3164Hostname`] from an IP address
+    ///
+    /// IPv4 addresses are rendered in dotted decimal notation & IPv6 addresses in their RFC 5952
+    /// canonical form (which is one of the representations RFC 2373 permits).
+    fn from_ip(ip: std::net::IpAddr) -> Result<Rfc3164Hostname> {
+        Rfc3164Hostname::new(ip.to_string().into_bytes())
+    }
     /// Attempt to figure-out an RFC [3164]-compliant hostname.
     ///
     /// Per the RFC:
@@ -185,6 +242,9 @@ impl Rfc3164Hostname {
     /// dotted decimal notation as used in STD 13.  If an IPv6 address is used, any valid
     /// representation used in RFC 2373 MAY be used.
     ///
+    /// With the `idna` feature (enabled by default), a non-ASCII hostname is first converted to its
+    /// ASCII-compatible encoding.
+    ///
     /// [3164]: https://datatracker.ietf.org/doc/html/rfc3164
     pub fn try_default() -> Result<Rfc3164Hostname> {
         // `hostname::get()` returns an `Result<OsString,_>`, which is really kind of a hassle to work
@@ -192,17 +252,15 @@ impl Rfc3164Hostname {
         hostname::get()
             .map_err(|err| err.into())
             // 👇 :=> StdResult<Rfc3164Hostname, Error>
-            .and_then(|hn| {
-                Rfc3164Hostname::new(Rfc3164Hostname::strip_domain(bytes_from_os_str(hn)))
-            })
+            .and_then(|hn| Rfc3164Hostname::from_name(ascii_hostname(bytes_from_os_str(hn))))
             // 👇 will return the Ok(Rfc3164Hostname), or call the closure :=> StdResult<Rfc3164Hostname, Error>
             .or_else(|_err| {
-                let ip: StdResult<std::net::IpAddr, Error> =
-                    local_ip_address::local_ip().map_err(|err| Error::BadIpAddress {
+                local_ip_address::local_ip()
+                    .map_err(|err| Error::BadIpAddress {
                         source: err,
                         back: Backtrace::new(),
-                    });
-                ip.map(|ip| Rfc3164Hostname(ip.to_string().into_bytes()))
+                    })
+                    .and_then(Rfc3164Hostname::from_ip)
             })
     }
 }
@@ -231,15 +289,53 @@ impl std::convert::TryFrom<String> for Rfc3164Hostname {
 ///
 /// Therefore [`Tag`] simply represents an ASCII alphanumeric string that is less than or equal to
 /// 32 characters in length.
+#[derive(Clone)]
 pub struct Tag(Vec<u8>);
 
+/// The RFC's upper bound on the length of the TAG
+const TAG_MAX_LEN: usize = 32;
+/// The upper bound on the length of a TAG under [lenient](Tag::new_lenient) validation
+const TAG_LENIENT_MAX_LEN: usize = 64;
+
+/// Return true if `tag` is a legal TAG, under lenient validation or not
+fn is_tag(tag: &[u8], lenient: bool) -> bool {
+    if lenient {
+        !tag.is_empty()
+            && tag.len() <= TAG_LENIENT_MAX_LEN
+            && tag
+                .iter()
+                .all(|&x| x.is_ascii_alphanumeric() || x == b'-' || x == b'_' || x == b'.')
+    } else {
+        tag.len() <= TAG_MAX_LEN && tag.iter().all(u8::is_ascii_alphanumeric)
+    }
+}
+
+impl std::fmt::Debug for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.debug_tuple("Tag")
+            .field(&String::from_utf8_lossy(&self.0))
+            .finish()
+    }
+}
+
 impl Tag {
     pub fn new(bytes: Vec<u8>) -> Result<Tag> {
-        if bytes.len() <= 32
-            && bytes
-                .iter()
-                .all(|&x| x.is_ascii_digit() || x.is_ascii_uppercase() || x.is_ascii_lowercase())
-        {
+        if is_tag(&bytes, false) {
+            Ok(Tag(bytes))
+        } else {
+            Err(Error::BadTag {
+                name: bytes,
+                back: Backtrace::new(),
+            })
+        }
+    }
+    /// Construct a [`Tag`] under relaxed validation rules
+    ///
+    /// Many collectors tolerate tags that the RFC doesn't permit; this constructor accepts up to 64
+    /// bytes of ASCII alphanumerics, `-`, `_` & `.`. Note that these characters would, per the
+    /// RFC, terminate the TAG field, so a strict receiver may mis-parse the result.
+    pub fn new_lenient(bytes: Vec<u8>) -> Result<Tag> {
+        if is_tag(&bytes, true) {
             Ok(Tag(bytes))
         } else {
             Err(Error::BadTag {
@@ -282,38 +378,64 @@ impl std::convert::TryFrom<String> for Tag {
     }
 }
 
-#[cfg(test)]
-mod test {
-
-    use super::*;
-
-    #[test]
-    fn test_hostname() {
-        let _x = Rfc3164Hostname::try_default(); // At least _exercise_ `Default`
-
-        // <https://users.rust-lang.org/t/difference-of-u8-n-and-u8/30695>
-        assert!(Rfc3164Hostname::new("not valid".as_bytes().into()).is_err());
-
-        assert!(
-            Rfc3164Hostname::strip_domain("staging.pwpinfra.com".as_bytes().into()) == b"staging"
-        );
+/// The separator written between the TAG (& process ID, if any) and the CONTENT
+///
+/// RFC [3164] says that "Any non-alphanumeric character will terminate the TAG field and will be
+/// assumed to be the starting character of the CONTENT field." If nothing separated the two, a
+/// message that happened to begin with, say, `[` or `:` would be mis-parsed by a strict receiver,
+/// so [`Rfc3164`] always writes one of these.
+///
+/// [3164]: https://datatracker.ietf.org/doc/html/rfc3164
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ContentSep {
+    /// A colon followed by a space, as in `tag[123]: content`; this is the most common form
+    #[default]
+    ColonSpace,
+    /// A bare colon, as in `tag[123]:content`
+    Colon,
+    /// A single space, as in `tag[123] content`
+    Space,
+    /// Something else; see [`Rfc3164Builder::with_tag_separator`]
+    Custom(String),
+}
 
-        let x = Rfc3164Hostname::try_from(String::from("bree"));
-        assert!(x.is_ok());
+impl std::convert::TryFrom<&str> for ContentSep {
+    type Error = Error;
+    /// Map `sep` to a [`ContentSep`], checking that it can actually separate the TAG from the
+    /// CONTENT
+    ///
+    /// Since any non-alphanumeric character terminates the TAG, `sep` must be non-empty & contain
+    /// no alphanumerics; nor may it begin with `[`, which a receiver would take for the start of
+    /// the PID. It must also be printable ASCII.
+    fn try_from(sep: &str) -> StdResult<Self, Self::Error> {
+        match sep {
+            ": " => Ok(ContentSep::ColonSpace),
+            ":" => Ok(ContentSep::Colon),
+            " " => Ok(ContentSep::Space),
+            _ if !sep.is_empty()
+                && !sep.starts_with('[')
+                && sep
+                    .bytes()
+                    .all(|b| (b' '..=b'~').contains(&b) && !b.is_ascii_alphanumeric()) =>
+            {
+                Ok(ContentSep::Custom(sep.to_string()))
+            }
+            _ => Err(Error::BadTagSeparator {
+                sep: sep.to_string(),
+                back: Backtrace::new(),
+            }),
+        }
     }
+}
 
-    #[test]
-    fn test_tag() {
-        let _x = Tag::try_default(); // At least exercise it
-
-        let x = Tag::new(b"tracingrfc".to_vec());
-        assert!(x.is_ok());
-
-        let x = Tag::new(b"012345678901234567890123456789012".to_vec()); // 33 chars-- no go
-        assert!(x.is_err());
-
-        let x = Tag::new("🩡".as_bytes().to_vec()); // Non-ASCII-- no go
-        assert!(x.is_err());
+impl ContentSep {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            ContentSep::ColonSpace => b": ",
+            ContentSep::Colon => b":",
+            ContentSep::Space => b" ",
+            ContentSep::Custom(sep) => sep.as_bytes(),
+        }
     }
 }
 
@@ -328,83 +450,287 @@ mod test {
 /// instances to [escape] unicode, but by default they will not.
 ///
 /// [escape]: str::escape_unicode
+#[derive(Clone, Debug)]
 pub struct Rfc3164 {
-    facility: Facility,
-    hostname: Rfc3164Hostname,
+    facility: AtomicFacility,
+    // `None` means "omit the HOSTNAME"
+    hostname: Option<Rfc3164Hostname>,
     tag: Tag,
     add_pid: Option<u32>,
+    content_sep: ContentSep,
     escape_unicode: bool,
+    clock: Clock,
+    // Render timestamps in UTC rather than local time
+    utc: bool,
+    // Validate per-message TAGs leniently
+    lenient: bool,
 }
 
 impl Rfc3164 {
     pub fn try_default() -> Result<Rfc3164> {
         Ok(Rfc3164 {
-            facility: Facility::LOG_USER,
-            hostname: Rfc3164Hostname::try_default()?,
+            facility: AtomicFacility::new(Facility::LOG_USER),
+            hostname: Some(Rfc3164Hostname::try_default()?),
             tag: Tag::try_default()?,
             add_pid: Some(std::process::id()),
+            content_sep: ContentSep::default(),
             escape_unicode: false,
+            clock: Clock::default(),
+            utc: false,
+            lenient: false,
         })
     }
     pub fn builder() -> Result<Rfc3164Builder> {
         Ok(Rfc3164Builder {
             imp: Rfc3164::try_default()?,
+            tag: None,
+        })
+    }
+    pub fn facility(&self) -> Facility {
+        self.facility.load()
+    }
+    /// Change the facility under which subsequent messages will be logged
+    ///
+    /// This takes `&self`, so it may be called on a formatter that's already part of a
+    /// [`Layer`](crate::layer::Layer) (see [`Layer::syslog_formatter`]). The change is atomic, but
+    /// made with relaxed memory ordering: a message being formatted concurrently may carry either
+    /// facility.
+    ///
+    /// [`Layer::syslog_formatter`]: crate::layer::Layer::syslog_formatter
+    pub fn set_facility(&self, facility: Facility) {
+        self.facility.store(facility)
+    }
+    /// The HOSTNAME, or `None` if it's to be omitted
+    pub fn hostname(&self) -> Option<&Rfc3164Hostname> {
+        self.hostname.as_ref()
+    }
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+    /// The process ID appended to the TAG, if any
+    pub fn pid(&self) -> Option<u32> {
+        self.add_pid
+    }
+    pub fn content_separator(&self) -> ContentSep {
+        self.content_sep.clone()
+    }
+    pub fn escape_unicode(&self) -> bool {
+        self.escape_unicode
+    }
+    /// True if timestamps are rendered in UTC rather than local time
+    pub fn utc(&self) -> bool {
+        self.utc
+    }
+    /// Format a message as a [`String`] rather than as bytes
+    ///
+    /// This is a convenience for callers who want to inspect the formatted message (in tests, for
+    /// instance) without going through a [`Transport`].
+    ///
+    /// [`Transport`]: crate::transport::Transport
+    pub fn format_to_string(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing_core::Metadata<'_>>,
+    ) -> Result<String> {
+        String::from_utf8(self.format(level, msg, timestamp, meta)?).map_err(|err| Error::NotUtf8 {
+            source: err,
+            back: Backtrace::new(),
         })
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Rfc3164Builder {
     imp: Rfc3164,
+    /// The TAG given to `tag_as_string`, to be validated by `try_build`
+    tag: Option<String>,
 }
 
 impl Rfc3164Builder {
     pub fn facility(mut self, facility: Facility) -> Self {
-        self.imp.facility = facility;
+        self.imp.facility = AtomicFacility::new(facility);
         self
     }
     pub fn hostname(mut self, hostname: Rfc3164Hostname) -> Self {
+        self.imp.hostname = Some(hostname);
+        self
+    }
+    /// Set the HOSTNAME, or omit it altogether with `None`
+    ///
+    /// When delivering to the local daemon (via `/dev/log`, say), the daemon will fill-in the
+    /// hostname itself, and some daemons will misparse a message that carries one. With `None`,
+    /// messages will be formatted as `<PRI>TIMESTAMP TAG[PID]: MSG`.
+    pub fn with_hostname(mut self, hostname: Option<Rfc3164Hostname>) -> Self {
         self.imp.hostname = hostname;
         self
     }
     pub fn hostname_as_string(mut self, hostname: String) -> Result<Self> {
-        self.imp.hostname = Rfc3164Hostname::try_from(hostname)?;
+        self.imp.hostname = Some(Rfc3164Hostname::try_from(hostname)?);
         Ok(self)
     }
+    /// Validate `_as_string` arguments leniently (or not)
+    ///
+    /// By default, the builder enforces the RFC's rules. In lenient mode, tags (including those
+    /// given per-message; see [`Extras::appname`]) are validated according to
+    /// [`Tag::new_lenient`].
+    ///
+    /// The mode in effect when the formatter is [built](Rfc3164Builder::try_build) is the one
+    /// that applies, regardless of the order in which this &
+    /// [`tag_as_string`](Rfc3164Builder::tag_as_string) are called.
+    pub fn with_lenient_validation(mut self, lenient: bool) -> Self {
+        self.imp.lenient = lenient;
+        self
+    }
+    /// Set the TAG, validating it strictly unless
+    /// [`with_lenient_validation`](Rfc3164Builder::with_lenient_validation) is turned on
+    ///
+    /// Since the validation mode may yet change, this fails only for tags that neither mode
+    /// would accept; the rest are validated by [`try_build`](Rfc3164Builder::try_build).
     pub fn tag_as_string(mut self, tag: String) -> Result<Self> {
-        self.imp.tag = Tag::try_from(tag)?;
+        if Tag::new(tag.as_bytes().to_vec()).is_err() {
+            Tag::new_lenient(tag.as_bytes().to_vec())?;
+        }
+        self.tag = Some(tag);
         Ok(self)
     }
+    /// Set the process ID to be written after the TAG, or `None` to omit it
+    pub fn pid(mut self, pid: Option<u32>) -> Self {
+        self.imp.add_pid = pid;
+        self
+    }
+    /// Set the separator written between the TAG (& PID) and the message
+    ///
+    /// A [`ContentSep::Custom`] separator is written as-is; prefer
+    /// [`with_tag_separator`](Rfc3164Builder::with_tag_separator), which validates it.
+    pub fn with_content_separator(mut self, content_sep: ContentSep) -> Self {
+        self.imp.content_sep = content_sep;
+        self
+    }
+    /// Set the separator written between the TAG (& PID) and the message to an arbitrary string;
+    /// the default is `": "`
+    ///
+    /// `sep` is validated as described under [`ContentSep::try_from`]; separators matching one
+    /// of the [`ContentSep`] variants are mapped to that variant.
+    pub fn with_tag_separator(self, sep: &str) -> Result<Self> {
+        Ok(self.with_content_separator(ContentSep::try_from(sep)?))
+    }
     pub fn escape_unicode(mut self, escape_unicode: bool) -> Self {
         self.imp.escape_unicode = escape_unicode;
         self
     }
+    /// Use `clock` to timestamp messages for which no timestamp is supplied
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.imp.clock = clock;
+        self
+    }
+    /// Render timestamps in UTC (or not) rather than in local time
+    ///
+    /// The RFC 3164 TIMESTAMP carries no zone, so receivers have to assume one; by convention it's
+    /// the sender's local time zone, which is our default. Rendering in UTC instead suits fleets
+    /// that span time zones, and makes the output independent of the host's zone (which, along
+    /// with [`with_clock`](Rfc3164Builder::with_clock), permits reproducible output).
+    pub fn with_utc(mut self, utc: bool) -> Self {
+        self.imp.utc = utc;
+        self
+    }
+    /// Build the formatter, validating any TAG given to
+    /// [`tag_as_string`](Rfc3164Builder::tag_as_string) under the final validation mode
+    pub fn try_build(mut self) -> Result<Rfc3164> {
+        if let Some(tag) = self.tag.take() {
+            self.imp.tag = if self.imp.lenient {
+                Tag::new_lenient(tag.into_bytes())?
+            } else {
+                Tag::try_from(tag)?
+            };
+        }
+        Ok(self.imp)
+    }
+    /// Build the formatter
+    ///
+    /// # Panics
+    ///
+    /// Panics if a TAG given to [`tag_as_string`](Rfc3164Builder::tag_as_string) isn't valid
+    /// under the final validation mode; use [`try_build`](Rfc3164Builder::try_build) to handle
+    /// that.
     pub fn build(self) -> Rfc3164 {
-        self.imp
+        self.try_build()
+            .unwrap_or_else(|err| panic!("invalid RFC 3164 formatter: {}", err))
     }
 }
 
 impl SyslogFormatter for Rfc3164 {
     type Error = Error;
     type Output = Vec<u8>;
+    fn app_name(&self) -> Option<String> {
+        Some(self.tag.to_string())
+    }
+    fn proc_id(&self) -> Option<String> {
+        self.add_pid.map(|pid| pid.to_string())
+    }
     fn format(
         &self,
         level: Level,
         msg: &str,
         timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing_core::Metadata<'_>>,
     ) -> Result<Self::Output> {
-        let mut buf = format!(
-            "<{}>{} ",
-            self.facility as u8 | level as u8,
-            timestamp
-                .map(|d| d.with_timezone(&Local))
-                .or_else(|| Some(Local::now()))
-                .unwrap()
-                .format("%b %_d %H:%M:%S"),
-        )
-        .into_bytes();
+        let mut buf = Vec::new();
+        self.format_into(&mut buf, level, msg, timestamp, meta)?;
+        Ok(buf)
+    }
+    fn format_into(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing_core::Metadata<'_>>,
+    ) -> Result<()> {
+        self.format_into_with(buf, level, msg, timestamp, meta, &Extras::default())
+    }
+    fn format_with(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&tracing_core::Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<Self::Output> {
+        let mut buf = Vec::new();
+        self.format_into_with(&mut buf, level, msg, timestamp, meta, extras)?;
+        Ok(buf)
+    }
+    fn format_into_with(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        _meta: Option<&tracing_core::Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<()> {
+        use std::io::Write;
+        // Writing to a `Vec<u8>` can't fail, so the `unwrap()`s below are safe.
+        write!(buf, "<{}>", self.facility.load().pri(level)).unwrap();
+        let timestamp = timestamp.unwrap_or_else(|| self.clock.now());
+        if self.utc {
+            write!(buf, "{}", timestamp.format("%b %_d %H:%M:%S")).unwrap();
+        } else {
+            write!(
+                buf,
+                "{}",
+                timestamp.with_timezone(&Local).format("%b %_d %H:%M:%S")
+            )
+            .unwrap();
+        }
 
         use bytes::BufMut;
-        buf.put_slice(&self.hostname.0);
+        if let Some(hostname) = &self.hostname {
+            buf.put_slice(b" ");
+            buf.put_slice(&hostname.0);
+        }
 
         // The MSG part has two fields known as the TAG field and the CONTENT field.  The value in
         // the TAG field will be the name of the program or process that generated the message.  The
@@ -415,18 +741,441 @@ impl SyslogFormatter for Rfc3164 {
         // of the CONTENT field.  Most commonly, the first character of the CONTENT field that
         // signifies the conclusion of the TAG field has been seen to be the left square bracket
         // character ("["), a colon character (":"), or a space character.
+        //
+        // We always write an explicit separator, PID or no, so that the tag/content boundary is
+        // unambiguous regardless of how the message begins. The one exception is an empty message;
+        // RFC 3164 has no notion of an absent CONTENT field, so in that case we just end the packet
+        // after the TAG (& PID).
         buf.put_slice(b" ");
-        buf.put_slice(&self.tag.0);
+        // As with RFC 5424, an invalid per-message TAG is quietly ignored.
+        match extras
+            .appname
+            .filter(|tag| is_tag(tag.as_bytes(), self.lenient))
+        {
+            Some(tag) => buf.put_slice(tag.as_bytes()),
+            None => buf.put_slice(&self.tag.0),
+        }
         if let Some(pid) = self.add_pid {
-            buf.put_slice(format!("[{}]: ", pid).as_bytes());
+            write!(buf, "[{}]", pid).unwrap();
+        }
+        if !msg.is_empty() {
+            buf.put_slice(self.content_sep.as_bytes());
         }
 
         if self.escape_unicode {
-            buf.put_slice(msg.escape_unicode().to_string().as_bytes())
+            write!(buf, "{}", msg.escape_unicode()).unwrap();
         } else {
             buf.put_slice(msg.as_bytes())
         }
 
-        Ok(buf)
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_hostname() {
+        let _x = Rfc3164Hostname::try_default(); // At least _exercise_ `Default`
+
+        // <https://users.rust-lang.org/t/difference-of-u8-n-and-u8/30695>
+        assert!(Rfc3164Hostname::new("not valid".as_bytes().into()).is_err());
+
+        assert!(
+            Rfc3164Hostname::strip_domain("staging.pwpinfra.com".as_bytes().into()) == b"staging"
+        );
+
+        let x = Rfc3164Hostname::try_from(String::from("bree"));
+        assert!(x.is_ok());
+    }
+
+    #[test]
+    fn hostname_ip_literals() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+        // The IP address fallback
+        let x = Rfc3164Hostname::from_ip(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 17))).unwrap();
+        assert_eq!(x.0, b"192.168.1.17");
+        let x =
+            Rfc3164Hostname::from_ip(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+                .unwrap();
+        assert_eq!(x.0, b"2001:db8::1");
+        let x = Rfc3164Hostname::from_ip(IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped()))
+            .unwrap();
+        assert_eq!(x.0, b"::ffff:192.0.2.1");
+
+        // Names
+        let x = Rfc3164Hostname::from_name(b"staging.pwpinfra.com".to_vec()).unwrap();
+        assert_eq!(x.0, b"staging");
+        let x = Rfc3164Hostname::from_name(b"10.0.0.1".to_vec()).unwrap();
+        assert_eq!(x.0, b"10.0.0.1");
+        let x = Rfc3164Hostname::from_name(b"::ffff:10.0.0.1".to_vec()).unwrap();
+        assert_eq!(x.0, b"::ffff:10.0.0.1");
+        assert!(Rfc3164Hostname::from_name(b"bad host.example.com".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_tag() {
+        let _x = Tag::try_default(); // At least exercise it
+
+        let x = Tag::new(b"tracingrfc".to_vec());
+        assert!(x.is_ok());
+
+        let x = Tag::new(b"012345678901234567890123456789012".to_vec()); // 33 chars-- no go
+        assert!(x.is_err());
+
+        let x = Tag::new("🩡".as_bytes().to_vec()); // Non-ASCII-- no go
+        assert!(x.is_err());
+    }
+
+    #[test]
+    fn test_content_separator() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(None)
+            .build();
+        for (msg, golden) in [
+            ("[not a pid]", " bree prototyping: [not a pid]"),
+            (":-)", " bree prototyping: :-)"),
+            ("Hello, world!", " bree prototyping: Hello, world!"),
+        ] {
+            let rsp = f
+                .format(
+                    Level::LOG_INFO,
+                    msg,
+                    Some(std::time::UNIX_EPOCH.into()),
+                    None,
+                )
+                .unwrap();
+            assert!(rsp.ends_with(golden.as_bytes()));
+        }
+
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(Some(123))
+            .with_content_separator(ContentSep::Space)
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "[x]",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        assert!(rsp.ends_with(b" bree prototyping[123] [x]"));
+
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(None)
+            .with_content_separator(ContentSep::Colon)
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "abc",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        assert!(rsp.ends_with(b" bree prototyping:abc"));
+
+        // An empty message gets no separator at all
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        assert!(rsp.ends_with(b" bree prototyping"));
+    }
+
+    #[test]
+    fn tag_override() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(None)
+            .build();
+        let ts = Some(Utc.timestamp_opt(1000000000, 0).unwrap());
+        let tag_of = |extras: &Extras<'_>| {
+            let buf = f
+                .format_with(Level::LOG_INFO, "Hello", ts, None, extras)
+                .unwrap();
+            let text = String::from_utf8(buf).unwrap();
+            text.split(' ').nth(5).unwrap().to_string()
+        };
+        assert_eq!(tag_of(&Extras::new()), "prototyping:");
+        assert_eq!(tag_of(&Extras::new().appname("tenantx")), "tenantx:");
+        // Invalid tags fall back to the configured one
+        assert_eq!(tag_of(&Extras::new().appname("tenant x")), "prototyping:");
+        assert_eq!(tag_of(&Extras::new().appname("tenant-x")), "prototyping:");
+
+        // unless they pass lenient validation, where that's turned on
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .with_lenient_validation(true)
+            .pid(None)
+            .build();
+        let tag_of = |extras: &Extras<'_>| {
+            let buf = f
+                .format_with(Level::LOG_INFO, "Hello", ts, None, extras)
+                .unwrap();
+            let text = String::from_utf8(buf).unwrap();
+            text.split(' ').nth(5).unwrap().to_string()
+        };
+        assert_eq!(tag_of(&Extras::new().appname("tenant-x")), "tenant-x:");
+        assert_eq!(tag_of(&Extras::new().appname("tenant x")), "prototyping:");
+        assert_eq!(
+            tag_of(&Extras::new().appname(&"x".repeat(65))),
+            "prototyping:"
+        );
+    }
+
+    #[test]
+    fn format_to_string() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(Some(123))
+            .build();
+        let rsp = f
+            .format_to_string(
+                Level::LOG_INFO,
+                "Hello, world!",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        assert!(rsp.starts_with("<14>"));
+        assert!(rsp.ends_with(" bree prototyping[123]: Hello, world!"));
+    }
+
+    #[test]
+    fn debug() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .facility(Facility::LOG_DAEMON)
+            .build();
+        let dbg = format!("{:?}", f);
+        assert!(dbg.contains("Rfc3164Hostname(\"bree\")"));
+        assert!(dbg.contains("Tag(\"prototyping\")"));
+        assert!(dbg.contains("LOG_DAEMON"));
+    }
+
+    #[test]
+    fn lenient_tag() {
+        let tag = "my-service-worker_0123456789".to_string();
+        assert!(Rfc3164::builder()
+            .unwrap()
+            .tag_as_string(tag.clone())
+            .and_then(Rfc3164Builder::try_build)
+            .is_err());
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .with_lenient_validation(true)
+            .tag_as_string(tag)
+            .unwrap()
+            .pid(None)
+            .build();
+        let rsp = f
+            .format_to_string(Level::LOG_INFO, "Hello", None, None)
+            .unwrap();
+        assert!(rsp.ends_with(" bree my-service-worker_0123456789: Hello"));
+
+        assert!(Tag::new_lenient(vec![b'a'; TAG_LENIENT_MAX_LEN]).is_ok());
+        assert!(Tag::new_lenient(vec![b'a'; TAG_LENIENT_MAX_LEN + 1]).is_err());
+        assert!(Tag::new_lenient(b"has space".to_vec()).is_err());
+        assert!(Tag::new_lenient(b"colon:".to_vec()).is_err());
+        // Tags no mode accepts are rejected up-front
+        assert!(Rfc3164::builder()
+            .unwrap()
+            .tag_as_string("has space".to_string())
+            .is_err());
+
+        // The mode applies regardless of call order
+        let f = Rfc3164::builder()
+            .unwrap()
+            .tag_as_string("my-service".to_string())
+            .unwrap()
+            .with_lenient_validation(true)
+            .try_build()
+            .unwrap();
+        assert_eq!(f.tag().to_string(), "my-service");
+    }
+
+    #[test]
+    fn tag_separator() {
+        let format = |pid: Option<u32>, sep: &str| {
+            let f = Rfc3164::builder()
+                .unwrap()
+                .hostname_as_string("bree".to_string())
+                .unwrap()
+                .tag_as_string("prototyping".to_string())
+                .unwrap()
+                .pid(pid)
+                .with_tag_separator(sep)
+                .unwrap()
+                .build();
+            f.format_to_string(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap()
+        };
+        assert!(format(Some(123), " - ").ends_with(" bree prototyping[123] - Hello"));
+        assert!(format(None, " - ").ends_with(" bree prototyping - Hello"));
+        assert!(format(Some(123), ": ").ends_with(" bree prototyping[123]: Hello"));
+        assert!(format(None, "|").ends_with(" bree prototyping|Hello"));
+
+        let b = || Rfc3164::builder().unwrap();
+        assert_eq!(
+            b().with_tag_separator(":")
+                .unwrap()
+                .build()
+                .content_separator(),
+            ContentSep::Colon
+        );
+        assert_eq!(
+            b().with_tag_separator(" | ")
+                .unwrap()
+                .build()
+                .content_separator(),
+            ContentSep::Custom(" | ".to_string())
+        );
+        assert_eq!(ContentSep::try_from(" ").unwrap(), ContentSep::Space);
+        // Would run on from the TAG, be taken for the PID, or isn't printable ASCII
+        for bad in ["", "x: ", "1", ": x", "[", "[1]: ", ":\n", "\u{2192} "] {
+            assert!(matches!(
+                b().with_tag_separator(bad),
+                Err(Error::BadTagSeparator { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn getters() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .facility(Facility::LOG_DAEMON)
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(Some(123))
+            .with_content_separator(ContentSep::Space)
+            .escape_unicode(true)
+            .build();
+        assert_eq!(f.facility(), Facility::LOG_DAEMON);
+        assert_eq!(f.hostname().unwrap().to_string(), "bree");
+        assert_eq!(f.tag().to_string(), "prototyping");
+        assert_eq!(f.pid(), Some(123));
+        assert_eq!(f.content_separator(), ContentSep::Space);
+        assert!(f.escape_unicode());
+    }
+
+    #[test]
+    fn set_facility() {
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let f = Rfc3164::builder()
+            .unwrap()
+            .facility(Facility::LOG_DAEMON)
+            .build();
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert!(buf.starts_with(b"<30>"));
+        f.set_facility(Facility::LOG_USER);
+        assert_eq!(f.facility(), Facility::LOG_USER);
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert!(buf.starts_with(b"<14>"));
+        // Clones are independent
+        let g = f.clone();
+        g.set_facility(Facility::LOG_LOCAL7);
+        assert_eq!(f.facility(), Facility::LOG_USER);
+    }
+
+    #[test]
+    fn utc() {
+        let f = Rfc3164::builder()
+            .unwrap()
+            .hostname_as_string("bree".to_string())
+            .unwrap()
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(Some(123))
+            .with_utc(true)
+            .with_clock(Clock::fixed(Utc.timestamp_opt(1_000_000_000, 0).unwrap()))
+            .build();
+        assert!(f.utc());
+        // Both a supplied timestamp & one from the clock should be rendered in UTC
+        let rsp = f
+            .format_to_string(
+                Level::LOG_INFO,
+                "Hello",
+                Some(Utc.timestamp_opt(1_000_086_400, 0).unwrap()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(rsp, "<14>Sep 10 01:46:40 bree prototyping[123]: Hello");
+        let rsp = f
+            .format_to_string(Level::LOG_INFO, "Hello", None, None)
+            .unwrap();
+        assert_eq!(rsp, "<14>Sep  9 01:46:40 bree prototyping[123]: Hello");
+    }
+
+    #[test]
+    fn no_hostname() {
+        let ts = Utc.timestamp_opt(1_000_000_000, 0).unwrap();
+        let f = Rfc3164::builder()
+            .unwrap()
+            .with_hostname(None)
+            .tag_as_string("prototyping".to_string())
+            .unwrap()
+            .pid(Some(123))
+            .build();
+        let rsp = f
+            .format_to_string(Level::LOG_INFO, "Hello", Some(ts), None)
+            .unwrap();
+        assert_eq!(
+            rsp,
+            format!(
+                "<14>{} prototyping[123]: Hello",
+                ts.with_timezone(&Local).format("%b %_d %H:%M:%S")
+            )
+        );
     }
 }
@@ -26,14 +26,17 @@
 //! [rsyslog]: https://www.rsyslog.com/
 
 use crate::{
-    byte_utils::bytes_from_os_str,
-    facility::{Facility, Level},
-    formatter::SyslogFormatter,
+    byte_utils::{ascii_hostname, bytes_from_os_str},
+    facility::{AtomicFacility, Facility, Level},
+    formatter::{Clock, Extras, SyslogFormatter},
 };
 
 use backtrace::Backtrace;
 
 use chrono::prelude::*;
+use tracing_core::Metadata;
+
+use std::collections::BTreeMap;
 
 type StdResult<T, E> = std::result::Result<T, E>;
 
@@ -52,14 +55,34 @@ pub enum Error {
         name: Vec<u8>,
         back: Backtrace,
     },
-    BadIpAddress,
+    /// Failed to retrieve an IP address in lieu of a hostname
+    BadIpAddress {
+        source: local_ip_address::Error,
+        back: Backtrace,
+    },
     BadProcId {
         name: Vec<u8>,
         back: Backtrace,
     },
+    /// Non-compliant SD-ID provided
+    BadSdId {
+        name: Vec<u8>,
+        back: Backtrace,
+    },
+    /// Non-compliant SD-PARAM name provided
+    BadSdName {
+        name: Vec<u8>,
+        back: Backtrace,
+    },
+    /// Non-compliant value for the standard SD-PARAM `name`
+    BadSdParam {
+        name: &'static str,
+        value: String,
+        back: Backtrace,
+    },
     /// Failed to format the `tracing` Event
     BadTracingFormat {
-        source: Box<dyn std::error::Error>,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         back: Backtrace,
     },
     /// Failed to fetch the current executable (via std::env)
@@ -72,6 +95,21 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync + 'static>,
         back: Backtrace,
     },
+    /// Failed to parse a syslog message
+    Parse {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        back: Backtrace,
+    },
+    /// A formatted message was not valid UTF-8
+    NotUtf8 {
+        source: std::string::FromUtf8Error,
+        back: Backtrace,
+    },
+    /// Non-compliant VERSION (it may not be zero)
+    BadVersion {
+        version: u8,
+        back: Backtrace,
+    },
 }
 
 impl std::fmt::Display for Error {
@@ -87,7 +125,11 @@ impl std::fmt::Display for Error {
             Error::BadHostname { name, .. } => {
                 write!(f, "{:?} is not an RFC 5424-compliant host name", name)
             }
-            Error::BadIpAddress => write!(f, "Failed to obtain a local IP address"),
+            Error::BadIpAddress { source, .. } => write!(
+                f,
+                "While attempting to retrieve an IP address for this host, got {}",
+                source
+            ),
             Error::BadTracingFormat { source, .. } => {
                 write!(f, "While formatting an Event or Span, got {}", source)
             }
@@ -104,6 +146,29 @@ impl std::fmt::Display for Error {
             Error::BadProcId { name, back } => {
                 write!(f, "Bad proc id. name: {name:?}, backtrace: {back:?}",)
             }
+            Error::BadSdId { name, .. } => {
+                write!(f, "{:?} is not an RFC 5424-compliant SD-ID", name)
+            }
+            Error::BadSdName { name, .. } => {
+                write!(f, "{:?} is not an RFC 5424-compliant SD-PARAM name", name)
+            }
+            Error::BadSdParam { name, value, .. } => {
+                write!(
+                    f,
+                    "{:?} is not a legal value for the SD-PARAM {}",
+                    value, name
+                )
+            }
+            Error::NotUtf8 { source, .. } => {
+                write!(f, "The formatted message was not UTF-8: {}", source)
+            }
+            Error::Parse { source, .. } => {
+                write!(f, "Failed to parse an RFC 5424 message: {}", source)
+            }
+            Error::BadVersion { version, .. } => {
+                write!(f, "{} is not an RFC 5424-compliant version", version)
+            }
+            _ => write!(f, "RFC 5424 formatting error"),
         }
     }
 }
@@ -115,7 +180,20 @@ impl std::fmt::Debug for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    #[allow(unreachable_patterns)]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::BadIpAddress { source, .. } => Some(source),
+            Error::BadTracingFormat { source, .. }
+            | Error::NoExecutable { source, .. }
+            | Error::NoHostname { source, .. }
+            | Error::Parse { source, .. } => Some(source.as_ref()),
+            Error::NotUtf8 { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -123,14 +201,54 @@ pub type Result<T> = std::result::Result<T, Error>;
 //                                         utility types                                          //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Return `bytes`, or the RFC 5424 NILVALUE ("-") if `bytes` is empty
+///
+/// This is the one path by which the HOSTNAME, APP-NAME & PROCID fields are rendered, both when
+/// formatting a message & in their [`Display`] implementations. It deals in raw bytes & so cannot
+/// fail.
+///
+/// [`Display`]: std::fmt::Display
+fn nil_or(bytes: &[u8]) -> &[u8] {
+    if bytes.is_empty() {
+        b"-"
+    } else {
+        bytes
+    }
+}
+
+/// The RFC's upper bounds on the lengths of the HOSTNAME, APP-NAME & PROCID header fields
+const HOSTNAME_MAX_LEN: usize = 255;
+const APP_NAME_MAX_LEN: usize = 48;
+/// The upper bound on the length of an APP-NAME under [lenient](AppName::new_lenient) validation
+const APP_NAME_LENIENT_MAX_LEN: usize = 255;
+const PROCID_MAX_LEN: usize = 128;
+
 /// A [`Vec<u8>`] instance with the additional constraint that it must be less than 256 bytes
 /// of ASCII.
+#[derive(Clone)]
 pub struct Hostname(Vec<u8>);
 
+impl std::fmt::Debug for Hostname {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.debug_tuple("Hostname")
+            .field(&String::from_utf8_lossy(&self.0))
+            .finish()
+    }
+}
+
+impl std::fmt::Display for Hostname {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.write_str(&String::from_utf8_lossy(nil_or(&self.0)))
+    }
+}
+
 impl Hostname {
     /// An RFC 5424-compliant hostname is at most 255 bytes of ASCII
+    ///
+    /// The RFC requires at least one character, but mandates the NILVALUE ("-") for an absent
+    /// hostname; an empty `bytes` is accepted & will be rendered as such.
     pub fn new(bytes: Vec<u8>) -> Result<Hostname> {
-        if bytes.is_ascii() && bytes.len() < 256 {
+        if bytes.is_ascii() && bytes.len() <= HOSTNAME_MAX_LEN {
             Ok(Hostname(bytes))
         } else {
             Err(Error::BadHostname {
@@ -139,6 +257,40 @@ impl Hostname {
             })
         }
     }
+    /// Build a [`Hostname`] from an IP address
+    ///
+    /// IPv4 addresses are rendered in dotted decimal notation & IPv6 addresses in their RFC 5952
+    /// canonical form; if `bracket_v6` is set, the latter are enclosed in square brackets
+    /// (`[2001:db8::1]`), as is customary for IPv6 literals elsewhere (in URIs, e.g.). The RFC
+    /// itself calls for the bare address, so that's what this crate uses when it falls back to an
+    /// IP address.
+    pub fn from_ip(ip: std::net::IpAddr, bracket_v6: bool) -> Result<Hostname> {
+        let name = match ip {
+            std::net::IpAddr::V6(ip) if bracket_v6 => format!("[{}]", ip),
+            ip => ip.to_string(),
+        };
+        Hostname::new(name.into_bytes())
+    }
+    /// The local IP address of `socket`
+    ///
+    /// When sending over TCP, this is the address the OS selected for the route to the syslog
+    /// daemon, & hence the address the daemon will see the message as coming from-- which may not
+    /// be that chosen by [`Hostname::default`] on multi-homed hosts. E.g.
+    ///
+    /// ```rust,no_run
+    /// use tracing_rfc_5424::{rfc5424::{Hostname, Rfc5424}, transport::TcpTransport};
+    /// let transport = TcpTransport::new("syslog.example.com:601").unwrap();
+    /// let formatter = Rfc5424::builder()
+    ///     .hostname(Hostname::from_local_addr(transport.stream()).unwrap())
+    ///     .build();
+    /// ```
+    pub fn from_local_addr(socket: &std::net::TcpStream) -> Result<Hostname> {
+        let addr = socket.local_addr().map_err(|err| Error::NoHostname {
+            source: Box::new(err),
+            back: Backtrace::new(),
+        })?;
+        Hostname::from_ip(addr.ip(), false)
+    }
 }
 
 impl std::default::Default for Hostname {
@@ -156,34 +308,54 @@ impl std::default::Default for Hostname {
     /// [gethostname()], then uses [netlink] to try & find an IP address. I'd like to come back & tighten
     /// this up.
     ///
+    /// With the `idna` feature (enabled by default), a non-ASCII hostname is first converted to its
+    /// ASCII-compatible encoding (e.g. "bücher" becomes "xn--bcher-kva").
+    ///
+    /// If neither can be had, the NILVALUE is used; see [`Hostname::try_default`] to find out
+    /// instead.
+    ///
     /// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
     /// [gethostname()]: https://man7.org/linux/man-pages/man2/gethostname.2.html
     /// [netlink]: https://man7.org/linux/man-pages/man7/netlink.7.html
     fn default() -> Self {
+        Hostname::try_default().unwrap_or_else(|_| Hostname(b"-".to_vec()))
+    }
+}
+
+impl Hostname {
+    /// Attempt to figure-out an RFC 5424-compliant hostname, as [`Hostname::default`] does, but
+    /// report failure rather than falling back to the NILVALUE
+    ///
+    /// Should both the hostname & the IP address fallback be unavailable, the error describes the
+    /// latter failure (see [`Error::BadIpAddress`]), with the underlying
+    /// [`local_ip_address::Error`] as its [`source`](std::error::Error::source).
+    pub fn try_default() -> Result<Hostname> {
+        Hostname::from_host_or_ip(hostname::get(), local_ip_address::local_ip)
+    }
+    /// Build a [`Hostname`] from the result of `hostname::get()`, falling back to the result of
+    /// `local_ip` should that fail
+    fn from_host_or_ip(
+        hostname: std::io::Result<std::ffi::OsString>,
+        local_ip: impl FnOnce() -> StdResult<std::net::IpAddr, local_ip_address::Error>,
+    ) -> Result<Hostname> {
         // `hostname::get()` returns an `Result<OsString,_>`, which is really kind of a hassle to work
         // with...
-        hostname::get()
+        hostname
             .map_err(|err| Error::NoHostname {
                 source: Box::new(err),
                 back: Backtrace::new(),
             })
             // 👇 :=> StdResult<Hostname, Error>
-            .and_then(|hn| Hostname::new(bytes_from_os_str(hn)))
+            .and_then(|hn| Hostname::new(ascii_hostname(bytes_from_os_str(hn))))
             // 👇 will return the Ok(Hostname), or call the closure :=> StdResult<Hostname, Error>
             .or_else(|_err| {
-                let ip: StdResult<std::net::IpAddr, Error> =
-                    local_ip_address::local_ip().map_err(|_| Error::BadIpAddress);
-                ip.and_then(|ip| {
-                    let buf = ip.to_string().into_bytes();
-                    if buf.len() < 256 {
-                        Ok(Hostname(buf))
-                    } else {
-                        Err(Error::BadIpAddress)
-                    }
-                })
+                local_ip()
+                    .map_err(|err| Error::BadIpAddress {
+                        source: err,
+                        back: Backtrace::new(),
+                    })
+                    .and_then(|ip| Hostname::from_ip(ip, false))
             }) // 👈 :=> StdResult<Hostname, Error>
-            .or_else::<Error, _>(|_| Ok(Hostname(b"-".to_vec())))
-            .unwrap()
     }
 }
 
@@ -196,17 +368,48 @@ impl std::convert::TryFrom<String> for Hostname {
 
 /// A string with the additional constraint contstraing that it is less than forty-nine bytes of
 /// ASCII.
+#[derive(Clone)]
 pub struct AppName(Vec<u8>);
 
+impl std::fmt::Debug for AppName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.debug_tuple("AppName")
+            .field(&String::from_utf8_lossy(&self.0))
+            .finish()
+    }
+}
+
 impl std::fmt::Display for AppName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
-        write!(f, "{}", std::str::from_utf8(&self.0).unwrap())
+        f.write_str(&String::from_utf8_lossy(nil_or(&self.0)))
     }
 }
 
 impl AppName {
+    /// An RFC 5424-compliant app name is at most 48 bytes of ASCII
+    ///
+    /// As with [`Hostname::new`], an empty `bytes` will be rendered as the NILVALUE.
     pub fn new(bytes: Vec<u8>) -> Result<AppName> {
-        if bytes.is_ascii() && bytes.len() < 49 {
+        if bytes.is_ascii() && bytes.len() <= APP_NAME_MAX_LEN {
+            Ok(AppName(bytes))
+        } else {
+            Err(Error::BadAppName {
+                name: bytes,
+                back: Backtrace::new(),
+            })
+        }
+    }
+    /// Construct an [`AppName`] under relaxed validation rules
+    ///
+    /// Many collectors tolerate APP-NAMEs longer than the RFC's forty-eight bytes; this constructor
+    /// accepts between one & 255 bytes of printable ASCII (no spaces, which would corrupt the
+    /// header). An empty APP-NAME is rejected; use [`Rfc5424Builder::with_nil_appname`] to send the
+    /// NILVALUE deliberately.
+    pub fn new_lenient(bytes: Vec<u8>) -> Result<AppName> {
+        if !bytes.is_empty()
+            && bytes.len() <= APP_NAME_LENIENT_MAX_LEN
+            && bytes.iter().all(|&x| x > 32 && x < 127)
+        {
             Ok(AppName(bytes))
         } else {
             Err(Error::BadAppName {
@@ -236,53 +439,55 @@ impl std::default::Default for AppName {
     ///
     /// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
     fn default() -> Self {
-        std::env::current_exe() // :=> StdResult<PathBuf, std::io::Error>
+        AppName::try_default().unwrap_or_else(|_| AppName(b"-".to_vec()))
+    }
+}
+
+impl AppName {
+    /// Attempt to figure-out an RFC 5424 Application Name, as [`AppName::default`] does, but
+    /// report failure to retrieve the current executable rather than falling back to "-"
+    pub fn try_default() -> Result<AppName> {
+        std::env::current_exe()
+            .map(|pbuf| AppName::from_file_name(pbuf.file_name()))
             .map_err(|err| Error::NoExecutable {
                 source: Box::new(err),
                 back: Backtrace::new(),
             })
-            .and_then(|pbuf| {
-                AppName::new(match pbuf.file_name() {
-                    // Arrrghhhh... wicked copy!
-                    Some(os_str) => bytes_from_os_str(os_str.to_os_string()),
-                    None => vec![b'-'],
-                })
-            })
-            .unwrap()
     }
-}
-
-#[cfg(test)]
-mod test {
-
-    use super::*;
-
-    #[test]
-    fn app_name() {
-        let _x = AppName::default(); // At least _exercise_ `Default`
-
-        let x: &[u8] = b"0123456789012345678901234567890123456789012345678";
-        let v: Vec<u8> = x.into();
-        assert!(AppName::new(v).is_err());
-
-        let x: &[u8] = b"udp-test";
-        let v: Vec<u8> = x.into();
-        assert!(AppName::new(v).is_ok());
+    /// Build an [`AppName`] from an executable's file name, falling back to "-" if there is none,
+    /// or if it won't do (it's too long, say, or-- on Unix-- not even UTF-8)
+    fn from_file_name(file_name: Option<&std::ffi::OsStr>) -> AppName {
+        file_name
+            // Arrrghhhh... wicked copy!
+            .and_then(|os_str| AppName::new(bytes_from_os_str(os_str.to_os_string())).ok())
+            .unwrap_or_else(|| AppName(b"-".to_vec()))
     }
 }
 
 /// A string with the additional constraint contstraing that it is less than 129 bytes of ASCII.
+#[derive(Clone)]
 pub struct ProcId(Vec<u8>);
 
+impl std::fmt::Debug for ProcId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
+        f.debug_tuple("ProcId")
+            .field(&String::from_utf8_lossy(&self.0))
+            .finish()
+    }
+}
+
 impl std::fmt::Display for ProcId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> StdResult<(), std::fmt::Error> {
-        write!(f, "{}", std::str::from_utf8(&self.0).unwrap())
+        f.write_str(&String::from_utf8_lossy(nil_or(&self.0)))
     }
 }
 
 impl ProcId {
+    /// An RFC 5424-compliant process ID is at most 128 bytes of ASCII
+    ///
+    /// As with [`Hostname::new`], an empty `bytes` will be rendered as the NILVALUE.
     pub fn new(bytes: Vec<u8>) -> Result<ProcId> {
-        if bytes.is_ascii() && bytes.len() < 129 {
+        if bytes.is_ascii() && bytes.len() <= PROCID_MAX_LEN {
             Ok(ProcId(bytes))
         } else {
             Err(Error::BadProcId {
@@ -291,6 +496,16 @@ impl ProcId {
             })
         }
     }
+    /// Use a UUID (in its hyphenated form) as the process ID
+    ///
+    /// The RFC notes that PROCID has "no interoperable meaning, except that a change in the value
+    /// indicates there has been a discontinuity in syslog reporting", so a stable per-instance
+    /// identifier is a legitimate choice-- & a more useful one than the OS pid for correlating
+    /// messages across a fleet.
+    #[cfg(feature = "uuid")]
+    pub fn from_uuid(uuid: uuid::Uuid) -> Result<ProcId> {
+        ProcId::new(uuid.hyphenated().to_string().into_bytes())
+    }
 }
 
 impl std::convert::TryFrom<String> for ProcId {
@@ -311,113 +526,2767 @@ impl std::default::Default for ProcId {
     ///
     /// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
     fn default() -> Self {
-        ProcId::new(format!("{}", std::process::id()).into_bytes()).unwrap()
+        ProcId(format!("{}", std::process::id()).into_bytes())
     }
 }
 
-/// A syslog formatter that produces RFC [5424]-conformant syslog messages.
+/// The SD-ID under which [`tracing`] metadata is reported, absent any other configuration
 ///
-/// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
-pub struct Rfc5424 {
-    facility: Facility,
-    hostname: Hostname,
-    appname: AppName,
-    pid: ProcId,
-    with_bom: bool,
+/// 64700 is a placeholder Private Enterprise Number; callers with their own PEN can substitute an
+/// SD-ID of their choosing via [`Rfc5424Builder::with_tracing_metadata_sdid`].
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+pub const DEFAULT_TRACING_METADATA_SDID: &str = "tracing-meta@64700";
+
+/// The SD-ID under which event fields are reported; see [`Rfc5424Builder::with_all_fields`]
+pub const FIELDS_SDID: &str = "fields@64700";
+
+/// The SD-ID under which the fields of enclosing spans are reported; see
+/// [`Rfc5424Builder::with_span_fields`]
+pub const SPAN_FIELDS_SDID: &str = "span@64700";
+
+/// Return true if `name` is a legal RFC 5424 SD-NAME
+///
+/// SD-NAME = 1*32PRINTUSASCII ; except '=', SP, ']', %d34 (")
+fn is_sd_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .iter()
+            .all(|&x| x > 32 && x < 127 && x != b'=' && x != b']' && x != b'"')
 }
 
-impl std::default::Default for Rfc5424 {
-    fn default() -> Self {
-        Rfc5424 {
-            facility: Facility::LOG_USER,
-            hostname: Hostname::default(),
-            appname: AppName::default(),
-            pid: ProcId::default(),
-            with_bom: false,
+/// Return true if `name` is a legal RFC 5424 MSGID (other than the NILVALUE)
+fn is_msgid(name: &[u8]) -> bool {
+    !name.is_empty() && name.len() <= 32 && name.iter().all(|&x| x > 32 && x < 127) && name != b"-"
+}
+
+/// Return true if `name` is a legal RFC 5424 APP-NAME (other than the NILVALUE)
+fn is_app_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name.len() <= APP_NAME_MAX_LEN
+        && name.iter().all(|&x| x > 32 && x < 127)
+        && name != b"-"
+}
+
+/// The UTF-8 byte order mark
+const BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+
+/// Append `msg` to `buf`, escaping control characters if `escape` is true (or every character if
+/// `escape_unicode` is), stopping before `buf` would exceed `limit` bytes; return true if at least
+/// one character was written
+fn write_msg(
+    buf: &mut Vec<u8>,
+    msg: &str,
+    escape: bool,
+    escape_unicode: bool,
+    limit: Option<usize>,
+) -> bool {
+    let fits = |len: usize| match limit {
+        Some(limit) => len <= limit,
+        None => true,
+    };
+    let mut wrote = false;
+    let mut utf8 = [0u8; 4];
+    for c in msg.chars() {
+        // Each character (or escape sequence) is written whole, or not at all
+        if escape_unicode {
+            let escaped = c.escape_unicode();
+            if !fits(buf.len() + escaped.len()) {
+                break;
+            }
+            // Escape sequences are pure ASCII
+            buf.extend(escaped.map(|c| c as u8));
+        } else if escape && c.is_control() {
+            let escaped = c.escape_default().to_string();
+            if !fits(buf.len() + escaped.len()) {
+                break;
+            }
+            buf.extend_from_slice(escaped.as_bytes());
+        } else {
+            let encoded = c.encode_utf8(&mut utf8);
+            if !fits(buf.len() + encoded.len()) {
+                break;
+            }
+            buf.extend_from_slice(encoded.as_bytes());
         }
+        wrote = true;
     }
+    wrote
 }
 
-pub struct Rfc5424Builder {
-    imp: Rfc5424,
+/// Which pieces of [`tracing`] [`Metadata`] to report as structured data
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+#[derive(Clone, Copy, Debug, Default)]
+struct TracingMetadata {
+    target: bool,
+    module: bool,
+    source_location: bool,
+    root_span_target: bool,
 }
 
-impl Rfc5424Builder {
-    pub fn facility(mut self, facility: Facility) -> Self {
-        self.imp.facility = facility;
-        self
+impl TracingMetadata {
+    fn any(&self) -> bool {
+        self.target || self.module || self.source_location || self.root_span_target
     }
-    pub fn hostname(mut self, hostname: Hostname) -> Self {
-        self.imp.hostname = hostname;
-        self
+    fn enabled(&self, param: MetaParam) -> bool {
+        match param {
+            MetaParam::Target => self.target,
+            MetaParam::RootTarget => self.root_span_target,
+            MetaParam::Module => self.module,
+            MetaParam::File | MetaParam::Line => self.source_location,
+        }
     }
-    pub fn hostname_as_string(mut self, hostname: String) -> Result<Self> {
-        self.imp.hostname = Hostname::try_from(hostname)?;
-        Ok(self)
+}
+
+/// A single piece of [`tracing`] [`Metadata`] that may be reported as an SD-PARAM
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MetaParam {
+    Target,
+    Module,
+    File,
+    Line,
+    /// The target of the outermost span enclosing the event; supplied via [`Extras`]
+    RootTarget,
+}
+
+impl MetaParam {
+    fn name(&self) -> &'static str {
+        match self {
+            MetaParam::Target => "target",
+            MetaParam::Module => "module",
+            MetaParam::File => "file",
+            MetaParam::Line => "line",
+            MetaParam::RootTarget => "root-target",
+        }
     }
-    pub fn appname_as_string(mut self, appname: String) -> Result<Self> {
-        self.imp.appname = AppName::try_from(appname)?;
-        Ok(self)
+    /// Retrieve this parameter's value from `meta` (or `extras`), escaped & ready to be written
+    /// between double-quotes, if it's present
+    ///
+    /// Empty values (a blank target, say) are treated as absent; an element made up solely of
+    /// absent parameters is omitted altogether.
+    fn value(&self, meta: &Metadata<'_>, extras: &Extras<'_>) -> Option<String> {
+        match self {
+            MetaParam::Target => Some(meta.target().to_string()),
+            MetaParam::Module => meta.module_path().map(str::to_string),
+            MetaParam::File => meta.file().map(str::to_string),
+            MetaParam::Line => meta.line().map(|line| line.to_string()),
+            MetaParam::RootTarget => extras.root_span_target.map(str::to_string),
+        }
+        .filter(|value| !value.is_empty())
+        .map(|value| escape_sd_value(&value))
     }
-    pub fn pid_as_string(mut self, pid: String) -> Result<Self> {
-        self.imp.pid = ProcId::try_from(pid)?;
-        Ok(self)
+}
+
+/// SD-PARAM names to use in place of the defaults given by [`MetaParam::name`]
+#[derive(Clone, Debug, Default)]
+struct MetaParamNames {
+    target: Option<String>,
+    module: Option<String>,
+    file: Option<String>,
+    line: Option<String>,
+}
+
+impl MetaParamNames {
+    fn name(&self, param: MetaParam) -> &str {
+        match param {
+            MetaParam::Target => self.target.as_deref(),
+            MetaParam::Module => self.module.as_deref(),
+            MetaParam::File => self.file.as_deref(),
+            MetaParam::Line => self.line.as_deref(),
+            MetaParam::RootTarget => None,
+        }
+        .unwrap_or_else(|| param.name())
     }
-    pub fn with_bom(mut self, with_bom: bool) -> Self {
-        self.imp.with_bom = with_bom;
-        self
+}
+
+/// Validate `name` as an SD-PARAM name for one of the [`tracing`] metadata fields
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+fn meta_param_name<N: Into<String>>(name: N) -> Result<Option<String>> {
+    let name = name.into();
+    if is_sd_name(name.as_bytes()) {
+        Ok(Some(name))
+    } else {
+        Err(Error::BadSdName {
+            name: name.into_bytes(),
+            back: Backtrace::new(),
+        })
     }
-    pub fn build(self) -> Rfc5424 {
-        self.imp
+}
+
+/// Escape `value` for use as an RFC 5424 PARAM-VALUE
+///
+/// The RFC requires that '"', '\\' & ']' be escaped with a backslash. It makes no provision for
+/// control characters, which some strict parsers will reject, so we replace them with their Rust
+/// escape sequences (`\t`, `\n`, `\u{1b}` & so on)-- with the leading backslash itself escaped, so
+/// that a compliant parser will recover the escape sequence verbatim.
+fn escape_sd_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | ']' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => {
+                out.push('\\');
+                out.extend(c.escape_default());
+            }
+            c => out.push(c),
+        }
     }
+    out
 }
 
-impl Rfc5424 {
-    pub fn builder() -> Rfc5424Builder {
-        Rfc5424Builder {
-            imp: Rfc5424::default(),
+/// All [`MetaParam`]s, in the order in which they appear in the single SD element
+const ALL_META_PARAMS: &[MetaParam] = &[
+    MetaParam::Target,
+    MetaParam::Module,
+    MetaParam::File,
+    MetaParam::Line,
+    MetaParam::RootTarget,
+];
+
+/// The SD elements (& the parameters in each) emitted when [`Rfc5424Builder::with_split_sd`] is set
+const SPLIT_SD_ELEMENTS: &[(&str, &[MetaParam])] = &[
+    ("tracing-loc@64700", &[MetaParam::File, MetaParam::Line]),
+    (
+        "tracing-target@64700",
+        &[MetaParam::Target, MetaParam::Module, MetaParam::RootTarget],
+    ),
+];
+
+/// The parameters of the standard `timeQuality` SD element (RFC 5424 §7.1)
+///
+/// This describes the reliability of the timestamps in the messages we send; collectors that
+/// understand it can, e.g., flag messages from hosts whose clocks aren't synchronized.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeQuality {
+    /// Whether the originator knows its time zone
+    pub tz_known: bool,
+    /// Whether the originator's clock is synchronized to a reliable external source (via NTP, say)
+    pub is_synced: bool,
+    /// How accurate the originator believes its clock to be, in microseconds; per the RFC, this is
+    /// only reported if `is_synced` is true
+    pub sync_accuracy: Option<u64>,
+}
+
+/// The parameters of the standard `origin` SD element (RFC 5424 §7.2)
+///
+/// This describes the originator of the messages we send. All fields are optional; those not set
+/// are omitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Origin {
+    /// The originator's IP address(es)
+    pub ip: Vec<std::net::IpAddr>,
+    /// An SMI Network Management Private Enterprise Code (e.g. "32473" or "32473.1.2")
+    pub enterprise_id: Option<String>,
+    /// The name of the software generating the messages (at most 48 characters)
+    pub software: Option<String>,
+    /// The version of that software (at most 32 characters)
+    pub sw_version: Option<String>,
+}
+
+impl Origin {
+    /// Check this [`Origin`] against the RFC's constraints
+    fn validate(&self) -> Result<()> {
+        fn bad(name: &'static str, value: &str) -> Error {
+            Error::BadSdParam {
+                name,
+                value: value.to_string(),
+                back: Backtrace::new(),
+            }
+        }
+        if let Some(id) = &self.enterprise_id {
+            if id.is_empty()
+                || id
+                    .split('.')
+                    .any(|part| part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()))
+            {
+                return Err(bad("enterpriseId", id));
+            }
+        }
+        if let Some(software) = &self.software {
+            if software.is_empty() || software.chars().count() > 48 {
+                return Err(bad("software", software));
+            }
+        }
+        if let Some(sw_version) = &self.sw_version {
+            if sw_version.is_empty() || sw_version.chars().count() > 32 {
+                return Err(bad("swVersion", sw_version));
+            }
         }
+        Ok(())
     }
 }
 
-impl SyslogFormatter for Rfc5424 {
-    type Error = Error;
-    type Output = Vec<u8>;
-    fn format(
-        &self,
-        level: Level,
-        msg: &str,
-        timestamp: Option<DateTime<Utc>>,
-    ) -> Result<Self::Output> {
-        let mut buf = format!(
-            "<{}>1 {} ",
-            self.facility as u8 | level as u8,
-            timestamp.unwrap_or(Utc::now()).to_rfc3339()
-        )
-        .into_bytes();
+/// Write `timestamp` in the form produced by [`DateTime::to_rfc3339`]
+///
+/// chrono's formatting machinery allocates on each call; this doesn't.
+fn write_timestamp(buf: &mut Vec<u8>, timestamp: &DateTime<Utc>) {
+    use std::io::Write;
+    let year = timestamp.year();
+    if (0..=9999).contains(&year) {
+        write!(buf, "{:04}", year).unwrap();
+    } else {
+        write!(buf, "{:+05}", year).unwrap();
+    }
+    // Leap seconds are represented as a nanosecond value of one billion or more
+    let nanos = timestamp.nanosecond();
+    write!(
+        buf,
+        "-{:02}-{:02}T{:02}:{:02}:{:02}",
+        timestamp.month(),
+        timestamp.day(),
+        timestamp.hour(),
+        timestamp.minute(),
+        timestamp.second() + nanos / 1_000_000_000,
+    )
+    .unwrap();
+    // As few fractional digits as possible, in groups of three
+    match nanos % 1_000_000_000 {
+        0 => (),
+        n if n % 1_000_000 == 0 => write!(buf, ".{:03}", n / 1_000_000).unwrap(),
+        n if n % 1_000 == 0 => write!(buf, ".{:06}", n / 1_000).unwrap(),
+        n => write!(buf, ".{:09}", n).unwrap(),
+    }
+    buf.extend_from_slice(b"+00:00");
+}
 
-        use bytes::buf::BufMut;
-        buf.put_slice(&self.hostname.0);
+/// The SD-ID of the element marking a STRUCTURED-DATA section as truncated; see
+/// [`Rfc5424Builder::with_max_sd_length`]
+pub const TRUNCATION_MARKER_SDID: &str = "syslog-tracing@64700";
 
-        buf.put_slice(format!(" {} {} - - ", self.appname, self.pid).as_bytes());
+/// The element marking a STRUCTURED-DATA section as truncated
+const TRUNCATION_MARKER: &str = "[syslog-tracing@64700 truncated=\"1\"]";
 
-        // From the RFC
+/// The sources of structured data
+///
+/// [`Rfc5424`] writes SD elements from each source in turn, by default in the order in which they
+/// are declared here. When a limit is placed on the size of the STRUCTURED-DATA section (see
+/// [`Rfc5424Builder::with_max_sd_length`]), elements written earlier are the ones preserved; the
+/// order may be changed via [`Rfc5424Builder::with_sd_order`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SdSource {
+    /// The standard `timeQuality` & `origin` elements
+    Standard,
+    /// Elements given to [`Rfc5424Builder::with_static_sd`]
+    Static,
+    /// [`tracing`] metadata
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    TracingMetadata,
+    /// The event's fields; see [`Rfc5424Builder::with_all_fields`]
+    Fields,
+    /// The fields of the spans enclosing the event; see [`Rfc5424Builder::with_span_fields`]
+    SpanFields,
+    /// Elements attached to the individual event; see [`IntoSdElements`]
+    PerMessage,
+}
 
-        // "The character set used in MSG SHOULD be UNICODE, encoded using UTF-8 as specified in
-        // [RFC3629].  If the syslog application cannot encode the MSG in Unicode, it MAY use
-        // any other encoding."
+const DEFAULT_SD_ORDER: [SdSource; 6] = [
+    SdSource::Standard,
+    SdSource::Static,
+    SdSource::TracingMetadata,
+    SdSource::Fields,
+    SdSource::SpanFields,
+    SdSource::PerMessage,
+];
 
-        // "If a syslog application encodes MSG in UTF-8, the string MUST start with the Unicode
-        // byte order mask (BOM), which for UTF-8 is ABNF %xEF.BB.BF.  The syslog application
-        // MUST encode in the "shortest form" and MAY use any valid UTF-8 sequence."
-        if self.with_bom {
-            buf.put_u8(0xef_u8);
-            buf.put_u8(0xbb_u8);
-            buf.put_u8(0xbf_u8);
+/// Writes SD elements to a buffer, optionally within a byte budget
+///
+/// Once a parameter doesn't fit, it & all subsequent parameters are dropped, and the truncation
+/// marker (if wanted) written in their stead. Space for the marker is reserved up-front.
+struct SdWriter<'a> {
+    buf: &'a mut Vec<u8>,
+    remaining: Option<usize>,
+    marker: bool,
+    truncated: bool,
+    wrote: bool,
+}
+
+impl<'a> SdWriter<'a> {
+    fn new(buf: &'a mut Vec<u8>, max_length: Option<usize>, marker: bool) -> SdWriter<'a> {
+        // Only reserve space for the marker if it will fit at all
+        let marker = marker && matches!(max_length, Some(max) if max >= TRUNCATION_MARKER.len());
+        SdWriter {
+            buf,
+            remaining: max_length.map(|max| {
+                if marker {
+                    max - TRUNCATION_MARKER.len()
+                } else {
+                    max
+                }
+            }),
+            marker,
+            truncated: false,
+            wrote: false,
+        }
+    }
+    /// Charge `len` bytes against our budget; return false (& note that we've truncated) if we
+    /// can't afford them
+    fn reserve(&mut self, len: usize) -> bool {
+        if self.truncated {
+            return false;
+        }
+        match self.remaining.as_mut() {
+            Some(remaining) if *remaining < len => {
+                self.truncated = true;
+                false
+            }
+            Some(remaining) => {
+                *remaining -= len;
+                true
+            }
+            None => true,
+        }
+    }
+    /// Write the element `id` with `params` (whose values must already be escaped), if it has
+    /// any parameters (& as many of them as fit)
+    fn element<N, V>(&mut self, id: &str, params: impl IntoIterator<Item = (N, V)>)
+    where
+        N: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut opened = false;
+        for (name, value) in params {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            // ` name="value"`, plus (if this is the first) `[id` & the closing `]`
+            let len = name.len() + value.len() + 4;
+            if !self.reserve(if opened { len } else { len + id.len() + 2 }) {
+                break;
+            }
+            if !opened {
+                self.buf.push(b'[');
+                self.buf.extend_from_slice(id.as_bytes());
+                opened = true;
+            }
+            self.buf.push(b' ');
+            self.buf.extend_from_slice(name.as_bytes());
+            self.buf.extend_from_slice(b"=\"");
+            self.buf.extend_from_slice(value.as_bytes());
+            self.buf.push(b'"');
+        }
+        if opened {
+            self.buf.push(b']');
+            self.wrote = true;
+        }
+    }
+    /// Write the element `id`, which has no parameters
+    fn empty_element(&mut self, id: &str) {
+        if self.reserve(id.len() + 2) {
+            self.buf.push(b'[');
+            self.buf.extend_from_slice(id.as_bytes());
+            self.buf.push(b']');
+            self.wrote = true;
+        }
+    }
+    /// Write `bytes`, one or more complete (& escaped) elements, if they fit in their entirety;
+    /// return false (without noting truncation) if they don't, so the caller can fall back to
+    /// writing them parameter-by-parameter
+    fn raw(&mut self, bytes: &[u8]) -> bool {
+        if self.truncated || matches!(self.remaining, Some(remaining) if remaining < bytes.len()) {
+            return false;
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= bytes.len();
+        }
+        self.buf.extend_from_slice(bytes);
+        self.wrote |= !bytes.is_empty();
+        true
+    }
+    /// Write the truncation marker, if called for; return true if anything at all was written
+    fn finish(self) -> bool {
+        if self.truncated && self.marker {
+            self.buf.extend_from_slice(TRUNCATION_MARKER.as_bytes());
+            return true;
         }
+        self.wrote
+    }
+}
 
-        buf.put_slice(msg.as_bytes());
+/// Write the standard `timeQuality` SD element
+fn write_time_quality(sd: &mut SdWriter<'_>, tq: &TimeQuality) {
+    let flag = |x: bool| if x { "1" } else { "0" }.to_string();
+    let params = [
+        Some(("tzKnown", flag(tq.tz_known))),
+        Some(("isSynced", flag(tq.is_synced))),
+        tq.sync_accuracy
+            .filter(|_| tq.is_synced)
+            .map(|accuracy| ("syncAccuracy", accuracy.to_string())),
+    ];
+    sd.element("timeQuality", params.into_iter().flatten())
+}
 
-        Ok(buf)
+/// Write the standard `origin` SD element (unless there's nothing to put in it)
+fn write_origin(sd: &mut SdWriter<'_>, origin: &Origin) {
+    let params = origin
+        .ip
+        .iter()
+        .map(|ip| ("ip", ip.to_string()))
+        .chain(
+            origin
+                .enterprise_id
+                .iter()
+                .map(|id| ("enterpriseId", id.clone())),
+        )
+        .chain(origin.software.iter().map(|sw| ("software", sw.clone())))
+        .chain(origin.sw_version.iter().map(|v| ("swVersion", v.clone())))
+        .map(|(name, value)| (name, escape_sd_value(&value)));
+    sd.element("origin", params)
+}
+
+/// Write key-value fields as an SD element with ID `sdid` (unless there are none to write)
+fn write_fields(sd: &mut SdWriter<'_>, sdid: &str, fields: &[(&str, String)]) {
+    let mut seen = BTreeMap::<&str, usize>::new();
+    let params = fields
+        .iter()
+        .filter(|(name, _)| is_sd_name(name.as_bytes()))
+        .map(|(name, value)| {
+            let count = seen.entry(name).or_insert(0);
+            *count += 1;
+            let name = if *count == 1 {
+                name.to_string()
+            } else {
+                // The suffixed name may run past the 32-character limit; truncate the original to
+                // fit
+                let suffix = format!(".{}", count);
+                format!("{}{}", &name[..name.len().min(32 - suffix.len())], suffix)
+            };
+            (name, escape_sd_value(value))
+        });
+    sd.element(sdid, params)
+}
+
+/// Render span fields as the SD element [`SPAN_FIELDS_SDID`], for the [`Layer`] to cache
+///
+/// [`Layer`]: crate::layer::Layer
+pub(crate) fn render_span_fields(fields: &[(&str, String)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_fields(
+        &mut SdWriter::new(&mut buf, None, false),
+        SPAN_FIELDS_SDID,
+        fields,
+    );
+    buf
+}
+
+/// An SD-ELEMENT supplied with an individual message
+///
+/// Where the standard elements & the `tracing` metadata are configured once, on the formatter,
+/// these are attached to a single [`Event`] via the reserved field [`SD_FIELD`]; see
+/// [`IntoSdElements`].
+///
+/// [`Event`]: tracing::Event
+/// [`SD_FIELD`]: crate::layer::SD_FIELD
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SdElement {
+    id: String,
+    params: Vec<(String, String)>,
+}
+
+impl SdElement {
+    /// Begin an element with SD-ID `id`
+    ///
+    /// Per the RFC, IDs not registered with IANA must be of the form
+    /// `name@<private enterprise number>`, e.g. "exampleSDID@32473".
+    pub fn new<S: Into<String>>(id: S) -> Result<SdElement> {
+        let id = id.into();
+        if !is_sd_name(id.as_bytes()) {
+            return Err(Error::BadSdId {
+                name: id.into_bytes(),
+                back: Backtrace::new(),
+            });
+        }
+        Ok(SdElement {
+            id,
+            params: Vec::new(),
+        })
+    }
+    /// Add the SD-PARAM `name`="`value`"; `value` will be escaped as needed
+    pub fn with_param<N: Into<String>, V: Into<String>>(
+        mut self,
+        name: N,
+        value: V,
+    ) -> Result<Self> {
+        let name = name.into();
+        if !is_sd_name(name.as_bytes()) {
+            return Err(Error::BadSdName {
+                name: name.into_bytes(),
+                back: Backtrace::new(),
+            });
+        }
+        self.params.push((name, value.into()));
+        Ok(self)
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+impl std::fmt::Display for SdElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}", self.id)?;
+        for (name, value) in &self.params {
+            write!(f, " {}=\"{}\"", name, escape_sd_value(value))?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Types that can describe themselves as RFC 5424 structured data
+///
+/// Implement this for a type you'd like to attach to messages as one or more SD-ELEMENTs, then
+/// log within [`with_structured_data`]:
+///
+/// ```rust
+/// use tracing_rfc_5424::{
+///     layer::with_structured_data,
+///     rfc5424::{IntoSdElements, SdElement},
+/// };
+///
+/// struct Order {
+///     id: u64,
+///     total: f64,
+/// }
+///
+/// impl IntoSdElements for Order {
+///     fn sd_elements(&self) -> Vec<SdElement> {
+///         SdElement::new("order@32473")
+///             .and_then(|elt| elt.with_param("id", self.id.to_string()))
+///             .and_then(|elt| elt.with_param("total", self.total.to_string()))
+///             .into_iter()
+///             .collect()
+///     }
+/// }
+///
+/// let order = Order { id: 11, total: 9.99 };
+/// with_structured_data(&order, || tracing::info!("order placed"));
+/// ```
+///
+/// With the `valuable` feature (& `--cfg tracing_unstable`), the elements may instead be recorded
+/// on the [`Event`] itself, in the reserved field [`SD_FIELD`]:
+/// `info!(syslog.sd = order.sd_elements().as_value(), "order placed")`.
+///
+/// [`with_structured_data`]: crate::layer::with_structured_data
+/// [`SD_FIELD`]: crate::layer::SD_FIELD
+/// [`Event`]: tracing::Event
+pub trait IntoSdElements {
+    fn sd_elements(&self) -> Vec<SdElement>;
+}
+
+impl IntoSdElements for SdElement {
+    fn sd_elements(&self) -> Vec<SdElement> {
+        vec![self.clone()]
+    }
+}
+
+impl IntoSdElements for [SdElement] {
+    fn sd_elements(&self) -> Vec<SdElement> {
+        self.to_vec()
+    }
+}
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static SD_ELEMENT_FIELDS: &[valuable::NamedField<'static>] = &[
+    valuable::NamedField::new("id"),
+    valuable::NamedField::new("params"),
+];
+
+/// An [`SdElement`] is a struct with fields `id` (a string) & `params` (a list of (name, value)
+/// string pairs); this is the form in which the [`Layer`] expects to find it in [`SD_FIELD`]
+///
+/// [`Layer`]: crate::layer::Layer
+/// [`SD_FIELD`]: crate::layer::SD_FIELD
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl valuable::Valuable for SdElement {
+    fn as_value(&self) -> valuable::Value<'_> {
+        valuable::Value::Structable(self)
+    }
+    fn visit(&self, visit: &mut dyn valuable::Visit) {
+        visit.visit_named_fields(&valuable::NamedValues::new(
+            SD_ELEMENT_FIELDS,
+            &[self.id.as_value(), self.params.as_value()],
+        ));
+    }
+}
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl valuable::Structable for SdElement {
+    fn definition(&self) -> valuable::StructDef<'_> {
+        valuable::StructDef::new_static("SdElement", valuable::Fields::Named(SD_ELEMENT_FIELDS))
+    }
+}
+
+/// Walk `value`, which should be an [`SdElement`] or a list of them, collecting the elements
+///
+/// Anything else is ignored, as are elements whose SD-ID or parameter names don't validate (which
+/// can only happen if some other type is masquerading as an [`SdElement`]).
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+pub(crate) fn sd_elements_from_value(value: valuable::Value<'_>) -> Vec<SdElement> {
+    use valuable::{NamedValues, Value, Visit};
+
+    #[derive(Default)]
+    struct Elements(Vec<SdElement>);
+
+    /// The fields of a single element
+    #[derive(Default)]
+    struct Element {
+        id: Option<String>,
+        params: Vec<(String, String)>,
+    }
+
+    struct Params<'a>(&'a mut Vec<(String, String)>);
+
+    impl Visit for Elements {
+        fn visit_value(&mut self, value: Value<'_>) {
+            match value {
+                Value::Listable(list) => list.visit(self),
+                Value::Structable(elt) if elt.definition().name() == "SdElement" => {
+                    let mut element = Element::default();
+                    elt.visit(&mut element);
+                    if let Some(elt) = element.id.and_then(|id| {
+                        element
+                            .params
+                            .into_iter()
+                            .try_fold(SdElement::new(id).ok()?, |elt, (name, value)| {
+                                elt.with_param(name, value)
+                            })
+                            .ok()
+                    }) {
+                        self.0.push(elt);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    impl Visit for Element {
+        fn visit_value(&mut self, _value: Value<'_>) {}
+        fn visit_named_fields(&mut self, named_values: &NamedValues<'_>) {
+            for (field, value) in named_values {
+                match (field.name(), value) {
+                    ("id", Value::String(id)) => self.id = Some(id.to_string()),
+                    ("params", Value::Listable(params)) => {
+                        params.visit(&mut Params(&mut self.params))
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    impl Visit for Params<'_> {
+        fn visit_value(&mut self, value: Value<'_>) {
+            if let Value::Tuplable(pair) = value {
+                pair.visit(self);
+            }
+        }
+        fn visit_unnamed_fields(&mut self, values: &[Value<'_>]) {
+            if let [Value::String(name), Value::String(value)] = values {
+                self.0.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    let mut elements = Elements::default();
+    elements.visit_value(value);
+    elements.0
+}
+
+/// A syslog formatter that produces RFC [5424]-conformant syslog messages.
+///
+/// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
+///
+/// Optionally, the target, module path & source location of the originating event or span may be
+/// reported as RFC 5424 STRUCTURED-DATA; see [`Rfc5424Builder::with_tracing_target`] & friends.
+#[derive(Clone, Debug)]
+pub struct Rfc5424 {
+    facility: AtomicFacility,
+    hostname: Hostname,
+    appname: AppName,
+    pid: ProcId,
+    with_bom: bool,
+    tracing_metadata: TracingMetadata,
+    // Kept apart from `tracing_metadata` so that the order in which the builder methods are
+    // invoked doesn't matter.
+    tracing_metadata_sdid: Option<String>,
+    meta_param_names: MetaParamNames,
+    split_sd: bool,
+    all_fields: bool,
+    span_fields: bool,
+    event_name_as_msgid: bool,
+    escape_control_chars: bool,
+    escape_unicode: bool,
+    max_length: Option<usize>,
+    clock: Clock,
+    time_quality: Option<TimeQuality>,
+    origin: Option<Origin>,
+    // SD-IDs & parameters, with the values escaped once, at build time
+    static_sd: Vec<(String, Vec<(String, String)>)>,
+    sd_order: Vec<SdSource>,
+    max_sd_length: Option<usize>,
+    sd_truncation_marker: bool,
+    version: u8,
+}
+
+impl std::default::Default for Rfc5424 {
+    /// Equivalent to [`Rfc5424::try_default`], falling back to [`Rfc5424::minimal`] should that
+    /// fail
+    fn default() -> Self {
+        Rfc5424::try_default().unwrap_or_else(|_| Rfc5424::minimal())
+    }
+}
+
+impl Rfc5424 {
+    /// Construct an [`Rfc5424`] formatter whose HOSTNAME, APP-NAME & PROCID are all the NILVALUE
+    ///
+    /// Unlike [`Default::default`], this makes no system calls (& in particular no network
+    /// lookups) whatsoever, which makes it predictable in sandboxed or offline environments, and
+    /// suitable for deployments where those fields are filled-in by a relay.
+    pub fn minimal() -> Rfc5424 {
+        Rfc5424 {
+            facility: AtomicFacility::new(Facility::LOG_USER),
+            hostname: Hostname(Vec::new()),
+            appname: AppName(Vec::new()),
+            pid: ProcId(Vec::new()),
+            with_bom: false,
+            tracing_metadata: TracingMetadata::default(),
+            tracing_metadata_sdid: None,
+            meta_param_names: MetaParamNames::default(),
+            split_sd: false,
+            all_fields: false,
+            span_fields: false,
+            event_name_as_msgid: false,
+            escape_control_chars: false,
+            escape_unicode: false,
+            max_length: None,
+            clock: Clock::default(),
+            time_quality: None,
+            origin: None,
+            static_sd: Vec::new(),
+            sd_order: DEFAULT_SD_ORDER.to_vec(),
+            max_sd_length: None,
+            sd_truncation_marker: true,
+            version: 1,
+        }
+    }
+    /// Construct an [`Rfc5424`] formatter with HOSTNAME, APP-NAME & PROCID taken from the
+    /// environment, reporting failure to determine any of them
+    ///
+    /// [`Default::default`] does the same, but falls back to [`Rfc5424::minimal`] on failure.
+    pub fn try_default() -> Result<Rfc5424> {
+        let mut imp = Rfc5424::minimal();
+        imp.hostname = Hostname::try_default()?;
+        imp.appname = AppName::try_default()?;
+        imp.pid = ProcId::default();
+        Ok(imp)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rfc5424Builder {
+    imp: Rfc5424,
+    lenient: bool,
+    /// The APP-NAME given to `appname_as_string`, to be validated by `try_build`
+    appname: Option<String>,
+}
+
+impl Rfc5424Builder {
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.imp.facility = AtomicFacility::new(facility);
+        self
+    }
+    pub fn hostname(mut self, hostname: Hostname) -> Self {
+        self.imp.hostname = hostname;
+        self
+    }
+    pub fn hostname_as_string(mut self, hostname: String) -> Result<Self> {
+        self.imp.hostname = Hostname::try_from(hostname)?;
+        Ok(self)
+    }
+    /// Render the HOSTNAME as the NILVALUE, for deployments that mustn't reveal it
+    pub fn with_nil_hostname(mut self) -> Self {
+        self.imp.hostname = Hostname(Vec::new());
+        self
+    }
+    pub fn appname(mut self, appname: AppName) -> Self {
+        self.imp.appname = appname;
+        self.appname = None;
+        self
+    }
+    /// Validate `_as_string` arguments leniently (or not)
+    ///
+    /// By default, the builder enforces the RFC's rules. In lenient mode, application names are
+    /// validated according to [`AppName::new_lenient`].
+    ///
+    /// The mode in effect when the formatter is [built](Rfc5424Builder::try_build) is the one
+    /// that applies, regardless of the order in which this &
+    /// [`appname_as_string`](Rfc5424Builder::appname_as_string) are called.
+    pub fn with_lenient_validation(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+    /// Set the APP-NAME, validating it strictly unless
+    /// [`with_lenient_validation`](Rfc5424Builder::with_lenient_validation) is turned on
+    ///
+    /// Since the validation mode may yet change, this fails only for names that neither mode
+    /// would accept; the rest are validated by [`try_build`](Rfc5424Builder::try_build).
+    pub fn appname_as_string(mut self, appname: String) -> Result<Self> {
+        if AppName::new(appname.as_bytes().to_vec()).is_err() {
+            AppName::new_lenient(appname.as_bytes().to_vec())?;
+        }
+        self.appname = Some(appname);
+        Ok(self)
+    }
+    /// Render the APP-NAME as the NILVALUE
+    pub fn with_nil_appname(mut self) -> Self {
+        self.imp.appname = AppName(Vec::new());
+        self.appname = None;
+        self
+    }
+    pub fn pid(mut self, pid: ProcId) -> Self {
+        self.imp.pid = pid;
+        self
+    }
+    pub fn pid_as_string(mut self, pid: String) -> Result<Self> {
+        self.imp.pid = ProcId::try_from(pid)?;
+        Ok(self)
+    }
+    /// Render the PROCID as the NILVALUE, for deployments that mustn't reveal it
+    pub fn with_nil_procid(mut self) -> Self {
+        self.imp.pid = ProcId(Vec::new());
+        self
+    }
+    /// Use `uuid` as the PROCID; see [`ProcId::from_uuid`]
+    #[cfg(feature = "uuid")]
+    pub fn pid_from_uuid(mut self, uuid: uuid::Uuid) -> Result<Self> {
+        self.imp.pid = ProcId::from_uuid(uuid)?;
+        Ok(self)
+    }
+    /// Prefix the MSG with the UTF-8 byte order mark, as the RFC requires of UTF-8 messages
+    ///
+    /// This is ignored if [`with_escape_unicode`](Rfc5424Builder::with_escape_unicode) is set.
+    pub fn with_bom(mut self, with_bom: bool) -> Self {
+        self.imp.with_bom = with_bom;
+        self
+    }
+    /// Report the `target` of the originating event or span as structured data
+    pub fn with_tracing_target(mut self, target: bool) -> Self {
+        self.imp.tracing_metadata.target = target;
+        self
+    }
+    /// Report the module path of the originating event or span as structured data
+    pub fn with_tracing_module(mut self, module: bool) -> Self {
+        self.imp.tracing_metadata.module = module;
+        self
+    }
+    /// Report the source file & line number of the originating event or span as structured data
+    pub fn with_tracing_source_location(mut self, source_location: bool) -> Self {
+        self.imp.tracing_metadata.source_location = source_location;
+        self
+    }
+    /// Report the target of the outermost span enclosing the originating event as structured
+    /// data, in a parameter named `root-target`
+    ///
+    /// This is handy for routing messages by subsystem, regardless of where the event itself was
+    /// emitted. The parameter is omitted for events that aren't within any span (and for spans
+    /// themselves). The [`Layer`] supplies the root span's target via [`Extras`]; other callers of
+    /// [`SyslogFormatter::format_with`] may do the same.
+    ///
+    /// [`Layer`]: crate::layer::Layer
+    pub fn with_root_span_target(mut self, root_span_target: bool) -> Self {
+        self.imp.tracing_metadata.root_span_target = root_span_target;
+        self
+    }
+    /// Set the SD-ID under which [`tracing`] metadata will be reported
+    ///
+    /// This has no effect unless at least one of [`with_tracing_target`], [`with_tracing_module`]
+    /// or [`with_tracing_source_location`] is also set (the order in which they're called doesn't
+    /// matter). If it's never called, [`DEFAULT_TRACING_METADATA_SDID`] will be used.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    /// [`with_tracing_target`]: Rfc5424Builder::with_tracing_target
+    /// [`with_tracing_module`]: Rfc5424Builder::with_tracing_module
+    /// [`with_tracing_source_location`]: Rfc5424Builder::with_tracing_source_location
+    pub fn with_tracing_metadata_sdid(mut self, sdid: String) -> Result<Self> {
+        if !is_sd_name(sdid.as_bytes()) {
+            return Err(Error::BadSdId {
+                name: sdid.into_bytes(),
+                back: Backtrace::new(),
+            });
+        }
+        self.imp.tracing_metadata_sdid = Some(sdid);
+        Ok(self)
+    }
+    /// Report the event's target under the SD-PARAM name `name`, rather than "target"
+    ///
+    /// `name` must be a legal SD-NAME: 1-32 printable ASCII characters, excluding space, '=', ']'
+    /// & '"'. This has no effect unless [`with_tracing_target`] is also set.
+    ///
+    /// [`with_tracing_target`]: Rfc5424Builder::with_tracing_target
+    pub fn with_target_param_name<N: Into<String>>(mut self, name: N) -> Result<Self> {
+        self.imp.meta_param_names.target = meta_param_name(name)?;
+        Ok(self)
+    }
+    /// Report the event's module path under the SD-PARAM name `name`, rather than "module"
+    ///
+    /// See [`with_target_param_name`](Rfc5424Builder::with_target_param_name).
+    pub fn with_module_param_name<N: Into<String>>(mut self, name: N) -> Result<Self> {
+        self.imp.meta_param_names.module = meta_param_name(name)?;
+        Ok(self)
+    }
+    /// Report the event's source file under the SD-PARAM name `name`, rather than "file"
+    ///
+    /// See [`with_target_param_name`](Rfc5424Builder::with_target_param_name).
+    pub fn with_file_param_name<N: Into<String>>(mut self, name: N) -> Result<Self> {
+        self.imp.meta_param_names.file = meta_param_name(name)?;
+        Ok(self)
+    }
+    /// Report the event's source line under the SD-PARAM name `name`, rather than "line"
+    ///
+    /// See [`with_target_param_name`](Rfc5424Builder::with_target_param_name).
+    pub fn with_line_param_name<N: Into<String>>(mut self, name: N) -> Result<Self> {
+        self.imp.meta_param_names.line = meta_param_name(name)?;
+        Ok(self)
+    }
+    /// Report [`tracing`] metadata in several SD elements rather than one
+    ///
+    /// By default, all requested metadata is reported in a single SD element. Some consumers prefer
+    /// a logical grouping; if `split_sd` is true the source location will be reported in an element
+    /// with SD-ID `tracing-loc@64700` & the target & module in one with SD-ID
+    /// `tracing-target@64700`, like so:
+    ///
+    /// ```text
+    /// [tracing-loc@64700 file="src/main.rs" line="12"][tracing-target@64700 target="app"]
+    /// ```
+    ///
+    /// In this mode, any SD-ID set via [`with_tracing_metadata_sdid`] is ignored.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    /// [`with_tracing_metadata_sdid`]: Rfc5424Builder::with_tracing_metadata_sdid
+    pub fn with_split_sd(mut self, split_sd: bool) -> Self {
+        self.imp.split_sd = split_sd;
+        self
+    }
+    /// Report the key-value fields of each event as structured data, in an element with SD-ID
+    /// [`FIELDS_SDID`]
+    ///
+    /// The fields are obtained from the [`TracingFormatter`] in use (see
+    /// [`TracingFormatter::fields`]); the `message` field & the reserved `syslog.*` fields are not
+    /// reported. A field whose name isn't a legal SD-NAME is skipped, & a repeated name is
+    /// suffixed with its occurrence (`name.2`, `name.3`, &c).
+    ///
+    /// [`TracingFormatter`]: crate::tracing::TracingFormatter
+    /// [`TracingFormatter::fields`]: crate::tracing::TracingFormatter::fields
+    pub fn with_all_fields(mut self, all_fields: bool) -> Self {
+        self.imp.all_fields = all_fields;
+        self
+    }
+    /// Report the key-value fields of the spans enclosing each event as structured data, in an
+    /// element with SD-ID [`SPAN_FIELDS_SDID`]
+    ///
+    /// This picks up, for instance, the arguments of functions annotated with `#[instrument]`.
+    /// Fields are gathered from the outermost span inward; where two spans record a field of the
+    /// same name, the innermost span's value wins. Fields recorded after a span is created (via
+    /// [`Span::record`]) are included. Names are handled as in
+    /// [`with_all_fields`](Rfc5424Builder::with_all_fields).
+    ///
+    /// [`Span::record`]: https://docs.rs/tracing/latest/tracing/struct.Span.html#method.record
+    pub fn with_span_fields(mut self, span_fields: bool) -> Self {
+        self.imp.span_fields = span_fields;
+        self
+    }
+    /// Use the name of the originating event or span as the MSGID
+    ///
+    /// RFC 5424 intends MSGID to identify the type of message; the name in [`tracing`] [`Metadata`]
+    /// is a natural fit. Names that aren't legal MSGIDs (more than 32 characters, or containing
+    /// spaces or non-ASCII characters-- as the default event names produced by the [`tracing`]
+    /// macros do) will be replaced with the NILVALUE.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    pub fn with_event_name_as_msgid(mut self, event_name_as_msgid: bool) -> Self {
+        self.imp.event_name_as_msgid = event_name_as_msgid;
+        self
+    }
+    /// Replace control characters in the MSG with their Rust escape sequences (`\n`, `\t`,
+    /// `\u{1b}` & so on)
+    ///
+    /// This keeps multi-line messages on one line, and terminal escape sequences out of log files.
+    pub fn with_escape_control_chars(mut self, escape_control_chars: bool) -> Self {
+        self.imp.escape_control_chars = escape_control_chars;
+        self
+    }
+    /// Replace every character of the MSG with its Unicode [escape] sequence (`\u{48}` & so on)
+    ///
+    /// The RFC asks for UTF-8, but permits "any other encoding" where a syslog application can't
+    /// use it; this is for collectors that don't accept UTF-8. Since the MSG is then no longer
+    /// UTF-8 text in any meaningful sense, escaping wins over
+    /// [`with_bom`](Rfc5424Builder::with_bom): no BOM will be written. The escape sequences cover
+    /// control characters as well, so this subsumes
+    /// [`with_escape_control_chars`](Rfc5424Builder::with_escape_control_chars).
+    ///
+    /// [escape]: str::escape_unicode
+    pub fn with_escape_unicode(mut self, escape_unicode: bool) -> Self {
+        self.imp.escape_unicode = escape_unicode;
+        self
+    }
+    /// Limit each syslog packet to `max_length` bytes
+    ///
+    /// The limit applies to the entire packet, header & BOM included, but only the MSG is ever
+    /// truncated: if the header & structured data exceed the limit on their own, the MSG is
+    /// dropped & the packet sent regardless. Truncation respects character (& escape sequence)
+    /// boundaries, so the MSG remains valid UTF-8.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.imp.max_length = Some(max_length);
+        self
+    }
+    /// Include the standard `timeQuality` SD element in each message
+    ///
+    /// Standard elements precede any [`tracing`] metadata in the STRUCTURED-DATA.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    pub fn with_time_quality(mut self, time_quality: TimeQuality) -> Self {
+        self.imp.time_quality = Some(time_quality);
+        self
+    }
+    /// Include the standard `origin` SD element in each message
+    ///
+    /// Fails if `origin`'s enterprise ID is not a dotted sequence of numbers, or if its software
+    /// name or version are empty or too long.
+    pub fn with_origin(mut self, origin: Origin) -> Result<Self> {
+        origin.validate()?;
+        self.imp.origin = Some(origin);
+        Ok(self)
+    }
+    /// Attach an SD element with SD-ID `sd_id` & parameters `params` to every message
+    ///
+    /// This is handy for tags describing the deployment as a whole (`[env@32473 deployment="prod"
+    /// region="us-west-2"]`, say). The element is validated & rendered here, once, rather than on
+    /// each message. May be called more than once, to attach several elements; they're written
+    /// after any standard elements & before any [`tracing`] metadata.
+    ///
+    /// Fails if `sd_id` isn't a legal SD-ID, or any parameter name isn't a legal SD-NAME.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    pub fn with_static_sd(mut self, sd_id: &str, params: Vec<(String, String)>) -> Result<Self> {
+        let element = params
+            .into_iter()
+            .try_fold(SdElement::new(sd_id)?, |element, (name, value)| {
+                element.with_param(name, value)
+            })?;
+        self.imp.static_sd.push((
+            element.id,
+            element
+                .params
+                .into_iter()
+                .map(|(name, value)| (name, escape_sd_value(&value)))
+                .collect(),
+        ));
+        Ok(self)
+    }
+    /// Limit the STRUCTURED-DATA section of each message to `max_sd_length` bytes
+    ///
+    /// With many sources of structured data in play, the section can grow large enough that the
+    /// message no longer fits in a datagram. With this set, SD parameters are written only until
+    /// the budget is exhausted: the first that doesn't fit, and all that would follow it, are
+    /// dropped (see [`with_sd_order`] to control which are written first). Unless turned-off via
+    /// [`with_sd_truncation_marker`], the element `[syslog-tracing@64700 truncated="1"]` (see
+    /// [`TRUNCATION_MARKER_SDID`]) is then written in their stead; space for it is reserved
+    /// within the budget (provided the budget is large enough to hold it at all).
+    ///
+    /// [`with_sd_order`]: Rfc5424Builder::with_sd_order
+    /// [`with_sd_truncation_marker`]: Rfc5424Builder::with_sd_truncation_marker
+    pub fn with_max_sd_length(mut self, max_sd_length: usize) -> Self {
+        self.imp.max_sd_length = Some(max_sd_length);
+        self
+    }
+    /// Mark (or not) messages whose STRUCTURED-DATA section was truncated; see
+    /// [`with_max_sd_length`](Rfc5424Builder::with_max_sd_length)
+    pub fn with_sd_truncation_marker(mut self, sd_truncation_marker: bool) -> Self {
+        self.imp.sd_truncation_marker = sd_truncation_marker;
+        self
+    }
+    /// Write structured data from the sources in `order` first, followed by any remaining sources
+    /// in the default order (that in which the [`SdSource`] variants are declared)
+    ///
+    /// Under a [limit](Rfc5424Builder::with_max_sd_length) on the size of the STRUCTURED-DATA
+    /// section, sources written earlier are the ones preserved.
+    pub fn with_sd_order(mut self, order: &[SdSource]) -> Self {
+        let mut sd_order = Vec::with_capacity(DEFAULT_SD_ORDER.len());
+        for source in order.iter().chain(DEFAULT_SD_ORDER.iter()) {
+            if !sd_order.contains(source) {
+                sd_order.push(*source);
+            }
+        }
+        self.imp.sd_order = sd_order;
+        self
+    }
+    /// Use `clock` to timestamp messages for which no timestamp is supplied
+    ///
+    /// By default, such messages are stamped with the current time; installing a fixed clock
+    /// (see [`Clock::fixed`]) makes the output reproducible.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.imp.clock = clock;
+        self
+    }
+    /// Set the VERSION field of the header (1 by default)
+    ///
+    /// Version 1 is the only one the RFC defines; this is for interoperating with collectors that
+    /// use a higher version to denote some extended format. Fails if `version` is zero.
+    pub fn with_version(mut self, version: u8) -> Result<Self> {
+        if version == 0 {
+            return Err(Error::BadVersion {
+                version,
+                back: Backtrace::new(),
+            });
+        }
+        self.imp.version = version;
+        Ok(self)
+    }
+    /// Build the formatter, validating any APP-NAME given to
+    /// [`appname_as_string`](Rfc5424Builder::appname_as_string) under the final validation mode
+    pub fn try_build(mut self) -> Result<Rfc5424> {
+        if let Some(appname) = self.appname.take() {
+            self.imp.appname = if self.lenient {
+                AppName::new_lenient(appname.into_bytes())?
+            } else {
+                AppName::try_from(appname)?
+            };
+        }
+        Ok(self.imp)
+    }
+    /// Build the formatter
+    ///
+    /// # Panics
+    ///
+    /// Panics if an APP-NAME given to [`appname_as_string`](Rfc5424Builder::appname_as_string)
+    /// isn't valid under the final validation mode; use
+    /// [`try_build`](Rfc5424Builder::try_build) to handle that.
+    pub fn build(self) -> Rfc5424 {
+        self.try_build()
+            .unwrap_or_else(|err| panic!("invalid RFC 5424 formatter: {}", err))
+    }
+}
+
+impl Rfc5424 {
+    pub fn builder() -> Rfc5424Builder {
+        Rfc5424Builder {
+            imp: Rfc5424::default(),
+            lenient: false,
+            appname: None,
+        }
+    }
+    pub fn facility(&self) -> Facility {
+        self.facility.load()
+    }
+    /// Change the facility under which subsequent messages will be logged
+    ///
+    /// This takes `&self`, so it may be called on a formatter that's already part of a
+    /// [`Layer`](crate::layer::Layer) (see [`Layer::syslog_formatter`]). The change is atomic, but
+    /// made with relaxed memory ordering: a message being formatted concurrently may carry either
+    /// facility.
+    ///
+    /// [`Layer::syslog_formatter`]: crate::layer::Layer::syslog_formatter
+    pub fn set_facility(&self, facility: Facility) {
+        self.facility.store(facility)
+    }
+    pub fn hostname(&self) -> &Hostname {
+        &self.hostname
+    }
+    pub fn appname(&self) -> &AppName {
+        &self.appname
+    }
+    pub fn pid(&self) -> &ProcId {
+        &self.pid
+    }
+    /// Whether the MSG will be prefixed with the UTF-8 BOM (when escaping doesn't rule it out)
+    pub fn with_bom(&self) -> bool {
+        self.with_bom
+    }
+    pub fn event_name_as_msgid(&self) -> bool {
+        self.event_name_as_msgid
+    }
+    pub fn escape_control_chars(&self) -> bool {
+        self.escape_control_chars
+    }
+    pub fn escape_unicode(&self) -> bool {
+        self.escape_unicode
+    }
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+    pub fn time_quality(&self) -> Option<&TimeQuality> {
+        self.time_quality.as_ref()
+    }
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+    /// Format a message as a [`String`] rather than as bytes
+    ///
+    /// This is a convenience for callers who want to inspect the formatted message (in tests, for
+    /// instance) without going through a [`Transport`]. Since the BOM is not itself a character,
+    /// but rather a marker on the encoding of the MSG part, it will be removed if this formatter
+    /// was configured to write one.
+    ///
+    /// [`Transport`]: crate::transport::Transport
+    pub fn format_to_string(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+    ) -> Result<String> {
+        let mut buf = Vec::new();
+        if let Some(idx) = self.write_packet(&mut buf, level, msg, timestamp, meta, &Extras::new())
+        {
+            buf.drain(idx..idx + BOM.len());
+        }
+        String::from_utf8(buf).map_err(|err| Error::NotUtf8 {
+            source: err,
+            back: Backtrace::new(),
+        })
+    }
+    /// Write the [`tracing`] metadata SD element(s) for `meta`
+    ///
+    /// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+    fn write_tracing_metadata(
+        &self,
+        sd: &mut SdWriter<'_>,
+        meta: &Metadata<'_>,
+        extras: &Extras<'_>,
+    ) {
+        let sdid = self
+            .tracing_metadata_sdid
+            .as_deref()
+            .unwrap_or(DEFAULT_TRACING_METADATA_SDID);
+        let single = [(sdid, ALL_META_PARAMS)];
+        let elements: &[(&str, &[MetaParam])] = if self.split_sd {
+            SPLIT_SD_ELEMENTS
+        } else {
+            &single
+        };
+        for (sdid, params) in elements {
+            sd.element(
+                sdid,
+                params
+                    .iter()
+                    .filter(|param| self.tracing_metadata.enabled(**param))
+                    .filter_map(|param| {
+                        param
+                            .value(meta, extras)
+                            .map(|value| (self.meta_param_names.name(*param), value))
+                    }),
+            );
+        }
+    }
+    /// Write a syslog packet to `buf`; return the offset of the BOM, if one was written
+    fn write_packet(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Option<usize> {
+        // `buf` may already hold data; the length limit applies to what we write
+        let start = buf.len();
+
+        use std::io::Write;
+        // Writing to a `Vec<u8>` can't fail, so the `unwrap()`s below are safe.
+        write!(
+            buf,
+            "<{}>{} ",
+            self.facility.load().pri(level),
+            self.version
+        )
+        .unwrap();
+        write_timestamp(buf, &timestamp.unwrap_or_else(|| self.clock.now()));
+
+        use bytes::buf::BufMut;
+        buf.put_u8(b' ');
+        buf.put_slice(nil_or(&self.hostname.0));
+        buf.put_u8(b' ');
+        // A per-message APP-NAME is used only if it's valid; we don't want to fail to log just
+        // because of a bad override.
+        match extras
+            .appname
+            .filter(|appname| is_app_name(appname.as_bytes()))
+        {
+            Some(appname) => buf.put_slice(appname.as_bytes()),
+            None => buf.put_slice(nil_or(&self.appname.0)),
+        }
+        buf.put_u8(b' ');
+        buf.put_slice(nil_or(&self.pid.0));
+        buf.put_u8(b' ');
+        // MSGID = NILVALUE / 1*32PRINTUSASCII
+        match meta
+            .filter(|_| self.event_name_as_msgid)
+            .map(|meta| meta.name())
+            .filter(|name| is_msgid(name.as_bytes()))
+        {
+            Some(name) => buf.put_slice(name.as_bytes()),
+            None => buf.put_u8(b'-'),
+        }
+        buf.put_u8(b' ');
+
+        // STRUCTURED-DATA: the NILVALUE unless we've been asked to report structured data of some
+        // sort (& actually have some to report).
+        let mut sd = SdWriter::new(buf, self.max_sd_length, self.sd_truncation_marker);
+        for source in &self.sd_order {
+            match source {
+                SdSource::Standard => {
+                    if let Some(time_quality) = &self.time_quality {
+                        write_time_quality(&mut sd, time_quality);
+                    }
+                    if let Some(origin) = &self.origin {
+                        write_origin(&mut sd, origin);
+                    }
+                }
+                SdSource::Static => {
+                    for (sdid, params) in &self.static_sd {
+                        sd.element(sdid, params.iter().map(|(n, v)| (n, v)));
+                    }
+                }
+                SdSource::TracingMetadata => {
+                    if let Some(meta) = meta.filter(|_| self.tracing_metadata.any()) {
+                        self.write_tracing_metadata(&mut sd, meta, extras);
+                    }
+                }
+                SdSource::Fields => {
+                    if let Some(fields) = extras.fields.filter(|_| self.all_fields) {
+                        write_fields(&mut sd, FIELDS_SDID, fields);
+                    }
+                }
+                SdSource::SpanFields => {
+                    if let Some(fields) = extras.span_fields.filter(|_| self.span_fields) {
+                        if !matches!(extras.span_sd, Some(span_sd) if sd.raw(span_sd)) {
+                            write_fields(&mut sd, SPAN_FIELDS_SDID, fields);
+                        }
+                    }
+                }
+                SdSource::PerMessage => {
+                    for element in extras.structured_data.unwrap_or_default() {
+                        if element.params().is_empty() {
+                            sd.empty_element(element.id());
+                        } else {
+                            sd.element(
+                                element.id(),
+                                element
+                                    .params()
+                                    .iter()
+                                    .map(|(name, value)| (name, escape_sd_value(value))),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        if !sd.finish() {
+            buf.put_u8(b'-');
+        }
+
+        // From the RFC
+
+        // "The character set used in MSG SHOULD be UNICODE, encoded using UTF-8 as specified in
+        // [RFC3629].  If the syslog application cannot encode the MSG in Unicode, it MAY use
+        // any other encoding."
+
+        // "If a syslog application encodes MSG in UTF-8, the string MUST start with the Unicode
+        // byte order mask (BOM), which for UTF-8 is ABNF %xEF.BB.BF.  The syslog application
+        // MUST encode in the "shortest form" and MAY use any valid UTF-8 sequence."
+        //
+        // MSG is optional, however; if it's empty, we omit it (& the preceding SP) altogether.
+        //
+        // The order of operations matters here: SP & BOM first, then the MSG proper (escaped as
+        // requested), all subject to the length limit, which counts the entire packet. We never
+        // write a BOM (or the SP) without at least one character of MSG to follow it.
+        let mut bom_at = None;
+        if !msg.is_empty() {
+            let mark = buf.len();
+            buf.put_u8(b' ');
+            if self.with_bom && !self.escape_unicode {
+                bom_at = Some(buf.len());
+                buf.put_slice(BOM);
+            }
+            let limit = self.max_length.map(|max_length| start + max_length);
+            let wrote = match (limit, self.escape_control_chars, self.escape_unicode) {
+                (None, false, false) => {
+                    buf.put_slice(msg.as_bytes());
+                    true
+                }
+                (limit, escape, escape_unicode) => {
+                    write_msg(buf, msg, escape, escape_unicode, limit)
+                }
+            };
+            if !wrote {
+                buf.truncate(mark);
+                bom_at = None;
+            }
+        }
+
+        bom_at
+    }
+}
+
+impl SyslogFormatter for Rfc5424 {
+    fn wants_fields(&self) -> bool {
+        self.all_fields
+    }
+    fn wants_span_fields(&self) -> bool {
+        self.span_fields
+    }
+    fn app_name(&self) -> Option<String> {
+        Some(self.appname.to_string())
+    }
+    fn proc_id(&self) -> Option<String> {
+        Some(self.pid.to_string())
+    }
+    type Error = Error;
+    type Output = Vec<u8>;
+    fn format(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+    ) -> Result<Self::Output> {
+        let mut buf = Vec::new();
+        self.format_into(&mut buf, level, msg, timestamp, meta)?;
+        Ok(buf)
+    }
+    fn format_into(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+    ) -> Result<()> {
+        self.format_into_with(buf, level, msg, timestamp, meta, &Extras::default())
+    }
+    fn format_with(
+        &self,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<Self::Output> {
+        let mut buf = Vec::new();
+        self.format_into_with(&mut buf, level, msg, timestamp, meta, extras)?;
+        Ok(buf)
+    }
+    fn format_into_with(
+        &self,
+        buf: &mut Vec<u8>,
+        level: Level,
+        msg: &str,
+        timestamp: Option<DateTime<Utc>>,
+        meta: Option<&Metadata<'_>>,
+        extras: &Extras<'_>,
+    ) -> Result<()> {
+        self.write_packet(buf, level, msg, timestamp, meta, extras);
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                            parsing                                             //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// An RFC 5424 syslog message, broken-out into its constituent fields
+///
+/// Fields that were the NILVALUE in the message are `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedMessage {
+    pub facility: Facility,
+    pub level: Level,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub hostname: Option<String>,
+    pub appname: Option<String>,
+    pub procid: Option<String>,
+    pub msgid: Option<String>,
+    /// SD-PARAMs (unescaped), keyed by SD-ID & then PARAM-NAME
+    pub structured_data: BTreeMap<String, BTreeMap<String, String>>,
+    /// The MSG part of the message, less any BOM
+    pub msg: String,
+}
+
+/// Parse an RFC [5424] syslog message
+///
+/// `buf` should contain exactly one message, without any transport framing (such as the trailing
+/// newline written by [`TcpTransport`]). Input that is not UTF-8, or that doesn't conform to the
+/// RFC, produces an [`Error::Parse`] describing the problem.
+///
+/// [5424]: https://datatracker.ietf.org/doc/html/rfc5424
+/// [`TcpTransport`]: crate::transport::TcpTransport
+pub fn parse(buf: &[u8]) -> Result<ParsedMessage> {
+    fn parse_error<E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>>(err: E) -> Error {
+        Error::Parse {
+            source: err.into(),
+            back: Backtrace::new(),
+        }
+    }
+
+    let msg = syslog_rfc5424::parse_message(std::str::from_utf8(buf).map_err(parse_error)?)
+        .map_err(parse_error)?;
+    if msg.version != 1 {
+        return Err(parse_error(format!("unsupported version {}", msg.version)));
+    }
+
+    Ok(ParsedMessage {
+        facility: Facility::from_code(msg.facility as u8)
+            .ok_or_else(|| parse_error("bad facility"))?,
+        level: Level::from_code(msg.severity as u8).ok_or_else(|| parse_error("bad severity"))?,
+        timestamp: msg.timestamp.and_then(|secs| {
+            Utc.timestamp_opt(secs, msg.timestamp_nanos.unwrap_or(0))
+                .single()
+        }),
+        hostname: msg.hostname,
+        appname: msg.appname,
+        procid: msg.procid.map(|procid| match procid {
+            syslog_rfc5424::message::ProcId::PID(pid) => pid.to_string(),
+            syslog_rfc5424::message::ProcId::Name(name) => name,
+        }),
+        msgid: msg.msgid,
+        structured_data: msg
+            .sd
+            .iter()
+            .map(|(id, params)| (id.clone(), params.clone()))
+            .collect(),
+        msg: msg
+            .msg
+            .strip_prefix('\u{feff}')
+            .map(str::to_string)
+            .unwrap_or(msg.msg),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn lenient_app_name() {
+        let appname = "a-rather-long-service-name-that-exceeds-the-rfc-limit".to_string();
+        assert!(Rfc5424::builder()
+            .appname_as_string(appname.clone())
+            .and_then(Rfc5424Builder::try_build)
+            .is_err());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .with_lenient_validation(true)
+            .appname_as_string(appname)
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .build();
+        assert!(f
+            .format_to_string(Level::LOG_INFO, "Hello", None, None)
+            .unwrap()
+            .ends_with(
+                " bree.local a-rather-long-service-name-that-exceeds-the-rfc-limit 123 - - Hello"
+            ));
+        assert!(AppName::new_lenient(b"has space".to_vec()).is_err());
+        assert!(AppName::new_lenient(Vec::new()).is_err());
+        assert!(AppName::new_lenient(vec![b'a'; APP_NAME_LENIENT_MAX_LEN]).is_ok());
+        assert!(AppName::new_lenient(vec![b'a'; APP_NAME_LENIENT_MAX_LEN + 1]).is_err());
+        // The empty name is fine strictly, so it's rejected only once the mode is settled
+        assert!(Rfc5424::builder()
+            .with_lenient_validation(true)
+            .appname_as_string(String::new())
+            .unwrap()
+            .try_build()
+            .is_err());
+        // While names no mode accepts are rejected up-front
+        assert!(Rfc5424::builder()
+            .appname_as_string("x".repeat(APP_NAME_LENIENT_MAX_LEN + 1))
+            .is_err());
+
+        // The mode applies regardless of call order...
+        let appname = "a-rather-long-service-name-that-exceeds-the-rfc-limit".to_string();
+        let f = Rfc5424::builder()
+            .appname_as_string(appname.clone())
+            .unwrap()
+            .with_lenient_validation(true)
+            .try_build()
+            .unwrap();
+        assert_eq!(f.appname().to_string(), appname);
+        // so that turning it off again means strict validation.
+        assert!(Rfc5424::builder()
+            .with_lenient_validation(true)
+            .appname_as_string(appname)
+            .unwrap()
+            .with_lenient_validation(false)
+            .try_build()
+            .is_err());
+    }
+
+    #[test]
+    fn app_name() {
+        let _x = AppName::default(); // At least _exercise_ `Default`
+
+        let x: &[u8] = b"0123456789012345678901234567890123456789012345678";
+        let v: Vec<u8> = x.into();
+        assert!(AppName::new(v).is_err());
+
+        let x: &[u8] = b"udp-test";
+        let v: Vec<u8> = x.into();
+        assert!(AppName::new(v).is_ok());
+    }
+
+    #[test]
+    fn version() {
+        let f = Rfc5424::minimal();
+        assert_eq!(f.version(), 1);
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .with_version(2)
+            .unwrap()
+            .build();
+        assert_eq!(f.version(), 2);
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert!(buf.starts_with(b"<14>2 2001-09-09T01:46:40+00:00 bree.local "));
+        assert!(matches!(
+            Rfc5424::builder().with_version(0),
+            Err(Error::BadVersion { version: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn minimal() {
+        let f = Rfc5424::minimal();
+        assert_eq!(f.hostname().to_string(), "-");
+        assert_eq!(f.appname().to_string(), "-");
+        assert_eq!(f.pid().to_string(), "-");
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert_eq!(buf, b"<14>1 2001-09-09T01:46:40+00:00 - - - - - Hello");
+        let msg = syslog_rfc5424::parse_message(std::str::from_utf8(&buf).unwrap()).unwrap();
+        assert_eq!(msg.hostname, None);
+        assert_eq!(msg.appname, None);
+        assert_eq!(msg.procid, None);
+        assert_eq!(msg.msg, "Hello");
+    }
+
+    #[test]
+    fn set_facility() {
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let f = Rfc5424::builder().facility(Facility::LOG_DAEMON).build();
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert!(buf.starts_with(b"<30>1 "));
+        f.set_facility(Facility::LOG_USER);
+        assert_eq!(f.facility(), Facility::LOG_USER);
+        let buf = f.format(Level::LOG_INFO, "Hello", ts, None).unwrap();
+        assert!(buf.starts_with(b"<14>1 "));
+    }
+
+    #[test]
+    fn getters() {
+        let f = Rfc5424::builder()
+            .facility(Facility::LOG_LOCAL3)
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_bom(true)
+            .with_event_name_as_msgid(true)
+            .with_escape_control_chars(true)
+            .with_max_length(1024)
+            .build();
+        assert_eq!(f.facility(), Facility::LOG_LOCAL3);
+        assert_eq!(f.hostname().to_string(), "bree.local");
+        assert_eq!(f.appname().to_string(), "prototyping");
+        assert_eq!(f.pid().to_string(), "123");
+        assert!(f.with_bom());
+        assert!(f.event_name_as_msgid());
+        assert!(f.escape_control_chars());
+        assert!(!f.escape_unicode());
+        assert_eq!(f.max_length(), Some(1024));
+        assert!(f.time_quality().is_none());
+        assert!(f.origin().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn app_name_fallback() {
+        use std::os::unix::ffi::OsStrExt;
+        let name =
+            |bytes: &[u8]| AppName::from_file_name(Some(std::ffi::OsStr::from_bytes(bytes))).0;
+        assert_eq!(name(b"udp-test"), b"udp-test");
+        // Not UTF-8, let alone ASCII
+        assert_eq!(name(b"caf\xe9"), b"-");
+        // Too long
+        assert_eq!(name(&[b'a'; APP_NAME_MAX_LEN + 1]), b"-");
+        assert_eq!(AppName::from_file_name(None).0, b"-");
+    }
+
+    #[test]
+    fn tracing_metadata_sdid() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let builder = || {
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+        };
+        let ts = Some(std::time::UNIX_EPOCH.into());
+
+        // No metadata requested => NILVALUE
+        let rsp = builder()
+            .build()
+            .format(Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&rsp).unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello"
+        );
+
+        // Default SD-ID
+        let rsp = builder()
+            .with_tracing_target(true)
+            .build()
+            .format(Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&rsp).unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - [tracing-meta@64700 target=\"my-target\"] Hello"
+        );
+
+        // Custom SD-ID set *after* enabling the target...
+        let rsp = builder()
+            .with_tracing_target(true)
+            .with_tracing_metadata_sdid("mymeta@32473".to_string())
+            .unwrap()
+            .build()
+            .format(Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&rsp).unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - [mymeta@32473 target=\"my-target\"] Hello"
+        );
+        // and before should give the same result.
+        let rsp2 = builder()
+            .with_tracing_metadata_sdid("mymeta@32473".to_string())
+            .unwrap()
+            .with_tracing_target(true)
+            .build()
+            .format(Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(rsp, rsp2);
+
+        assert!(builder()
+            .with_tracing_metadata_sdid("bad id".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn meta_param_names() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let ts = Some(std::time::UNIX_EPOCH.into());
+
+        for split_sd in [false, true] {
+            let rsp = Rfc5424::builder()
+                .with_tracing_target(true)
+                .with_tracing_source_location(true)
+                .with_split_sd(split_sd)
+                .with_file_param_name("src_file")
+                .unwrap()
+                .with_line_param_name("src_line".to_string())
+                .unwrap()
+                .build()
+                .format(Level::LOG_INFO, "Hello", ts, Some(meta))
+                .unwrap();
+            let parsed = parse(&rsp).unwrap();
+            let params = parsed
+                .structured_data
+                .values()
+                .flat_map(|params| params.iter())
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<BTreeMap<_, _>>();
+            assert_eq!(params["src_file"], meta.file().unwrap());
+            assert_eq!(params["src_line"], meta.line().unwrap().to_string());
+            // The names we didn't change keep their defaults
+            assert_eq!(params["target"], "my-target");
+            assert!(!params.contains_key("file"));
+            assert!(!params.contains_key("line"));
+        }
+
+        for bad in ["", "src file", "src=file", "src]file", "src\"file"] {
+            assert!(matches!(
+                Rfc5424::builder().with_file_param_name(bad),
+                Err(Error::BadSdName { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn split_sd() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_tracing_target(true)
+            .with_tracing_source_location(true)
+            .with_split_sd(true)
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                Some(meta),
+            )
+            .unwrap();
+
+        let msg = syslog_rfc5424::parse_message(std::str::from_utf8(&rsp).unwrap()).unwrap();
+        assert_eq!(msg.sd.len(), 2);
+        let loc = msg.sd.find_sdid("tracing-loc@64700").unwrap();
+        assert_eq!(loc.get("file").map(String::as_str), meta.file());
+        assert_eq!(loc.get("line"), Some(&meta.line().unwrap().to_string()));
+        assert!(loc.get("target").is_none());
+        let target = msg.sd.find_sdid("tracing-target@64700").unwrap();
+        assert_eq!(target.get("target").map(String::as_str), Some("my-target"));
+        assert!(target.get("file").is_none());
+        assert_eq!(msg.msg, "Hello");
+    }
+
+    #[test]
+    fn sd_value_escaping() {
+        assert_eq!(escape_sd_value("plain"), "plain");
+        assert_eq!(escape_sd_value(r#"a"b\c]d"#), r#"a\"b\\c\]d"#);
+        assert_eq!(escape_sd_value("a\tb\n"), r"a\\tb\\n");
+
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my\ttarget\n", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_tracing_target(true)
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                Some(meta),
+            )
+            .unwrap();
+        assert!(!rsp.iter().any(|x| x.is_ascii_control()));
+
+        let msg = syslog_rfc5424::parse_message(std::str::from_utf8(&rsp).unwrap()).unwrap();
+        let target = msg.sd.find_tuple("tracing-meta@64700", "target").unwrap();
+        assert_eq!(target, r"my\ttarget\n");
+        assert_eq!(msg.msg, "Hello");
+    }
+
+    #[test]
+    fn format_to_string() {
+        let builder = || {
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+        };
+        let golden = "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello, 世界!";
+        assert_eq!(
+            builder()
+                .build()
+                .format_to_string(
+                    Level::LOG_INFO,
+                    "Hello, 世界!",
+                    Some(std::time::UNIX_EPOCH.into()),
+                    None
+                )
+                .unwrap(),
+            golden
+        );
+        assert_eq!(
+            builder()
+                .with_bom(true)
+                .build()
+                .format_to_string(
+                    Level::LOG_INFO,
+                    "Hello, 世界!",
+                    Some(std::time::UNIX_EPOCH.into()),
+                    None
+                )
+                .unwrap(),
+            golden
+        );
+    }
+
+    #[test]
+    fn hostname_from_ip() {
+        let v4: std::net::IpAddr = [192, 0, 2, 1].into();
+        let v6: std::net::IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(
+            Hostname::from_ip(v4, false).unwrap().to_string(),
+            "192.0.2.1"
+        );
+        // Bracketing only applies to IPv6
+        assert_eq!(
+            Hostname::from_ip(v4, true).unwrap().to_string(),
+            "192.0.2.1"
+        );
+        assert_eq!(
+            Hostname::from_ip(v6, false).unwrap().to_string(),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            Hostname::from_ip(v6, true).unwrap().to_string(),
+            "[2001:db8::1]"
+        );
+        // & the result should make it into a well-formed message
+        let f = Rfc5424::builder()
+            .hostname(Hostname::from_ip(v6, true).unwrap())
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            parse(&rsp).unwrap().hostname.as_deref(),
+            Some("[2001:db8::1]")
+        );
+    }
+
+    #[test]
+    fn hostname_fallback_error() {
+        let no_hostname = || {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no hostname",
+            ))
+        };
+
+        // Failing to get the hostname falls back to the IP address...
+        let hostname =
+            Hostname::from_host_or_ip(no_hostname(), || Ok([10, 0, 0, 1].into())).unwrap();
+        assert_eq!(hostname.to_string(), "10.0.0.1");
+
+        // & should that fail too, the reason is reported.
+        let err = Hostname::from_host_or_ip(no_hostname(), || {
+            Err(local_ip_address::Error::LocalIpAddressNotFound)
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::BadIpAddress { .. }));
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(matches!(
+            source.downcast_ref::<local_ip_address::Error>(),
+            Some(local_ip_address::Error::LocalIpAddressNotFound)
+        ));
+        assert!(err.to_string().contains(&source.to_string()));
+    }
+
+    #[test]
+    fn nil_header_fields() {
+        let builder = || {
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+        };
+        let ts = Some(std::time::UNIX_EPOCH.into());
+        for (f, golden) in [
+            (
+                builder().with_nil_hostname().build(),
+                "<14>1 1970-01-01T00:00:00+00:00 - prototyping 123 - - Hello",
+            ),
+            (
+                builder().with_nil_appname().build(),
+                "<14>1 1970-01-01T00:00:00+00:00 bree.local - 123 - - Hello",
+            ),
+            (
+                builder().with_nil_procid().build(),
+                "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping - - - Hello",
+            ),
+            (
+                builder()
+                    .with_nil_hostname()
+                    .with_nil_appname()
+                    .with_nil_procid()
+                    .build(),
+                "<14>1 1970-01-01T00:00:00+00:00 - - - - - Hello",
+            ),
+        ] {
+            let rsp = f
+                .format_to_string(Level::LOG_INFO, "Hello", ts, None)
+                .unwrap();
+            assert_eq!(rsp, golden);
+            // & make sure the result is still a well-formed message
+            let parsed = parse(rsp.as_bytes()).unwrap();
+            let nil_or = |field: Option<String>| field.unwrap_or_else(|| "-".to_string());
+            assert_eq!(nil_or(parsed.hostname), f.hostname().to_string());
+            assert_eq!(nil_or(parsed.appname), f.appname().to_string());
+            assert_eq!(nil_or(parsed.procid), f.pid().to_string());
+            assert_eq!(parsed.msg, "Hello");
+        }
+    }
+
+    #[test]
+    fn header_fields_at_boundaries() {
+        // Empty fields are rendered as the NILVALUE...
+        let f = Rfc5424::builder()
+            .hostname(Hostname::new(vec![]).unwrap())
+            .appname(AppName::new(vec![]).unwrap())
+            .pid(ProcId::new(vec![]).unwrap())
+            .build();
+        assert_eq!(
+            f.format_to_string(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                None
+            )
+            .unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 - - - - - Hello"
+        );
+        assert_eq!(format!("{}", Hostname::new(vec![]).unwrap()), "-");
+
+        // while the longest legal values are rendered verbatim.
+        let hostname = vec![b'h'; 255];
+        let appname = vec![b'a'; 48];
+        let pid = vec![b'p'; 128];
+        let f = Rfc5424::builder()
+            .hostname(Hostname::new(hostname.clone()).unwrap())
+            .appname(AppName::new(appname.clone()).unwrap())
+            .pid(ProcId::new(pid.clone()).unwrap())
+            .build();
+        let rsp = f
+            .format(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap();
+        let mut golden = b"<14>1 1970-01-01T00:00:00+00:00 ".to_vec();
+        golden.extend_from_slice(&hostname);
+        golden.push(b' ');
+        golden.extend_from_slice(&appname);
+        golden.push(b' ');
+        golden.extend_from_slice(&pid);
+        golden.extend_from_slice(b" - - Hello");
+        assert_eq!(rsp, golden);
+
+        // Check each bound exactly: zero & one bytes are fine, as are the maxima, but one more
+        // than the maximum is not.
+        for len in [0, 1, HOSTNAME_MAX_LEN] {
+            assert!(Hostname::new(vec![b'h'; len]).is_ok());
+        }
+        assert!(Hostname::new(vec![b'h'; HOSTNAME_MAX_LEN + 1]).is_err());
+        assert_eq!(HOSTNAME_MAX_LEN, 255);
+        for len in [0, 1, APP_NAME_MAX_LEN] {
+            assert!(AppName::new(vec![b'a'; len]).is_ok());
+        }
+        assert!(AppName::new(vec![b'a'; APP_NAME_MAX_LEN + 1]).is_err());
+        assert_eq!(APP_NAME_MAX_LEN, 48);
+        for len in [0, 1, PROCID_MAX_LEN] {
+            assert!(ProcId::new(vec![b'p'; len]).is_ok());
+        }
+        assert!(ProcId::new(vec![b'p'; PROCID_MAX_LEN + 1]).is_err());
+        assert_eq!(PROCID_MAX_LEN, 128);
+
+        // A one-byte value is written as-is (& in particular, isn't confused with the NILVALUE)
+        let f = Rfc5424::builder()
+            .hostname(Hostname::new(b"h".to_vec()).unwrap())
+            .appname(AppName::new(b"a".to_vec()).unwrap())
+            .pid(ProcId::new(b"p".to_vec()).unwrap())
+            .build();
+        let parsed = parse(
+            &f.format(
+                Level::LOG_INFO,
+                "Hello",
+                Some(std::time::UNIX_EPOCH.into()),
+                None,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(parsed.hostname.as_deref(), Some("h"));
+        assert_eq!(parsed.appname.as_deref(), Some("a"));
+        assert_eq!(parsed.procid.as_deref(), Some("p"));
+    }
+
+    #[test]
+    fn clone() {
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .facility(Facility::LOG_LOCAL3)
+            .with_bom(true)
+            .with_tracing_target(true)
+            .build();
+        let g = f.clone();
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let ts = Some(std::time::UNIX_EPOCH.into());
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hello", ts, Some(meta)).unwrap(),
+            g.format(Level::LOG_INFO, "Hello", ts, Some(meta)).unwrap()
+        );
+    }
+
+    #[test]
+    fn debug() {
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .facility(Facility::LOG_LOCAL3)
+            .build();
+        let dbg = format!("{:?}", f);
+        assert!(dbg.contains("Hostname(\"bree.local\")"));
+        assert!(dbg.contains("AppName(\"prototyping\")"));
+        assert!(dbg.contains("LOG_LOCAL3"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .facility(Facility::LOG_LOCAL3)
+            .with_bom(true)
+            .with_tracing_target(true)
+            .build();
+        let ts = Utc.timestamp_opt(1_000_000_000, 123_456_000).unwrap();
+        let rsp = f
+            .format(Level::LOG_WARNING, "Hello, 世界!", Some(ts), Some(meta))
+            .unwrap();
+
+        let parsed = parse(&rsp).unwrap();
+        assert_eq!(parsed.facility, Facility::LOG_LOCAL3);
+        assert_eq!(parsed.level, Level::LOG_WARNING);
+        assert_eq!(parsed.timestamp, Some(ts));
+        assert_eq!(parsed.hostname.as_deref(), Some("bree.local"));
+        assert_eq!(parsed.appname.as_deref(), Some("prototyping"));
+        assert_eq!(parsed.procid.as_deref(), Some("123"));
+        assert_eq!(parsed.msgid, None);
+        assert_eq!(parsed.structured_data.len(), 1);
+        assert_eq!(
+            parsed.structured_data[DEFAULT_TRACING_METADATA_SDID]
+                .get("target")
+                .map(String::as_str),
+            Some("my-target")
+        );
+        assert_eq!(parsed.msg, "Hello, 世界!");
+
+        // Malformed input is an error, not a panic
+        assert!(matches!(parse(b"<14>1 garbage"), Err(Error::Parse { .. })));
+        assert!(matches!(parse(&[0xff, 0xfe]), Err(Error::Parse { .. })));
+    }
+
+    #[test]
+    fn pri() {
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        for code in 0..24 {
+            let f = Rfc5424::builder()
+                .facility(Facility::from_code(code).unwrap())
+                .build();
+            for severity in 0..8 {
+                let buf = f
+                    .format(Level::from_code(severity).unwrap(), "Hello", ts, None)
+                    .unwrap();
+                let prefix = format!("<{}>1 ", code as u32 * 8 + severity as u32);
+                assert!(buf.starts_with(prefix.as_bytes()));
+            }
+        }
+    }
+
+    #[test]
+    fn timestamp() {
+        for (secs, nanos) in [
+            (1_000_000_000, 0),
+            (1_000_000_000, 120_000_000),
+            (1_000_000_000, 123_456_000),
+            (1_000_000_000, 123_456_789),
+            (1_000_000_019, 1_500_000_000), // leap second
+            (0, 1),
+            (-62_135_596_800, 0), // 0001-01-01
+        ] {
+            let ts = Utc.timestamp_opt(secs, nanos).unwrap();
+            let mut buf = Vec::new();
+            write_timestamp(&mut buf, &ts);
+            assert_eq!(String::from_utf8(buf).unwrap(), ts.to_rfc3339());
+        }
+    }
+
+    #[test]
+    fn standard_sd_elements() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_tracing_target(true)
+            .with_time_quality(TimeQuality {
+                tz_known: true,
+                is_synced: true,
+                sync_accuracy: Some(60000),
+            })
+            .with_origin(Origin {
+                ip: vec!["192.0.2.1".parse().unwrap()],
+                enterprise_id: Some("32473.1".to_string()),
+                software: Some("prototyping".to_string()),
+                sw_version: Some("1.0.0".to_string()),
+            })
+            .unwrap()
+            .build();
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let text = f
+            .format_to_string(Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(
+            text,
+            "<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - \
+             [timeQuality tzKnown=\"1\" isSynced=\"1\" syncAccuracy=\"60000\"]\
+             [origin ip=\"192.0.2.1\" enterpriseId=\"32473.1\" software=\"prototyping\" \
+             swVersion=\"1.0.0\"][tracing-meta@64700 target=\"my-target\"] Hello"
+        );
+
+        let parsed = parse(text.as_bytes()).unwrap();
+        let tq = &parsed.structured_data["timeQuality"];
+        assert_eq!(tq["tzKnown"], "1");
+        assert_eq!(tq["isSynced"], "1");
+        assert_eq!(tq["syncAccuracy"], "60000");
+        let origin = &parsed.structured_data["origin"];
+        assert_eq!(origin["ip"], "192.0.2.1");
+        assert_eq!(origin["enterpriseId"], "32473.1");
+        assert_eq!(origin["software"], "prototyping");
+        assert_eq!(origin["swVersion"], "1.0.0");
+
+        // syncAccuracy is omitted for unsynchronized clocks, as is an empty origin element
+        let f = Rfc5424::builder()
+            .with_time_quality(TimeQuality {
+                tz_known: true,
+                is_synced: false,
+                sync_accuracy: Some(60000),
+            })
+            .with_origin(Origin::default())
+            .unwrap()
+            .build();
+        let parsed = parse(&f.format(Level::LOG_INFO, "Hello", ts, None).unwrap()).unwrap();
+        assert_eq!(parsed.structured_data.len(), 1);
+        assert_eq!(parsed.structured_data["timeQuality"].len(), 2);
+        assert_eq!(parsed.structured_data["timeQuality"]["isSynced"], "0");
+
+        // Bad origins are rejected
+        for origin in [
+            Origin {
+                enterprise_id: Some("32473.".to_string()),
+                ..Default::default()
+            },
+            Origin {
+                enterprise_id: Some("acme".to_string()),
+                ..Default::default()
+            },
+            Origin {
+                sw_version: Some("x".repeat(33)),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                Rfc5424::builder().with_origin(origin),
+                Err(Error::BadSdParam { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn bom_escaping_and_truncation() {
+        let builder = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_bom(true)
+            .with_escape_control_chars(true);
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let header = b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - -".to_vec();
+        let packet = |msg: &[u8]| {
+            let mut packet = header.clone();
+            packet.extend_from_slice(msg);
+            packet
+        };
+
+        // BOM first, then the escaped MSG
+        let f = builder.clone().build();
+        assert_eq!(
+            f.format(Level::LOG_INFO, "a\tb\n", ts, None).unwrap(),
+            packet(b" \xef\xbb\xbfa\\tb\\n")
+        );
+        assert_eq!(
+            f.format_to_string(Level::LOG_INFO, "a\tb\n", ts, None)
+                .unwrap()
+                .into_bytes(),
+            packet(b" a\\tb\\n")
+        );
+
+        // The limit counts the whole packet, BOM included...
+        let f = builder
+            .clone()
+            .with_max_length(header.len() + 1 + 3 + 5)
+            .build();
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hello, world!", ts, None)
+                .unwrap(),
+            packet(b" \xef\xbb\xbfHello")
+        );
+        // never splits a character...
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hell\u{f6}", ts, None).unwrap(),
+            packet(b" \xef\xbb\xbfHell")
+        );
+        // nor an escape sequence...
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hell\no", ts, None).unwrap(),
+            packet(b" \xef\xbb\xbfHell")
+        );
+        // & drops the SP & BOM if there's no room for any of the MSG.
+        let f = builder.with_max_length(header.len() + 1 + 3).build();
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hello, world!", ts, None)
+                .unwrap(),
+            header
+        );
+        assert_eq!(
+            f.format_to_string(Level::LOG_INFO, "Hello, world!", ts, None)
+                .unwrap()
+                .into_bytes(),
+            header
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn pid_from_uuid() {
+        let uuid = uuid::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_from_uuid(uuid)
+            .unwrap()
+            .build();
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        assert_eq!(
+            f.format_to_string(Level::LOG_INFO, "Hello", ts, None)
+                .unwrap(),
+            "<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping \
+             67e55044-10b1-426f-9247-bb680e5fe0c8 - - Hello"
+        );
+    }
+
+    #[test]
+    fn escape_unicode() {
+        let builder = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_bom(true)
+            .with_escape_unicode(true);
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+        let header = b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - -".to_vec();
+
+        // Escaping wins over the BOM, & the result is pure ASCII
+        let f = builder.clone().build();
+        let buf = f.format(Level::LOG_INFO, "Hi, 世界!", ts, None).unwrap();
+        assert!(buf.is_ascii());
+        let mut expected = header.clone();
+        expected.extend_from_slice(b" \\u{48}\\u{69}\\u{2c}\\u{20}\\u{4e16}\\u{754c}\\u{21}");
+        assert_eq!(buf, expected);
+
+        // Escape sequences are never split by the length limit
+        let f = builder.with_max_length(header.len() + 1 + 13).build();
+        let mut expected = header;
+        expected.extend_from_slice(b" \\u{48}\\u{69}");
+        assert_eq!(
+            f.format(Level::LOG_INFO, "Hi, 世界!", ts, None).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn static_sd() {
+        let f = Rfc5424::builder()
+            .with_static_sd(
+                "env@32473",
+                vec![
+                    ("deployment".to_string(), "prod".to_string()),
+                    ("region".to_string(), "us-\"west\"-2".to_string()),
+                ],
+            )
+            .unwrap()
+            .build();
+        for msg in ["Hello", "world"] {
+            let buf = f.format(Level::LOG_INFO, msg, None, None).unwrap();
+            let parsed = parse(&buf).unwrap();
+            assert_eq!(parsed.msg, msg);
+            let sd = &parsed.structured_data["env@32473"];
+            assert_eq!(sd["deployment"], "prod");
+            assert_eq!(sd["region"], "us-\"west\"-2");
+        }
+
+        assert!(Rfc5424::builder().with_static_sd("no way", vec![]).is_err());
+        assert!(Rfc5424::builder()
+            .with_static_sd("env@32473", vec![("a=b".to_string(), "c".to_string())])
+            .is_err());
+    }
+
+    #[test]
+    fn max_sd_length() {
+        let fields = (0..10)
+            .map(|i| {
+                (
+                    ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"][i],
+                    "x".repeat(10),
+                )
+            })
+            .collect::<Vec<(&str, String)>>();
+        let extras = Extras::new().fields(&fields);
+        let builder = || {
+            Rfc5424::builder()
+                .with_static_sd(
+                    "env@32473",
+                    vec![("deployment".to_string(), "prod".to_string())],
+                )
+                .unwrap()
+                .with_all_fields(true)
+                .with_max_sd_length(128)
+        };
+
+        // `[env@32473 deployment="prod"]` is 29 bytes & the marker 36, leaving room for three
+        // 15-byte fields (the first also paying 14 bytes to open & close the element)
+        let buf = builder()
+            .build()
+            .format_with(Level::LOG_INFO, "Hello", None, None, &extras)
+            .unwrap();
+        let text = String::from_utf8(buf.clone()).unwrap();
+        assert!(text.contains(concat!(
+            r#" [env@32473 deployment="prod"][fields@64700 a="xxxxxxxxxx" b="xxxxxxxxxx" "#,
+            r#"c="xxxxxxxxxx"][syslog-tracing@64700 truncated="1"] Hello"#
+        )));
+        let parsed = parse(&buf).unwrap();
+        assert_eq!(parsed.structured_data[FIELDS_SDID].len(), 3);
+        assert_eq!(
+            parsed.structured_data[TRUNCATION_MARKER_SDID]["truncated"],
+            "1"
+        );
+
+        // Without the marker, there's room for another field
+        let buf = builder()
+            .with_sd_truncation_marker(false)
+            .build()
+            .format_with(Level::LOG_INFO, "Hello", None, None, &extras)
+            .unwrap();
+        let parsed = parse(&buf).unwrap();
+        assert_eq!(parsed.structured_data[FIELDS_SDID].len(), 5);
+        assert!(!parsed.structured_data.contains_key(TRUNCATION_MARKER_SDID));
+
+        // Putting the fields first squeezes-out the static element
+        let buf = builder()
+            .with_sd_order(&[SdSource::Fields])
+            .build()
+            .format_with(Level::LOG_INFO, "Hello", None, None, &extras)
+            .unwrap();
+        let parsed = parse(&buf).unwrap();
+        assert_eq!(parsed.structured_data[FIELDS_SDID].len(), 5);
+        assert!(!parsed.structured_data.contains_key("env@32473"));
+
+        // & with no budget, everything is written
+        let buf = Rfc5424::builder()
+            .with_all_fields(true)
+            .build()
+            .format_with(Level::LOG_INFO, "Hello", None, None, &extras)
+            .unwrap();
+        let parsed = parse(&buf).unwrap();
+        assert_eq!(parsed.structured_data[FIELDS_SDID].len(), 10);
+    }
+
+    #[test]
+    fn absent_tracing_metadata() {
+        // Records bridged from the `log` crate, e.g., may have no module, file or line
+        let meta = crate::test_event!(
+            name: "log record",
+            file: None,
+            line: None,
+            module: None,
+            level: tracing::Level::INFO,
+            target: "",
+            |event| event.metadata()
+        );
+        for split_sd in [false, true] {
+            let f = Rfc5424::builder()
+                .with_tracing_target(true)
+                .with_tracing_module(true)
+                .with_tracing_source_location(true)
+                .with_split_sd(split_sd)
+                .build();
+            let rsp = f
+                .format_to_string(Level::LOG_INFO, "Hello", None, Some(meta))
+                .unwrap();
+            // MSGID & STRUCTURED-DATA both NIL
+            assert!(rsp.ends_with(" - - Hello"), "{}", rsp);
+            assert!(!rsp.contains('['), "{}", rsp);
+        }
+    }
+
+    #[test]
+    fn try_default() {
+        let f = Rfc5424::try_default().unwrap();
+        assert_eq!(f.pid().to_string(), std::process::id().to_string());
+        assert_ne!(f.appname().to_string(), "-");
+    }
+
+    #[test]
+    fn all_fields() {
+        let fields = [
+            ("a", "1".to_string()),
+            ("not a name", "x".to_string()),
+            ("a", "2".to_string()),
+            ("b", "3".to_string()),
+        ];
+        let extras = Extras::new().fields(&fields);
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 0).unwrap());
+
+        // Off by default
+        let buf = Rfc5424::minimal()
+            .format_with(Level::LOG_INFO, "Hello", ts, None, &extras)
+            .unwrap();
+        assert!(buf.ends_with(b" - - - - Hello"));
+
+        let buf = Rfc5424::builder()
+            .with_all_fields(true)
+            .build()
+            .format_with(Level::LOG_INFO, "Hello", ts, None, &extras)
+            .unwrap();
+        assert!(buf.ends_with(b" - [fields@64700 a=\"1\" a.2=\"2\" b=\"3\"] Hello"));
+    }
+
+    #[test]
+    fn format_into() {
+        let meta = crate::test_event!(level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_tracing_target(true)
+            .with_tracing_source_location(true)
+            .build();
+        let ts = Some(Utc.timestamp_opt(1_000_000_000, 123_456_000).unwrap());
+
+        // `format_into` should produce just what `format` does...
+        let mut buf = Vec::with_capacity(1024);
+        f.format_into(&mut buf, Level::LOG_INFO, "Hello", ts, Some(meta))
+            .unwrap();
+        assert_eq!(
+            buf,
+            f.format(Level::LOG_INFO, "Hello", ts, Some(meta)).unwrap()
+        );
+
+        // & the buffer should be re-used from message to message.
+        let (ptr, cap) = (buf.as_ptr(), buf.capacity());
+        for i in 0..1000 {
+            buf.clear();
+            f.format_into(
+                &mut buf,
+                Level::LOG_INFO,
+                &format!("Hello #{}", i),
+                ts,
+                None,
+            )
+            .unwrap();
+            assert_eq!((buf.as_ptr(), buf.capacity()), (ptr, cap));
+        }
+        assert!(buf.ends_with(b" - - Hello #999"));
+    }
+
+    #[test]
+    fn event_name_as_msgid() {
+        let qualifies = crate::test_event!(name: "login-failed", level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let does_not = crate::test_event!(name: "event src/main.rs:12", level: tracing::Level::INFO, target: "my-target", |event| event.metadata());
+        let f = Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_event_name_as_msgid(true)
+            .build();
+        let ts = Some(std::time::UNIX_EPOCH.into());
+
+        assert_eq!(
+            f.format_to_string(Level::LOG_INFO, "Hello", ts, Some(qualifies))
+                .unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 login-failed - Hello"
+        );
+        assert_eq!(
+            f.format_to_string(Level::LOG_INFO, "Hello", ts, Some(does_not))
+                .unwrap(),
+            "<14>1 1970-01-01T00:00:00+00:00 bree.local prototyping 123 - - Hello"
+        );
     }
 }
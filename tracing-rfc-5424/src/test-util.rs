@@ -0,0 +1,460 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Utilities for testing code that logs via a syslog [`Layer`].
+//!
+//! This module is only available with the `test-util` feature. [`TestCollector`] stands in for a
+//! syslog daemon: it listens on an ephemeral UDP, TCP or Unix domain socket & hands back the raw
+//! messages it receives, so that a [`Layer`] may be exercised end-to-end without a live daemon.
+//! The [`test_event!`](crate::test_event) macro builds an [`Event`] by hand, for testing
+//! [`TracingFormatter`] implementations without any subscriber at all.
+//!
+//! [`Event`]: tracing::Event
+//! [`TracingFormatter`]: crate::tracing::TracingFormatter
+//!
+//! ```
+//! use std::time::Duration;
+//! use tracing_rfc_5424::{layer::Layer, test_util::TestCollector, transport::UdpTransport};
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let mut collector = TestCollector::udp().unwrap();
+//! let subscriber = tracing_subscriber::Registry::default().with(Layer::with_transport(
+//!     UdpTransport::new(collector.addr().unwrap()).unwrap(),
+//! ));
+//! tracing::subscriber::with_default(subscriber, || {
+//!     tracing::info!("Hello, world!");
+//! });
+//! let msg = collector.recv_one(Duration::from_secs(5)).unwrap();
+//! assert!(msg.ends_with(b"Hello, world!"));
+//! ```
+//!
+//! [`Layer`]: crate::layer::Layer
+
+use std::{
+    io::BufRead,
+    net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixDatagram, path::PathBuf};
+
+/// Build an [`Event`] with the given level, target & message, and pass it to a closure
+///
+/// `level` must be a constant [`tracing::Level`] & `target` a constant string, since they go into
+/// the event's `'static` [`Metadata`]. The event has a single field, "message". The macro
+/// evaluates to whatever the closure returns:
+///
+/// ```rust
+/// use tracing::field::Visit;
+/// use tracing_rfc_5424::{test_event, tracing::MessageEventVisitor};
+///
+/// let message = test_event!(tracing::Level::INFO, "my-target", "Hello, world!", |event| {
+///     let mut visitor = MessageEventVisitor::new();
+///     event.record(&mut visitor);
+///     visitor.message().map(|msg| msg.to_string())
+/// });
+/// assert_eq!(message.as_deref(), Some("Hello, world!"));
+/// ```
+///
+/// Events with other metadata may be built by naming the arguments, in this order: an optional
+/// `name` (the default is "test event"), optional `file`, `line` & `module` (given together; the
+/// defaults are the invocation site), an optional `message` (with no message, the event has no
+/// fields at all), `level` & `target`. Since the [`Metadata`] lives for `'static`, the
+/// closure may hand it back for use on its own:
+///
+/// ```rust
+/// use tracing_rfc_5424::test_event;
+///
+/// // Say, a record bridged from the `log` crate
+/// let meta = test_event!(
+///     name: "log record",
+///     file: None,
+///     line: None,
+///     module: None,
+///     level: tracing::Level::INFO,
+///     target: "",
+///     |event| event.metadata()
+/// );
+/// assert_eq!(meta.name(), "log record");
+/// assert!(meta.fields().field("message").is_none());
+/// assert!(meta.file().is_none());
+/// ```
+///
+/// [`Event`]: tracing::Event
+/// [`Metadata`]: tracing::Metadata
+#[macro_export]
+macro_rules! test_event {
+    (@or , $default:expr) => {
+        $default
+    };
+    (@or $x:expr, $default:expr) => {
+        $x
+    };
+    (@dispatch $metadata:ident, $f:expr, $msg:expr) => {{
+        let fields = $metadata.fields();
+        let field = fields.field("message").unwrap();
+        let message = ::std::format!("{}", $msg);
+        let value = ::tracing::field::display(message.as_str());
+        let values = [(
+            &field,
+            ::std::option::Option::Some(&value as &dyn ::tracing::Value),
+        )];
+        let value_set = fields.value_set(&values);
+        $crate::test_util::with_event(&::tracing::Event::new($metadata, &value_set), $f)
+    }};
+    (@dispatch $metadata:ident, $f:expr) => {{
+        let values: [(
+            &::tracing::field::Field,
+            ::std::option::Option<&dyn ::tracing::Value>,
+        ); 0] = [];
+        let value_set = $metadata.fields().value_set(&values);
+        $crate::test_util::with_event(&::tracing::Event::new($metadata, &value_set), $f)
+    }};
+    (
+        $(name: $name:expr,)?
+        $(file: $file:expr, line: $line:expr, module: $module:expr,)?
+        $(message: $msg:expr,)?
+        level: $level:expr,
+        target: $target:expr,
+        $f:expr
+    ) => {{
+        static CALLSITE: $crate::test_util::TestCallsite = {
+            static METADATA: ::tracing::Metadata<'static> = ::tracing::Metadata::new(
+                $crate::test_event!(@or $($name)?, "test event"),
+                $target,
+                $level,
+                $crate::test_event!(@or $($file)?, ::std::option::Option::Some(::std::file!())),
+                $crate::test_event!(@or $($line)?, ::std::option::Option::Some(::std::line!())),
+                $crate::test_event!(
+                    @or $($module)?,
+                    ::std::option::Option::Some(::std::module_path!())
+                ),
+                ::tracing::field::FieldSet::new(
+                    $crate::test_event!(@or $({ let _ = ::std::stringify!($msg); &["message"] })?, &[]),
+                    ::tracing::callsite::Identifier(&CALLSITE),
+                ),
+                ::tracing::metadata::Kind::EVENT,
+            );
+            $crate::test_util::TestCallsite::new(&METADATA)
+        };
+        let metadata = ::tracing::Callsite::metadata(&CALLSITE);
+        $crate::test_event!(@dispatch metadata, $f $(, $msg)?)
+    }};
+    ($level:expr, $target:expr, $msg:expr, $f:expr) => {
+        $crate::test_event!(message: $msg, level: $level, target: $target, $f)
+    };
+}
+
+/// The [`Callsite`] behind events built by [`test_event!`](crate::test_event); not for direct
+/// use
+///
+/// [`Callsite`]: tracing::Callsite
+#[doc(hidden)]
+pub struct TestCallsite {
+    metadata: &'static tracing::Metadata<'static>,
+}
+
+impl TestCallsite {
+    pub const fn new(metadata: &'static tracing::Metadata<'static>) -> TestCallsite {
+        TestCallsite { metadata }
+    }
+}
+
+/// Invoke `f` on `event`; this gives closures passed to [`test_event!`](crate::test_event) a
+/// known argument type. Not for direct use.
+#[doc(hidden)]
+pub fn with_event<R, F: FnOnce(&tracing::Event<'_>) -> R>(event: &tracing::Event<'_>, f: F) -> R {
+    f(event)
+}
+
+impl tracing::Callsite for TestCallsite {
+    fn set_interest(&self, _interest: tracing::subscriber::Interest) {}
+    fn metadata(&self) -> &tracing::Metadata<'static> {
+        self.metadata
+    }
+}
+
+enum Inner {
+    Udp(UdpSocket),
+    Tcp {
+        listener: TcpListener,
+        // Established on the first call to `recv_one()`
+        stream: Option<std::io::BufReader<TcpStream>>,
+    },
+    #[cfg(unix)]
+    Unix {
+        socket: UnixDatagram,
+        path: PathBuf,
+    },
+}
+
+/// A stand-in for a syslog daemon, listening on an ephemeral socket
+///
+/// Construct one via [`udp`](TestCollector::udp), [`tcp`](TestCollector::tcp) or
+/// [`unix`](TestCollector::unix), point a [`Transport`] at its [`addr`](TestCollector::addr) (or
+/// [`path`](TestCollector::path)), log, and then retrieve what was sent via
+/// [`recv_one`](TestCollector::recv_one).
+///
+/// TCP messages are expected to be newline-framed (as [`TcpTransport`] sends them); the trailing
+/// newline is stripped. Only the first TCP connection made to the collector is served.
+///
+/// [`Transport`]: crate::transport::Transport
+/// [`TcpTransport`]: crate::transport::TcpTransport
+pub struct TestCollector {
+    inner: Inner,
+}
+
+impl TestCollector {
+    /// Listen for datagrams on an ephemeral UDP port on the loopback interface
+    pub fn udp() -> std::io::Result<TestCollector> {
+        Ok(TestCollector {
+            inner: Inner::Udp(UdpSocket::bind("127.0.0.1:0")?),
+        })
+    }
+    /// Listen for a connection on an ephemeral TCP port on the loopback interface
+    pub fn tcp() -> std::io::Result<TestCollector> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        // So that `recv_one()` can give up on a connection that never comes
+        listener.set_nonblocking(true)?;
+        Ok(TestCollector {
+            inner: Inner::Tcp {
+                listener,
+                stream: None,
+            },
+        })
+    }
+    /// Listen for datagrams on a Unix domain socket in the temporary directory
+    ///
+    /// The socket is removed when the collector is dropped.
+    #[cfg(unix)]
+    pub fn unix() -> std::io::Result<TestCollector> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tracing-rfc-5424-{}-{}.sock",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_file(&path);
+        Ok(TestCollector {
+            inner: Inner::Unix {
+                socket: UnixDatagram::bind(&path)?,
+                path,
+            },
+        })
+    }
+    /// The address on which this collector is listening, if it's a UDP or TCP collector
+    pub fn addr(&self) -> Option<SocketAddr> {
+        match &self.inner {
+            Inner::Udp(socket) => socket.local_addr().ok(),
+            Inner::Tcp { listener, .. } => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Inner::Unix { .. } => None,
+        }
+    }
+    /// The path at which this collector is listening, if it's a Unix domain socket collector
+    #[cfg(unix)]
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match &self.inner {
+            Inner::Unix { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+    /// Wait up to `timeout` for the next message; return `None` if none arrives in that time (or
+    /// if the sender hung up)
+    pub fn recv_one(&mut self, timeout: Duration) -> Option<Vec<u8>> {
+        // Zero timeouts are an error for `set_read_timeout()`
+        let timeout = timeout.max(Duration::from_millis(1));
+        match &mut self.inner {
+            Inner::Udp(socket) => {
+                socket.set_read_timeout(Some(timeout)).ok()?;
+                let mut buf = vec![0u8; 65536];
+                let n = socket.recv(&mut buf).ok()?;
+                buf.truncate(n);
+                Some(buf)
+            }
+            Inner::Tcp { listener, stream } => {
+                let deadline = Instant::now() + timeout;
+                if stream.is_none() {
+                    let conn = loop {
+                        match listener.accept() {
+                            Ok((conn, _)) => break conn,
+                            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                                if Instant::now() >= deadline {
+                                    return None;
+                                }
+                                std::thread::sleep(Duration::from_millis(5));
+                            }
+                            Err(_) => return None,
+                        }
+                    };
+                    conn.set_nonblocking(false).ok()?;
+                    *stream = Some(std::io::BufReader::new(conn));
+                }
+                let reader = stream.as_mut()?;
+                let remaining = deadline
+                    .saturating_duration_since(Instant::now())
+                    .max(Duration::from_millis(1));
+                reader.get_ref().set_read_timeout(Some(remaining)).ok()?;
+                let mut buf = Vec::new();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => {
+                        if buf.last() == Some(&b'\n') {
+                            buf.pop();
+                        }
+                        Some(buf)
+                    }
+                }
+            }
+            #[cfg(unix)]
+            Inner::Unix { socket, .. } => {
+                socket.set_read_timeout(Some(timeout)).ok()?;
+                let mut buf = vec![0u8; 65536];
+                let n = socket.recv(&mut buf).ok()?;
+                buf.truncate(n);
+                Some(buf)
+            }
+        }
+    }
+}
+
+impl Drop for TestCollector {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Inner::Unix { path, .. } = &self.inner {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::{
+        formatter::Clock,
+        layer::Layer,
+        rfc5424::Rfc5424,
+        tracing::TrivialTracingFormatter,
+        transport::{TcpTransport, UdpTransport},
+    };
+
+    use chrono::prelude::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn formatter() -> Rfc5424 {
+        Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .with_clock(Clock::fixed(Utc.timestamp_opt(1_000_000_000, 0).unwrap()))
+            .build()
+    }
+
+    #[test]
+    fn udp() {
+        let mut collector = TestCollector::udp().unwrap();
+        let subscriber = tracing_subscriber::Registry::default().with(Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            UdpTransport::new(collector.addr().unwrap()).unwrap(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+        });
+        assert_eq!(
+            collector.recv_one(Duration::from_secs(5)).unwrap(),
+            b"<14>1 2001-09-09T01:46:40+00:00 bree.local prototyping 123 - - Hello, world!"
+        );
+        assert_eq!(collector.recv_one(Duration::from_millis(10)), None);
+    }
+
+    #[test]
+    fn tcp() {
+        let mut collector = TestCollector::tcp().unwrap();
+        let subscriber = tracing_subscriber::Registry::default().with(Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            TcpTransport::new(collector.addr().unwrap()).unwrap(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+            tracing::warn!("Goodbye, world!");
+        });
+        assert!(collector
+            .recv_one(Duration::from_secs(5))
+            .unwrap()
+            .ends_with(b"Hello, world!"));
+        assert!(collector
+            .recv_one(Duration::from_secs(5))
+            .unwrap()
+            .starts_with(b"<12>1 "));
+    }
+
+    #[test]
+    fn test_event() {
+        use crate::tracing::MessageEventVisitor;
+        let (message, level, target) = crate::test_event!(
+            tracing::Level::WARN,
+            "my-target",
+            "Hello, 世界!",
+            |event| {
+                let mut visitor = MessageEventVisitor::new();
+                event.record(&mut visitor);
+                (
+                    visitor.message().map(|msg| msg.to_string()),
+                    *event.metadata().level(),
+                    event.metadata().target(),
+                )
+            }
+        );
+        assert_eq!(message.as_deref(), Some("Hello, 世界!"));
+        assert_eq!(level, tracing::Level::WARN);
+        assert_eq!(target, "my-target");
+
+        // Each expansion gets its own callsite
+        let level = crate::test_event!(tracing::Level::ERROR, "my-target", 5, |event| {
+            *event.metadata().level()
+        });
+        assert_eq!(level, tracing::Level::ERROR);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        let mut collector = TestCollector::unix().unwrap();
+        let path = collector.path().unwrap().to_path_buf();
+        let subscriber = tracing_subscriber::Registry::default().with(Layer::new(
+            formatter(),
+            TrivialTracingFormatter::default(),
+            crate::transport::UnixSocket::new(&path).unwrap(),
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Hello, world!");
+        });
+        assert!(collector
+            .recv_one(Duration::from_secs(5))
+            .unwrap()
+            .ends_with(b"Hello, world!"));
+        drop(collector);
+        assert!(!path.exists());
+    }
+}
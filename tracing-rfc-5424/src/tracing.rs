@@ -16,8 +16,9 @@
 //! Primitives for mapping [`tracing`] entities to syslog messages.
 //!
 //! [`TracingFormatter`] implementations handle encoding [`Event`]s and [`Span`]s into text. This
-//! module provides at this time only a single implementation: [`TrivialTracingFormatter`] that
-//! simply extracts the "message" field from [`Event`]s.
+//! module provides at this time two implementations: [`TrivialTracingFormatter`] that simply
+//! extracts the "message" field from [`Event`]s, and [`SpanLifecycleFormatter`] which does the same
+//! but additionally produces a message each time a [`Span`] is entered or exited.
 //!
 //! [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
 //! [`Span`]: https://docs.rs/tracing/0.1.35/tracing/struct.Span.html
@@ -88,13 +89,44 @@ pub trait TracingFormatter<S>
 where
     S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
 {
-    type Error: std::error::Error + 'static;
+    type Error: std::error::Error + Send + Sync + 'static;
     /// An event has occurred
     fn on_event(
         &self,
         event: &tracing::Event,
         ctx: tracing_subscriber::layer::Context<'_, S>,
     ) -> StdResult<Option<(String, Level)>, Self::Error>;
+    /// The key-value fields of `event`, as (name, value) pairs
+    ///
+    /// This is consulted only when the [`SyslogFormatter`] in use [wants them]. The `message` field
+    /// & the reserved `syslog.*` fields should be omitted. The default implementation returns no
+    /// fields.
+    ///
+    /// [`SyslogFormatter`]: crate::formatter::SyslogFormatter
+    /// [wants them]: crate::formatter::SyslogFormatter::wants_fields
+    fn fields(&self, event: &tracing::Event) -> Vec<(&'static str, String)> {
+        let _ = event;
+        Vec::new()
+    }
+    /// An event has occurred, & the [`SyslogFormatter`] in use [wants] its fields
+    ///
+    /// This returns the result of [`on_event`] along with the result of [`fields`]; the default
+    /// implementation just calls each in turn. Implementations that visit the event's fields in
+    /// order to format it should override this to collect them in the same pass.
+    ///
+    /// [`SyslogFormatter`]: crate::formatter::SyslogFormatter
+    /// [wants]: crate::formatter::SyslogFormatter::wants_fields
+    /// [`on_event`]: TracingFormatter::on_event
+    /// [`fields`]: TracingFormatter::fields
+    #[allow(clippy::type_complexity)]
+    fn on_event_with_fields(
+        &self,
+        event: &tracing::Event,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<(Option<(String, Level)>, Vec<(&'static str, String)>), Self::Error> {
+        let fields = self.fields(event);
+        self.on_event(event, ctx).map(|rsp| (rsp, fields))
+    }
     /// A span with the given ID was entered
     fn on_enter(
         &self,
@@ -115,7 +147,15 @@ where
 
 #[non_exhaustive]
 pub enum Error {
-    NoMessageField { name: &'static str, back: Backtrace },
+    NoMessageField {
+        name: &'static str,
+        back: Backtrace,
+    },
+    #[cfg(feature = "json")]
+    Json {
+        source: serde_json::Error,
+        back: Backtrace,
+    },
 }
 
 impl std::fmt::Display for Error {
@@ -128,6 +168,8 @@ impl std::fmt::Display for Error {
             Error::NoMessageField { name, .. } => {
                 write!(f, "No message field found in event {}", name)
             }
+            #[cfg(feature = "json")]
+            Error::Json { source, .. } => write!(f, "While rendering JSON: {}", source),
         }
     }
 }
@@ -137,50 +179,319 @@ impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::NoMessageField { name: _, back } => write!(f, "{:#?}\n{}", back, self),
+            #[cfg(feature = "json")]
+            Error::Json { source: _, back } => write!(f, "{:#?}\n{}", back, self),
         }
     }
 }
 
-impl std::error::Error for Error {}
-
-fn default_level_mapping(level: &tracing::Level) -> Level {
-    match level {
-        &tracing::Level::TRACE | &tracing::Level::DEBUG => Level::LOG_DEBUG,
-        &tracing::Level::INFO => Level::LOG_INFO,
-        &tracing::Level::WARN => Level::LOG_WARNING,
-        &tracing::Level::ERROR => Level::LOG_ERR,
+impl std::error::Error for Error {
+    #[allow(unreachable_patterns)]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self {
+            #[cfg(feature = "json")]
+            Error::Json { source, .. } => Some(source),
+            _ => None,
+        }
     }
 }
 
 /// A [`TracingFormatter`] that just returns an [`Event`]s "message" field, if present (fails
 /// otherwise). It doesn't respond to any other events.
 ///
+/// By default, any trailing carriage returns or newlines are stripped from the message (it's easy
+/// to wind up with one when building messages via `format!("{}\n", ...)` & the like, and with
+/// stream framing it produces a spurious empty record). This can be turned-off via
+/// [`with_trim_trailing_newline`](TrivialTracingFormatter::with_trim_trailing_newline).
+///
+/// Embedded newlines (from a message pretty-printed via `{:#?}`, say) are left alone by default,
+/// but line-oriented collectors will split such messages into several records. They may be
+/// replaced with a single character via
+/// [`with_newline_replacement`](TrivialTracingFormatter::with_newline_replacement).
+///
+/// Messages colorized with ANSI escape sequences (by an upstream library, say) can be cleaned-up
+/// via [`with_strip_ansi`](TrivialTracingFormatter::with_strip_ansi).
+///
+/// Fields holding errors (e.g. `error!(error = &err as &dyn Error, "operation failed")`) are,
+/// like all other fields, ignored by default. With
+/// [`with_error_chain`](TrivialTracingFormatter::with_error_chain), each such error, along with
+/// its chain of [`source`](std::error::Error::source)s, is appended to the message.
+///
+/// Events with no "message" field (e.g. `info!(count = 5)`) are, by default, an error. If
+/// [`fallback_to_name`](TrivialTracingFormatter::fallback_to_name) is set, they will instead be
+/// forwarded with the event's name as the message; failing that, if
+/// [`allow_empty_message`](TrivialTracingFormatter::allow_empty_message) is set, with an empty
+/// message.
+///
 /// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
 pub struct TrivialTracingFormatter {
     map_level: Box<dyn Fn(&tracing::Level) -> Level + Send + Sync>,
+    trim_trailing_newline: bool,
+    allow_empty_message: bool,
+    fallback_to_name: bool,
+    newline_replacement: Option<char>,
+    error_chain: bool,
+    strip_ansi: bool,
 }
 
 impl std::default::Default for TrivialTracingFormatter {
     fn default() -> Self {
         TrivialTracingFormatter {
-            map_level: Box::new(default_level_mapping),
+            map_level: Box::new(|level| Level::from(level)),
+            trim_trailing_newline: true,
+            allow_empty_message: false,
+            fallback_to_name: false,
+            newline_replacement: None,
+            error_chain: false,
+            strip_ansi: false,
         }
     }
 }
 
-struct MessageEventVisitor {
+impl TrivialTracingFormatter {
+    /// Strip (or not) trailing `\r` & `\n` characters from messages
+    pub fn with_trim_trailing_newline(mut self, trim_trailing_newline: bool) -> Self {
+        self.trim_trailing_newline = trim_trailing_newline;
+        self
+    }
+    /// Forward (or not) Events that have no "message" field with an empty message
+    pub fn allow_empty_message(mut self, allow_empty_message: bool) -> Self {
+        self.allow_empty_message = allow_empty_message;
+        self
+    }
+    /// Forward (or not) Events that have no "message" field with the event's name (from its
+    /// [`Metadata`]) as the message
+    ///
+    /// This takes precedence over [`allow_empty_message`](TrivialTracingFormatter::allow_empty_message).
+    ///
+    /// [`Metadata`]: tracing_core::Metadata
+    pub fn fallback_to_name(mut self, fallback_to_name: bool) -> Self {
+        self.fallback_to_name = fallback_to_name;
+        self
+    }
+    /// Replace each line break (`\n` or `\r\n`) remaining in a message (i.e. after any trailing
+    /// newline has been trimmed) with `replacement`, or (with `None`) leave them be
+    ///
+    /// This keeps one event to one record with line-oriented collectors. To replace line breaks with
+    /// a visible `\n` escape instead, see
+    /// [`Rfc5424Builder::with_escape_control_chars`](crate::rfc5424::Rfc5424Builder::with_escape_control_chars).
+    pub fn with_newline_replacement(mut self, replacement: Option<char>) -> Self {
+        self.newline_replacement = replacement;
+        self
+    }
+    /// Append (or not) errors carried by the Event's fields to the message, each followed by its
+    /// chain of sources: "operation failed: top-level error: its source: its source's source"
+    pub fn with_error_chain(mut self, error_chain: bool) -> Self {
+        self.error_chain = error_chain;
+        self
+    }
+    /// Remove (or not) ANSI CSI escape sequences (colors & the like, e.g. `\x1b[31m`) from
+    /// messages
+    pub fn with_strip_ansi(mut self, strip_ansi: bool) -> Self {
+        self.strip_ansi = strip_ansi;
+        self
+    }
+}
+
+/// Remove ANSI CSI sequences (ESC `[`, any parameter & intermediate bytes, and a final byte) from
+/// `s`
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            // Parameter bytes are 0x30-0x3f & intermediate bytes 0x20-0x2f; the sequence ends with
+            // the first byte in 0x40-0x7e (or, if it's malformed, the first that's none of these).
+            for c in chars.by_ref() {
+                if !('\x20'..='\x3f').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A [`Visit`] implementation that extracts an [`Event`]'s "message" field, along with string
+/// representations of all its other fields
+///
+/// Typed values are captured faithfully: strings are recorded verbatim (rather than via their
+/// [`Debug`] implementation, which would quote them), as are values recorded with `%` (i.e. via
+/// their [`Display`] implementation). With the `valuable` feature (& `--cfg tracing_unstable`),
+/// fields recorded as [`valuable`] values are rendered structurally, as `{name=value ...}` & the
+/// like, rather than via [`Debug`]. This is available to implementors of [`TracingFormatter`]
+/// who want to make use of an [`Event`]'s fields.
+///
+/// [`Visit`]: tracing::field::Visit
+/// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
+/// [`Debug`]: std::fmt::Debug
+/// [`Display`]: std::fmt::Display
+/// [`valuable`]: https://docs.rs/valuable
+#[derive(Debug, Default)]
+pub struct MessageEventVisitor {
     message: Option<String>,
+    fields: Vec<(&'static str, String)>,
+    errors: Vec<(&'static str, Vec<String>)>,
+}
+
+impl MessageEventVisitor {
+    pub fn new() -> MessageEventVisitor {
+        MessageEventVisitor::default()
+    }
+    /// The "message" field, if any
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+    /// All fields other than "message" (& the reserved `syslog.*` fields), as (name, value) pairs
+    /// in the order in which they were recorded
+    pub fn fields(&self) -> &[(&'static str, String)] {
+        &self.fields
+    }
+    /// All fields recorded as errors, as (name, chain) pairs; each chain begins with the error
+    /// itself & continues through its [`source`](std::error::Error::source)s
+    pub fn errors(&self) -> &[(&'static str, Vec<String>)] {
+        &self.errors
+    }
+    fn record(&mut self, field: &tracing::field::Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else if !field.name().starts_with("syslog.") {
+            // Fields in the "syslog." namespace are reserved for directing the [`Layer`]
+            self.fields.push((field.name(), value));
+        }
+    }
+}
+
+/// Render a [`valuable::Value`] compactly
+///
+/// Primitives are rendered as they would be had they been recorded directly (strings verbatim),
+/// while composites are rendered as space-separated lists of their members: `{name=value ...}` for
+/// structs with named fields & maps, `(...)` for tuples & tuple structs, and `[...]` for lists.
+/// Enum variants are prefixed by their names.
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+fn render_valuable(value: valuable::Value<'_>) -> String {
+    use valuable::Value;
+
+    /// Collects the rendered members of a composite value
+    #[derive(Default)]
+    struct Members {
+        members: Vec<String>,
+        named: bool,
+    }
+
+    impl Members {
+        fn collect(value: &dyn valuable::Valuable) -> Members {
+            let mut members = Members::default();
+            value.visit(&mut members);
+            members
+        }
+        fn join(&self, open: char, close: char) -> String {
+            format!("{}{}{}", open, self.members.join(" "), close)
+        }
+        fn join_fields(&self) -> String {
+            if self.named {
+                self.join('{', '}')
+            } else {
+                self.join('(', ')')
+            }
+        }
+    }
+
+    impl valuable::Visit for Members {
+        fn visit_value(&mut self, value: Value<'_>) {
+            self.members.push(render_valuable(value));
+        }
+        fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+            self.named = true;
+            for (field, value) in named_values {
+                self.members
+                    .push(format!("{}={}", field.name(), render_valuable(*value)));
+            }
+        }
+        fn visit_unnamed_fields(&mut self, values: &[Value<'_>]) {
+            for value in values {
+                self.visit_value(*value);
+            }
+        }
+        fn visit_entry(&mut self, key: Value<'_>, value: Value<'_>) {
+            self.named = true;
+            self.members.push(format!(
+                "{}={}",
+                render_valuable(key),
+                render_valuable(value)
+            ));
+        }
+    }
+
+    match value {
+        Value::String(s) => s.to_string(),
+        Value::Path(path) => path.display().to_string(),
+        Value::Error(err) => err.to_string(),
+        Value::Unit => "()".to_string(),
+        Value::Structable(s) => Members::collect(&s).join_fields(),
+        Value::Enumerable(e) => {
+            let members = Members::collect(&e);
+            if members.members.is_empty() {
+                e.variant().name().to_string()
+            } else {
+                format!("{}{}", e.variant().name(), members.join_fields())
+            }
+        }
+        Value::Listable(l) => Members::collect(&l).join('[', ']'),
+        Value::Tuplable(t) => Members::collect(&t).join('(', ')'),
+        Value::Mappable(m) => Members::collect(&m).join('{', '}'),
+        // Numbers, booleans & characters
+        value => format!("{:?}", value),
+    }
 }
 
 impl tracing::field::Visit for MessageEventVisitor {
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.record(field, value.to_string())
+    }
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.record(field, value.to_string())
+    }
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.record(field, value.to_string())
+    }
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.record(field, value.to_string())
+    }
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.record(field, value.to_string())
+    }
     fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
-        if field.name() == "message" {
-            // Regrettably, we have only a `Debug` implementation available to us; but the tracing
-            // macros `info!()`, `event!()` & the like all take care to "pre-format" the `mesage`
-            // field so that `value` actually refers to a `std::fmt::Arguments` instance, which will
-            // print to a debug format without enclosing double-quotes.
-            self.message = Some(format!("{:?}", value));
+        // Regrettably, we have only a `Debug` implementation available to us; but the tracing
+        // macros `info!()`, `event!()` & the like all take care to "pre-format" the `mesage`
+        // field so that `value` actually refers to a `std::fmt::Arguments` instance, which will
+        // print to a debug format without enclosing double-quotes. Likewise, fields recorded with
+        // `%` are wrapped in a type whose `Debug` implementation defers to `Display`.
+        self.record(field, format!("{:?}", value))
+    }
+    fn record_error(
+        &mut self,
+        field: &tracing::field::Field,
+        value: &(dyn std::error::Error + 'static),
+    ) {
+        self.record(field, value.to_string());
+        if field.name().starts_with("syslog.") {
+            return;
         }
+        let mut chain = vec![value.to_string()];
+        let mut source = value.source();
+        while let Some(err) = source {
+            chain.push(err.to_string());
+            source = err.source();
+        }
+        self.errors.push((field.name(), chain));
+    }
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    fn record_value(&mut self, field: &tracing::field::Field, value: valuable::Value<'_>) {
+        self.record(field, render_valuable(value))
     }
 }
 
@@ -194,14 +505,661 @@ where
         event: &tracing::Event,
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) -> StdResult<Option<(String, Level)>, Error> {
-        let mut visitor = MessageEventVisitor { message: None };
+        let mut visitor = MessageEventVisitor::new();
         event.record(&mut visitor);
+        self.format_visited(event, &mut visitor)
+    }
+    fn fields(&self, event: &tracing::Event) -> Vec<(&'static str, String)> {
+        let mut visitor = MessageEventVisitor::new();
+        event.record(&mut visitor);
+        visitor.fields
+    }
+    fn on_event_with_fields(
+        &self,
+        event: &tracing::Event,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<(Option<(String, Level)>, Vec<(&'static str, String)>), Error> {
+        let mut visitor = MessageEventVisitor::new();
+        event.record(&mut visitor);
+        let rsp = self.format_visited(event, &mut visitor)?;
+        Ok((rsp, visitor.fields))
+    }
+}
+
+impl TrivialTracingFormatter {
+    /// Format `event`, whose fields have already been recorded to `visitor` (taking its message)
+    fn format_visited(
+        &self,
+        event: &tracing::Event,
+        visitor: &mut MessageEventVisitor,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        if visitor.message.is_none() {
+            if self.fallback_to_name {
+                visitor.message = Some(event.metadata().name().to_string());
+            } else if self.allow_empty_message {
+                visitor.message = Some(String::new());
+            }
+        }
         visitor
             .message
-            .ok_or(Error::NoMessageField {
+            .take()
+            // Lazily: capturing a backtrace on every event is *expensive*
+            .ok_or_else(|| Error::NoMessageField {
                 name: event.metadata().name(),
                 back: Backtrace::new(),
             })
-            .map(|s| Some((s, (*self.map_level)(event.metadata().level()))))
+            .map(|mut s| {
+                if self.strip_ansi && s.contains('\x1b') {
+                    s = strip_ansi(&s);
+                }
+                if self.trim_trailing_newline {
+                    s.truncate(s.trim_end_matches(['\r', '\n']).len());
+                }
+                if self.error_chain {
+                    for (_, chain) in &visitor.errors {
+                        for err in chain {
+                            s.push_str(": ");
+                            s.push_str(err);
+                        }
+                    }
+                }
+                if let Some(replacement) = self.newline_replacement.filter(|_| s.contains('\n')) {
+                    let mut buf = [0u8; 4];
+                    let replacement: &str = replacement.encode_utf8(&mut buf);
+                    s = s.replace("\r\n", replacement).replace('\n', replacement);
+                }
+                Some((s, (*self.map_level)(event.metadata().level())))
+            })
+    }
+}
+
+/// A [`TracingFormatter`] that, in addition to handling [`Event`]s just as
+/// [`TrivialTracingFormatter`] does, produces a message at `LOG_INFO` each time a [`Span`] is
+/// entered ("→ span_name") or exited ("← span_name").
+///
+/// This is handy for request tracing, but can be voluminous, so it must be asked for explicitly.
+///
+/// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
+/// [`Span`]: https://docs.rs/tracing/0.1.35/tracing/struct.Span.html
+#[derive(Default)]
+pub struct SpanLifecycleFormatter {
+    events: TrivialTracingFormatter,
+}
+
+impl SpanLifecycleFormatter {
+    fn span_message<S>(
+        prefix: &str,
+        id: &tracing_core::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> Option<(String, Level)>
+    where
+        S: tracing_core::subscriber::Subscriber
+            + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        ctx.span(id)
+            .map(|span| (format!("{} {}", prefix, span.name()), Level::LOG_INFO))
+    }
+}
+
+impl<S> TracingFormatter<S> for SpanLifecycleFormatter
+where
+    S: tracing_core::subscriber::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    type Error = Error;
+    fn on_event(
+        &self,
+        event: &tracing::Event,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        self.events.on_event(event, ctx)
+    }
+    fn fields(&self, event: &tracing::Event) -> Vec<(&'static str, String)> {
+        TracingFormatter::<S>::fields(&self.events, event)
+    }
+    fn on_event_with_fields(
+        &self,
+        event: &tracing::Event,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<(Option<(String, Level)>, Vec<(&'static str, String)>), Error> {
+        self.events.on_event_with_fields(event, ctx)
+    }
+    fn on_enter(
+        &self,
+        id: &tracing_core::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        Ok(SpanLifecycleFormatter::span_message("→", id, ctx))
+    }
+    fn on_exit(
+        &self,
+        id: &tracing_core::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        Ok(SpanLifecycleFormatter::span_message("←", id, ctx))
+    }
+}
+
+/// A [`TracingFormatter`] that renders each [`Event`] in [logfmt]: the message under the key
+/// `msg`, followed by each of the Event's fields, e.g.
+///
+/// ```text
+/// msg="order placed" user=frodo total=9.99
+/// ```
+///
+/// Values that are empty, or that contain spaces, `=`, `"` or control characters, are quoted,
+/// with `"` & `\` escaped by a backslash & control characters rendered as Rust escape sequences
+/// (`\n` & so on). Characters that can't appear in a key are replaced with `_`. As with
+/// [`TrivialTracingFormatter`], any trailing newlines are trimmed from the message, and the
+/// reserved `syslog.*` fields are omitted. Events with no "message" field are rendered with
+/// their fields alone.
+///
+/// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
+/// [logfmt]: https://brandur.org/logfmt
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogfmtTracingFormatter;
+
+impl LogfmtTracingFormatter {
+    fn push_key(buf: &mut String, key: &str) {
+        buf.extend(key.chars().map(|c| {
+            if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        }));
+    }
+    fn push_value(buf: &mut String, value: &str) {
+        let quote = value.is_empty()
+            || value
+                .chars()
+                .any(|c| c == '=' || c == '"' || c.is_whitespace() || c.is_control());
+        if !quote {
+            buf.push_str(value);
+            return;
+        }
+        buf.push('"');
+        for c in value.chars() {
+            match c {
+                '"' | '\\' => {
+                    buf.push('\\');
+                    buf.push(c);
+                }
+                c if c.is_control() => buf.extend(c.escape_default()),
+                c => buf.push(c),
+            }
+        }
+        buf.push('"');
+    }
+}
+
+impl<S> TracingFormatter<S> for LogfmtTracingFormatter
+where
+    S: tracing_core::subscriber::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    type Error = Error;
+    fn on_event(
+        &self,
+        event: &tracing::Event,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        let mut visitor = MessageEventVisitor::new();
+        event.record(&mut visitor);
+        Ok(Some(LogfmtTracingFormatter::format_visited(
+            event, &visitor,
+        )))
+    }
+    fn fields(&self, event: &tracing::Event) -> Vec<(&'static str, String)> {
+        let mut visitor = MessageEventVisitor::new();
+        event.record(&mut visitor);
+        visitor.fields
+    }
+    fn on_event_with_fields(
+        &self,
+        event: &tracing::Event,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<(Option<(String, Level)>, Vec<(&'static str, String)>), Error> {
+        let mut visitor = MessageEventVisitor::new();
+        event.record(&mut visitor);
+        let rsp = LogfmtTracingFormatter::format_visited(event, &visitor);
+        Ok((Some(rsp), visitor.fields))
+    }
+}
+
+impl LogfmtTracingFormatter {
+    /// Format `event`, whose fields have already been recorded to `visitor`
+    fn format_visited(event: &tracing::Event, visitor: &MessageEventVisitor) -> (String, Level) {
+        let mut buf = String::new();
+        if let Some(message) = &visitor.message {
+            buf.push_str("msg=");
+            LogfmtTracingFormatter::push_value(&mut buf, message.trim_end_matches(['\r', '\n']));
+        }
+        for (key, value) in &visitor.fields {
+            if !buf.is_empty() {
+                buf.push(' ');
+            }
+            LogfmtTracingFormatter::push_key(&mut buf, key);
+            buf.push('=');
+            LogfmtTracingFormatter::push_value(&mut buf, value);
+        }
+        (buf, Level::from(event.metadata().level()))
+    }
+}
+
+/// A [`TracingFormatter`] that renders each [`Event`] as a JSON object, for pipelines that parse
+/// the syslog MSG as such
+///
+/// The object carries the Event's level & target, along with its fields (the message included),
+/// in the style of `tracing-subscriber`'s JSON format:
+///
+/// ```text
+/// {"fields":{"count":3,"message":"order placed","ok":true},"level":"INFO","target":"app"}
+/// ```
+///
+/// Fields recorded as integers, floats & booleans are rendered as JSON numbers & booleans; all
+/// others as strings. Non-finite floats, which JSON can't represent, become `null`. Should a name
+/// be repeated, the last value wins. The reserved `syslog.*` fields are omitted. Object members
+/// appear in order of their names.
+///
+/// This requires the `json` feature.
+///
+/// [`Event`]: https://docs.rs/tracing/0.1.35/tracing/struct.Event.html
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonTracingFormatter;
+
+/// A [`Visit`](tracing::field::Visit) implementation that collects each field as a JSON value
+#[cfg(feature = "json")]
+#[derive(Default)]
+struct JsonVisitor {
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "json")]
+impl JsonVisitor {
+    fn record<V: Into<serde_json::Value>>(&mut self, field: &tracing::field::Field, value: V) {
+        if !field.name().starts_with("syslog.") {
+            self.fields.insert(field.name().to_string(), value.into());
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl tracing::field::Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        // `From<f64>` maps non-finite values to `null`
+        self.record(field, value)
+    }
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.record(field, value)
+    }
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.record(field, value)
+    }
+    fn record_i128(&mut self, field: &tracing::field::Field, value: i128) {
+        // Without serde_json's `arbitrary_precision`, these are only representable in 64 bits
+        match i64::try_from(value) {
+            Ok(value) => self.record(field, value),
+            Err(_) => self.record(field, value.to_string()),
+        }
+    }
+    fn record_u128(&mut self, field: &tracing::field::Field, value: u128) {
+        match u64::try_from(value) {
+            Ok(value) => self.record(field, value),
+            Err(_) => self.record(field, value.to_string()),
+        }
+    }
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.record(field, value)
+    }
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.record(field, value)
+    }
+    fn record_error(
+        &mut self,
+        field: &tracing::field::Field,
+        value: &(dyn std::error::Error + 'static),
+    ) {
+        self.record(field, value.to_string())
+    }
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        // As with `MessageEventVisitor`, the message & fields recorded with `%` arrive here
+        // wrapped such that their `Debug` implementation defers to `Display`.
+        self.record(field, format!("{:?}", value))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<S> TracingFormatter<S> for JsonTracingFormatter
+where
+    S: tracing_core::subscriber::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    type Error = Error;
+    fn on_event(
+        &self,
+        event: &tracing::Event,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) -> StdResult<Option<(String, Level)>, Error> {
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let meta = event.metadata();
+        let mut object = serde_json::Map::new();
+        object.insert("level".to_string(), meta.level().to_string().into());
+        object.insert("target".to_string(), meta.target().into());
+        object.insert("fields".to_string(), visitor.fields.into());
+        let buf = serde_json::to_string(&object).map_err(|source| Error::Json {
+            source,
+            back: Backtrace::new(),
+        })?;
+        Ok(Some((buf, Level::from(meta.level()))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::{layer::Layer, rfc5424::Rfc5424, transport::VecTransport};
+
+    use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+
+    /// Run `f` under a subscriber that formats Events via `formatter`; return the resulting
+    /// syslog messages
+    fn capture<TF, F>(formatter: TF, f: F) -> Vec<String>
+    where
+        TF: TracingFormatter<Registry> + Send + Sync + 'static,
+        F: FnOnce(),
+    {
+        let transport = VecTransport::new();
+        let subscriber = Registry::default().with(Layer::new(
+            Rfc5424::builder()
+                .hostname_as_string("bree.local".to_string())
+                .unwrap()
+                .appname_as_string("prototyping".to_string())
+                .unwrap()
+                .pid_as_string("123".to_string())
+                .unwrap()
+                .build(),
+            formatter,
+            transport.clone(),
+        ));
+        tracing::subscriber::with_default(subscriber, f);
+        transport
+            .messages()
+            .into_iter()
+            .map(|m| String::from_utf8(m).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn trim_trailing_newline() {
+        let msgs = capture(TrivialTracingFormatter::default(), || {
+            tracing::info!("Hello, world!\n");
+            tracing::info!("Hello, world!\r\n");
+            tracing::info!("Hello,\nworld!");
+        });
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].ends_with(" - - Hello, world!"));
+        assert!(msgs[1].ends_with(" - - Hello, world!"));
+        assert!(msgs[2].ends_with(" - - Hello,\nworld!"));
+
+        let msgs = capture(
+            TrivialTracingFormatter::default().with_trim_trailing_newline(false),
+            || {
+                tracing::info!("Hello, world!\n");
+            },
+        );
+        assert!(msgs[0].ends_with(" - - Hello, world!\n"));
+    }
+
+    #[test]
+    fn logfmt() {
+        let msgs = capture(LogfmtTracingFormatter, || {
+            tracing::info!(user = "frodo", path = "/the shire", "order placed");
+            tracing::info!(quote = "say \"hi\"", syslog.appname = "bree", "done\n");
+            tracing::info!(count = 5);
+        });
+        assert!(msgs[0].ends_with(r#" - - msg="order placed" user=frodo path="/the shire""#));
+        assert!(msgs[1].ends_with(r#" - - msg=done quote="say \"hi\"""#));
+        assert!(msgs[2].ends_with(" - - count=5"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let msgs = capture(JsonTracingFormatter, || {
+            tracing::info!(
+                target: "app",
+                count = 3,
+                ratio = 0.5,
+                ok = true,
+                name = "say \"hi\"\n",
+                nan = f64::NAN,
+                syslog.appname = "bree",
+                "order placed"
+            );
+        });
+        let json: serde_json::Value =
+            serde_json::from_str(msgs[0].split_once(" - - ").unwrap().1).unwrap();
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["target"], "app");
+        let fields = json["fields"].as_object().unwrap();
+        assert_eq!(fields.len(), 6);
+        assert_eq!(fields["message"].as_str(), Some("order placed"));
+        assert_eq!(fields["count"].as_i64(), Some(3));
+        assert_eq!(fields["ratio"].as_f64(), Some(0.5));
+        assert_eq!(fields["ok"].as_bool(), Some(true));
+        assert_eq!(fields["name"].as_str(), Some("say \"hi\"\n"));
+        assert!(fields["nan"].is_null());
+    }
+
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    #[test]
+    fn valuable() {
+        use valuable::{Fields, NamedField, NamedValues, StructDef, Structable, Valuable, Value};
+
+        struct Order {
+            id: u64,
+            items: Vec<String>,
+            rush: bool,
+        }
+
+        static ORDER_FIELDS: &[NamedField<'static>] = &[
+            NamedField::new("id"),
+            NamedField::new("items"),
+            NamedField::new("rush"),
+        ];
+
+        impl Valuable for Order {
+            fn as_value(&self) -> Value<'_> {
+                Value::Structable(self)
+            }
+            fn visit(&self, visit: &mut dyn valuable::Visit) {
+                visit.visit_named_fields(&NamedValues::new(
+                    ORDER_FIELDS,
+                    &[
+                        self.id.as_value(),
+                        self.items.as_value(),
+                        self.rush.as_value(),
+                    ],
+                ));
+            }
+        }
+
+        impl Structable for Order {
+            fn definition(&self) -> StructDef<'_> {
+                StructDef::new_static("Order", Fields::Named(ORDER_FIELDS))
+            }
+        }
+
+        let order = Order {
+            id: 1138,
+            items: vec!["ring".to_string(), "sting".to_string()],
+            rush: true,
+        };
+        let msgs = capture(LogfmtTracingFormatter, || {
+            tracing::info!(
+                order = order.as_value(),
+                pair = (1u8, "two").as_value(),
+                user = "frodo".as_value(),
+                "order placed"
+            );
+        });
+        assert!(msgs[0].ends_with(concat!(
+            r#" - - msg="order placed" order="{id=1138 items=[ring sting] rush=true}" "#,
+            r#"pair="(1 two)" user=frodo"#
+        )));
+    }
+
+    #[test]
+    fn strip_ansi() {
+        let msgs = capture(
+            TrivialTracingFormatter::default().with_strip_ansi(true),
+            || {
+                tracing::info!("\x1b[31mred\x1b[0m");
+                tracing::info!("\x1b[1;38;5;208mbold orange\x1b[m & plain");
+            },
+        );
+        assert!(msgs[0].ends_with(" - - red"));
+        assert!(msgs[1].ends_with(" - - bold orange & plain"));
+
+        // Off by default
+        let msgs = capture(TrivialTracingFormatter::default(), || {
+            tracing::info!("\x1b[31mred\x1b[0m");
+        });
+        assert!(msgs[0].ends_with(" - - \x1b[31mred\x1b[0m"));
+    }
+
+    #[test]
+    fn newline_replacement() {
+        let msgs = capture(
+            TrivialTracingFormatter::default().with_newline_replacement(Some(' ')),
+            || {
+                tracing::info!("Error {{\n    code: 5,\r\n}}\n");
+            },
+        );
+        assert!(msgs[0].ends_with(" - - Error {     code: 5, }"));
+
+        // The default is to leave them alone
+        let msgs = capture(TrivialTracingFormatter::default(), || {
+            tracing::info!("Hello,\nworld!");
+        });
+        assert!(msgs[0].ends_with(" - - Hello,\nworld!"));
+    }
+
+    #[test]
+    fn error_chain() {
+        #[derive(Debug)]
+        struct Layered(&'static str, Option<Box<Layered>>);
+        impl std::fmt::Display for Layered {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+        impl std::error::Error for Layered {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                self.1
+                    .as_deref()
+                    .map(|err| err as &(dyn std::error::Error + 'static))
+            }
+        }
+        let err = Layered(
+            "couldn't read config",
+            Some(Box::new(Layered(
+                "couldn't open file",
+                Some(Box::new(Layered("permission denied", None))),
+            ))),
+        );
+
+        let msgs = capture(
+            TrivialTracingFormatter::default().with_error_chain(true),
+            || {
+                tracing::error!(error = &err as &dyn std::error::Error, "operation failed");
+            },
+        );
+        assert!(msgs[0].ends_with(
+            " - - operation failed: couldn't read config: couldn't open file: permission denied"
+        ));
+
+        // The default is to leave them out
+        let msgs = capture(TrivialTracingFormatter::default(), || {
+            tracing::error!(error = &err as &dyn std::error::Error, "operation failed");
+        });
+        assert!(msgs[0].ends_with(" - - operation failed"));
+    }
+
+    #[test]
+    fn empty_message() {
+        let msgs = capture(
+            TrivialTracingFormatter::default().allow_empty_message(true),
+            || {
+                tracing::info!(count = 5);
+            },
+        );
+        assert_eq!(msgs.len(), 1);
+        let msg = syslog_rfc5424::parse_message(&msgs[0]).unwrap();
+        assert_eq!(msg.hostname.as_deref(), Some("bree.local"));
+        assert_eq!(msg.msg, "");
+        assert!(msgs[0].ends_with(" bree.local prototyping 123 - -"));
+    }
+
+    #[test]
+    fn fallback_to_name() {
+        let msgs = capture(
+            TrivialTracingFormatter::default()
+                .fallback_to_name(true)
+                .allow_empty_message(true),
+            || {
+                tracing::event!(name: "cache-miss", tracing::Level::INFO, count = 5);
+            },
+        );
+        assert_eq!(msgs.len(), 1);
+        assert!(msgs[0].ends_with(" bree.local prototyping 123 - - cache-miss"));
+
+        // Still an error by default
+        let msgs = capture(TrivialTracingFormatter::default(), || {
+            tracing::event!(name: "cache-miss", tracing::Level::INFO, count = 5);
+        });
+        assert!(msgs.is_empty());
+    }
+
+    #[test]
+    fn visitor() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<MessageEventVisitor>>>);
+        impl<S: tracing::Subscriber> tracing_subscriber::layer::Layer<S> for Capture {
+            fn on_event(
+                &self,
+                event: &tracing::Event<'_>,
+                _ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                let mut visitor = MessageEventVisitor::new();
+                event.record(&mut visitor);
+                self.0.lock().unwrap().push(visitor);
+            }
+        }
+
+        let capture = Capture::default();
+        let subscriber = Registry::default().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let err = "x".parse::<i32>().unwrap_err();
+            tracing::error!(err = %err, count = 5, neg = -3, ok = false, name = "bree", "failed");
+        });
+
+        let visitors = capture.0.lock().unwrap();
+        assert_eq!(visitors.len(), 1);
+        assert_eq!(visitors[0].message(), Some("failed"));
+        assert_eq!(
+            visitors[0].fields(),
+            &[
+                ("err", "invalid digit found in string".to_string()),
+                ("count", "5".to_string()),
+                ("neg", "-3".to_string()),
+                ("ok", "false".to_string()),
+                ("name", "bree".to_string()),
+            ]
+        );
     }
 }
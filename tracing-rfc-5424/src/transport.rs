@@ -19,7 +19,11 @@
 //! # Introduction
 //!
 //! This module defines the [`Transport`] trait that all implementations must support, as well
-//! as the UDP, TCP & Unix socket (datagram as well as stream) implementations.
+//! as the UDP, TCP & Unix socket (datagram as well as stream) implementations. It also provides
+//! [`VecTransport`], which merely collects messages in memory & is handy for testing,
+//! [`RateLimitedTransport`], which wraps another [`Transport`] to protect the daemon from floods,
+//! and [`LazyTransport`], which defers connecting to the daemon until it's needed.
+//! With the `relp` feature, `RelpTransport` provides acknowledged delivery via RELP.
 //!
 //! # Examples
 //!
@@ -72,10 +76,21 @@ use crate::formatter::SyslogFormatter;
 
 use backtrace::Backtrace;
 
-use std::{net::TcpStream, path::Path};
+use std::{
+    net::TcpStream,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 #[cfg(unix)]
-use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::os::unix::{
+    io::{FromRawFd, RawFd},
+    net::{UnixDatagram, UnixStream},
+};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                       common error type                                        //
@@ -84,11 +99,50 @@ use std::os::unix::net::{UnixDatagram, UnixStream};
 /// syslog transport layer errors
 #[non_exhaustive]
 pub enum Error {
+    /// Failed to establish a connection to the syslog daemon at `addr`
+    Connect {
+        addr: String,
+        source: std::io::Error,
+        back: Backtrace,
+    },
     /// I/O error
     Io {
         source: std::io::Error,
         back: Backtrace,
     },
+    /// Failed to bind our local socket to `addr` (before we got as far as the syslog daemon)
+    Bind {
+        addr: String,
+        source: std::io::Error,
+        back: Backtrace,
+    },
+    /// A message of `len` bytes was not sent, since it exceeds the datagram size limit of `max`
+    MessageTooLarge {
+        len: usize,
+        max: usize,
+        back: Backtrace,
+    },
+    /// The peer violated the wire protocol in use (RELP, e.g.)
+    Protocol { reason: String, back: Backtrace },
+    /// The peer declined to accept a message, replying with `status`
+    Rejected { status: String, back: Backtrace },
+}
+
+impl Error {
+    fn connect<A: std::fmt::Display>(addr: A) -> impl FnOnce(std::io::Error) -> Error {
+        move |err| Error::Connect {
+            addr: addr.to_string(),
+            source: err,
+            back: Backtrace::new(),
+        }
+    }
+    fn bind<A: std::fmt::Display>(addr: A) -> impl FnOnce(std::io::Error) -> Error {
+        move |err| Error::Bind {
+            addr: addr.to_string(),
+            source: err,
+            back: Backtrace::new(),
+        }
+    }
 }
 
 impl std::convert::From<std::io::Error> for Error {
@@ -107,7 +161,26 @@ impl std::fmt::Display for Error {
     #[allow(unreachable_patterns)]
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            Error::Connect { addr, source, .. } => {
+                write!(f, "While connecting to {}, got {}", addr, source)
+            }
             Error::Io { source, .. } => write!(f, "I/O error: {}", source),
+            Error::Bind { addr, source, .. } => {
+                write!(
+                    f,
+                    "While binding the local socket to {}, got {}",
+                    addr, source
+                )
+            }
+            Error::MessageTooLarge { len, max, .. } => write!(
+                f,
+                "A message of {} bytes exceeds the maximum datagram size of {}",
+                len, max
+            ),
+            Error::Protocol { reason, .. } => write!(f, "Protocol error: {}", reason),
+            Error::Rejected { status, .. } => {
+                write!(f, "The peer rejected the message: {}", status)
+            }
             _ => write!(f, "syslog transport layer error"),
         }
     }
@@ -117,7 +190,12 @@ impl std::fmt::Debug for Error {
     #[allow(unreachable_patterns)]
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            Error::Connect { back, .. } => write!(f, "{}\n{:#?}", self, back),
             Error::Io { source: _, back } => write!(f, "{}\n{:#?}", self, back),
+            Error::Bind { back, .. } => write!(f, "{}\n{:#?}", self, back),
+            Error::MessageTooLarge { back, .. } => write!(f, "{}\n{:#?}", self, back),
+            Error::Protocol { back, .. } => write!(f, "{}\n{:#?}", self, back),
+            Error::Rejected { back, .. } => write!(f, "{}\n{:#?}", self, back),
             _ => write!(f, "{}", self),
         }
     }
@@ -133,34 +211,501 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 /// Operations all transport layers must support.
 pub trait Transport<F: SyslogFormatter> {
-    type Error: std::error::Error;
+    type Error: std::error::Error + Send + Sync + 'static;
     /// Send a slice of byte on this transport mechanism.
     fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error>;
+    /// Send a message held in a borrowed buffer, if this transport mechanism supports that
+    ///
+    /// This permits callers (the [`Layer`](crate::layer::Layer), notably) to format messages into a
+    /// re-usable buffer rather than allocating a fresh [`SyslogFormatter::Output`] for each one.
+    /// Implementations return `None` if they can't send from a slice, in which case the caller
+    /// should fall back to [`send`](Transport::send); that's the default.
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        let _ = buf;
+        None
+    }
+    /// Flush any messages this transport mechanism may have buffered.
+    ///
+    /// Short-lived processes may exit before the last few messages reach the syslog daemon; this
+    /// gives them a way to make sure everything they've sent has actually been handed-off. The
+    /// default implementation does nothing, which is appropriate for transports that don't buffer
+    /// (datagram sockets, e.g.).
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Boxed transports are transports, too; this permits choosing a transport at runtime (from
+/// configuration, say) & handing a `Box<dyn Transport<F, Error = Error> + Send + Sync>` to a
+/// [`Layer`](crate::layer::Layer).
+impl<F, T> Transport<F> for Box<T>
+where
+    F: SyslogFormatter,
+    T: Transport<F> + ?Sized,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        (**self).send(buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        (**self).send_slice(buf)
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+
+/// ... as are shared transports.
+impl<F, T> Transport<F> for std::sync::Arc<T>
+where
+    F: SyslogFormatter,
+    T: Transport<F> + ?Sized,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        (**self).send(buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        (**self).send_slice(buf)
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+
+/// Resolve `addr` up-front, so that we can report the destination should we fail to connect
+fn resolve<A: std::net::ToSocketAddrs>(addr: A) -> Result<Vec<std::net::SocketAddr>> {
+    let addrs: Vec<std::net::SocketAddr> = addr.to_socket_addrs()?.collect();
+    if addrs.is_empty() {
+        return Err(Error::Io {
+            source: std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            ),
+            back: Backtrace::new(),
+        });
+    }
+    Ok(addrs)
+}
+
+fn describe(addrs: &[std::net::SocketAddr]) -> String {
+    addrs
+        .iter()
+        .map(|addr| addr.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                         UDP Transport                                          //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// The largest payload a UDP datagram can carry over IPv4 (65,535 bytes less the IP & UDP headers)
+pub const MAX_UDP_PAYLOAD: usize = 65_507;
+
 /// Sending syslog messages via UDP datagrams.
+#[derive(Debug)]
 pub struct UdpTransport {
     socket: std::net::UdpSocket,
+    // Messages longer than this are rejected without being sent
+    max_datagram_size: usize,
+    // Empty if `socket` is connected to a single destination
+    destinations: Vec<std::net::SocketAddr>,
+    // Running count of bytes the kernel has accepted; `None` unless asked for
+    bytes_sent: Option<std::sync::atomic::AtomicUsize>,
 }
 
 impl UdpTransport {
     /// Construct a [`Transport`] implementation via UDP at `addr`.
     pub fn new<A: std::net::ToSocketAddrs>(addr: A) -> Result<UdpTransport> {
+        let addrs = resolve(addr)?;
+        let dest = describe(&addrs);
         // Bind to any available port on localhost...
-        let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").map_err(Error::bind("0.0.0.0:0"))?;
         // and connect to the syslog daemon at `addr`...
-        socket.connect(addr)?;
+        socket.connect(&addrs[..]).map_err(Error::connect(&dest))?;
         // and we're done!
-        Ok(UdpTransport { socket })
+        Ok(UdpTransport {
+            socket,
+            max_datagram_size: MAX_UDP_PAYLOAD,
+            destinations: Vec::new(),
+            bytes_sent: None,
+        })
+    }
+    /// Adopt a socket created & configured elsewhere (inherited via socket activation, say)
+    ///
+    /// `socket` must already be connected to the syslog daemon.
+    pub fn from_socket(socket: std::net::UdpSocket) -> UdpTransport {
+        UdpTransport {
+            socket,
+            max_datagram_size: MAX_UDP_PAYLOAD,
+            destinations: Vec::new(),
+            bytes_sent: None,
+        }
+    }
+    /// Construct a [`Transport`] implementation that sends each message to every one of `addrs`
+    ///
+    /// Rather than opening one socket per destination, this binds a single (unconnected) socket &
+    /// sends each message to each destination in turn, in the order given. All destinations must be
+    /// of the same address family (IPv4 or IPv6). If sending to one of them fails, we still attempt
+    /// the rest, then return the first error.
+    pub fn new_multi<A: std::net::ToSocketAddrs>(addrs: &[A]) -> Result<UdpTransport> {
+        let mut destinations = Vec::new();
+        for addr in addrs {
+            // Just take the first address to which each resolves; we don't want to send the same
+            // message to both the IPv4 & IPv6 addresses of "localhost", say.
+            destinations.push(resolve(addr)?[0]);
+        }
+        if destinations.is_empty() {
+            return Err(Error::Io {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "no destinations given",
+                ),
+                back: Backtrace::new(),
+            });
+        }
+        let local: std::net::SocketAddr = match destinations.first() {
+            Some(std::net::SocketAddr::V6(_)) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+            _ => (std::net::Ipv4Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = std::net::UdpSocket::bind(local).map_err(Error::bind(local))?;
+        Ok(UdpTransport {
+            socket,
+            max_datagram_size: MAX_UDP_PAYLOAD,
+            destinations,
+            bytes_sent: None,
+        })
     }
     /// Construct a [`Transport`] implementation via UDP at localhost:514
     pub fn local() -> Result<UdpTransport> {
         UdpTransport::new("localhost:514")
     }
+    /// Construct a [`Transport`] implementation that sends to the IPv4 multicast group `group`
+    ///
+    /// The socket will join `group` on the interface with address `interface`
+    /// ([`Ipv4Addr::UNSPECIFIED`] lets the OS choose). Multicast datagrams are sent with a TTL of
+    /// one by default, meaning they will not leave the local network segment; if your collectors
+    /// are further afield than that, raise it via
+    /// [`set_multicast_ttl`](UdpTransport::set_multicast_ttl).
+    ///
+    /// [`Ipv4Addr::UNSPECIFIED`]: std::net::Ipv4Addr::UNSPECIFIED
+    pub fn multicast(
+        group: std::net::Ipv4Addr,
+        port: u16,
+        interface: std::net::Ipv4Addr,
+    ) -> Result<UdpTransport> {
+        let dest = std::net::SocketAddrV4::new(group, port);
+        let local = std::net::SocketAddrV4::new(interface, 0);
+        let socket = std::net::UdpSocket::bind(local).map_err(Error::bind(local))?;
+        socket
+            .join_multicast_v4(&group, &interface)
+            .map_err(Error::connect(dest))?;
+        socket.connect(dest).map_err(Error::connect(dest))?;
+        Ok(UdpTransport {
+            socket,
+            max_datagram_size: MAX_UDP_PAYLOAD,
+            destinations: Vec::new(),
+            bytes_sent: None,
+        })
+    }
+    /// Construct a [`Transport`] implementation that sends to the IPv6 multicast group `group`
+    ///
+    /// `interface` is the index of the interface on which to join `group` (zero lets the OS
+    /// choose). As with [`multicast`](UdpTransport::multicast), the hop limit defaults to one.
+    pub fn multicast_v6(
+        group: std::net::Ipv6Addr,
+        port: u16,
+        interface: u32,
+    ) -> Result<UdpTransport> {
+        let dest = std::net::SocketAddrV6::new(group, port, 0, interface);
+        let local = std::net::SocketAddrV6::new(std::net::Ipv6Addr::UNSPECIFIED, 0, 0, 0);
+        let socket = std::net::UdpSocket::bind(local).map_err(Error::bind(local))?;
+        socket
+            .join_multicast_v6(&group, interface)
+            .map_err(Error::connect(dest))?;
+        socket.connect(dest).map_err(Error::connect(dest))?;
+        Ok(UdpTransport {
+            socket,
+            max_datagram_size: MAX_UDP_PAYLOAD,
+            destinations: Vec::new(),
+            bytes_sent: None,
+        })
+    }
+    /// Set the time-to-live of outgoing IPv4 multicast datagrams (i.e. the number of routers
+    /// through which they may pass)
+    pub fn set_multicast_ttl(&self, ttl: u32) -> Result<()> {
+        Ok(self.socket.set_multicast_ttl_v4(ttl)?)
+    }
+    /// Reject messages longer than `max_datagram_size` bytes (the default is [`MAX_UDP_PAYLOAD`])
+    ///
+    /// Depending on the platform, an oversized datagram may fail to send or may be silently
+    /// truncated. Lowering this to (say) the path MTU means such messages will instead be refused
+    /// up-front with [`Error::MessageTooLarge`].
+    pub fn with_max_datagram_size(mut self, max_datagram_size: usize) -> UdpTransport {
+        self.max_datagram_size = max_datagram_size;
+        self
+    }
+    /// Reject messages that won't fit in a single packet on a link whose MTU is `mtu` bytes
+    ///
+    /// This is [`with_max_datagram_size`](UdpTransport::with_max_datagram_size) less the IP & UDP
+    /// headers (28 bytes over IPv4, 48 over IPv6). Oversized messages are refused, not split:
+    /// splitting a message across datagrams would require some sort of sequence marker in the
+    /// structured data, and collectors in general wouldn't know to reassemble them.
+    pub fn with_mtu(self, mtu: usize) -> UdpTransport {
+        let overhead = match self.socket.local_addr() {
+            Ok(std::net::SocketAddr::V6(_)) => 48,
+            _ => 28,
+        };
+        self.with_max_datagram_size(mtu.saturating_sub(overhead))
+    }
+    /// Set (or clear) the IP "don't fragment" flag on outgoing datagrams
+    ///
+    /// With the flag set, a datagram too large for the path MTU is dropped rather than fragmented
+    /// (& subsequent sends may fail with `EMSGSIZE` once the kernel learns the path MTU); pair this
+    /// with [`with_mtu`](UdpTransport::with_mtu). This is only supported on Linux & Android; on
+    /// other platforms it returns an I/O error of kind [`Unsupported`].
+    ///
+    /// [`Unsupported`]: std::io::ErrorKind::Unsupported
+    pub fn with_dont_fragment(self, dont_fragment: bool) -> Result<UdpTransport> {
+        set_dont_fragment(&self.socket, dont_fragment)?;
+        Ok(self)
+    }
+    /// Keep a running count of the bytes handed-off to the kernel, retrievable via
+    /// [`bytes_sent`](UdpTransport::bytes_sent)
+    ///
+    /// UDP offers no confirmation of delivery; this at least confirms that the local send path was
+    /// exercised, which can be handy in integration tests. Transports not so configured pay nothing
+    /// for this.
+    pub fn with_confirmation(mut self) -> UdpTransport {
+        self.bytes_sent = Some(std::sync::atomic::AtomicUsize::new(0));
+        self
+    }
+    /// The number of bytes accepted by the kernel so far, or `None` if this transport wasn't
+    /// constructed [`with_confirmation`](UdpTransport::with_confirmation)
+    pub fn bytes_sent(&self) -> Option<usize> {
+        self.bytes_sent
+            .as_ref()
+            .map(|n| n.load(std::sync::atomic::Ordering::Relaxed))
+    }
+    /// Create an independently owned handle to the same socket
+    ///
+    /// If this transport is counting bytes sent, the clone will keep its own count (starting from
+    /// zero).
+    pub fn try_clone(&self) -> Result<UdpTransport> {
+        Ok(UdpTransport {
+            socket: self.socket.try_clone()?,
+            max_datagram_size: self.max_datagram_size,
+            destinations: self.destinations.clone(),
+            bytes_sent: self
+                .bytes_sent
+                .as_ref()
+                .map(|_| std::sync::atomic::AtomicUsize::new(0)),
+        })
+    }
+    /// Wait up to `timeout` for the kernel to transmit any datagrams still queued on our socket
+    ///
+    /// UDP has no equivalent of `SO_LINGER`: a send completes once the kernel has accepted the
+    /// datagram, not once it's actually gone out, so a short-lived process (a CLI tool, say) may
+    /// exit with its last few messages still queued. Calling this before exiting gives a
+    /// best-effort assurance that they've left the host (though not, of course, that they've
+    /// arrived).
+    ///
+    /// On Linux & Android, this polls the socket's send queue (via the `SIOCOUTQ` ioctl) until it's
+    /// empty or `timeout` elapses, and returns whether it emptied. Elsewhere (or should the ioctl
+    /// fail), the queue can't be inspected, so this simply sleeps for `timeout` (or
+    /// [`DRAIN_FALLBACK`], whichever is shorter) & returns false.
+    pub fn drain(&self, timeout: Duration) -> Result<bool> {
+        drain_send_queue(&self.socket, timeout)
+    }
+    fn record_sent(&self, n: usize) {
+        if let Some(bytes_sent) = &self.bytes_sent {
+            bytes_sent.fetch_add(n, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+    /// Send `buf` to our destination(s)
+    fn send_bytes(&self, buf: &[u8]) -> Result<()> {
+        if buf.len() > self.max_datagram_size {
+            return Err(Error::MessageTooLarge {
+                len: buf.len(),
+                max: self.max_datagram_size,
+                back: Backtrace::new(),
+            });
+        }
+        if self.destinations.is_empty() {
+            let n = self.socket.send(buf)?;
+            self.record_sent(n);
+            return Ok(());
+        }
+        let mut first_err = None;
+        for dest in &self.destinations {
+            match self.socket.send_to(buf, dest) {
+                Ok(n) => self.record_sent(n),
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        match first_err {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_dont_fragment(socket: &std::net::UdpSocket, dont_fragment: bool) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let (level, name, value) = match (socket.local_addr()?, dont_fragment) {
+        (std::net::SocketAddr::V4(_), true) => (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DO,
+        ),
+        (std::net::SocketAddr::V4(_), false) => (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DONT,
+        ),
+        (std::net::SocketAddr::V6(_), true) => (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DO,
+        ),
+        (std::net::SocketAddr::V6(_), false) => (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DONT,
+        ),
+    };
+    // SAFETY: `socket` owns a valid descriptor, and `value` is a `c_int` that outlives the call,
+    // whose size we pass along with it.
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// How long [`UdpTransport::drain`] sleeps on platforms (or sockets) where the send queue can't be
+/// inspected
+pub const DRAIN_FALLBACK: Duration = Duration::from_millis(50);
+
+/// The number of bytes in `socket`'s send queue (for UDP, not yet transmitted by the kernel; for
+/// TCP, not yet acknowledged by the peer; for Unix streams, not yet read by the peer)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn send_queue_len<S: std::os::unix::io::AsRawFd>(socket: &S) -> std::io::Result<usize> {
+    let mut len: libc::c_int = 0;
+    // SAFETY: `socket` owns a valid descriptor, and SIOCOUTQ (a.k.a. TIOCOUTQ) writes a single
+    // `c_int` through the pointer we pass, which outlives the call.
+    let rc = unsafe {
+        libc::ioctl(
+            socket.as_raw_fd(),
+            libc::TIOCOUTQ,
+            &mut len as *mut libc::c_int,
+        )
+    };
+    if rc == 0 {
+        Ok(len.max(0) as usize)
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn drain_send_queue(socket: &std::net::UdpSocket, timeout: Duration) -> Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match send_queue_len(socket) {
+            Ok(0) => return Ok(true),
+            Ok(_) => (),
+            // If the queue can't be inspected, behave as we would elsewhere
+            Err(_) => {
+                std::thread::sleep(
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(DRAIN_FALLBACK),
+                );
+                return Ok(false);
+            }
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(false);
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(1)));
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn drain_send_queue(_socket: &std::net::UdpSocket, timeout: Duration) -> Result<bool> {
+    std::thread::sleep(timeout.min(DRAIN_FALLBACK));
+    Ok(false)
+}
+
+/// How long [`TcpTransport`] & [`UnixSocketStream`] wait in `flush()` for the peer to take
+/// delivery of everything written, when no write timeout has been set on the socket
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Wait until `socket`'s send queue is empty, for no longer than `write_timeout` (or
+/// [`FLUSH_TIMEOUT`], if that's `None`)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn flush_stream<S: std::os::unix::io::AsRawFd>(
+    socket: &S,
+    write_timeout: Option<Duration>,
+) -> Result<()> {
+    let deadline = Instant::now() + write_timeout.unwrap_or(FLUSH_TIMEOUT);
+    loop {
+        match send_queue_len(socket) {
+            // If the queue can't be inspected, there's nothing on which to wait.
+            Ok(0) | Err(_) => return Ok(()),
+            Ok(_) => (),
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "timed-out waiting for the peer to take delivery",
+            )
+            .into());
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(1)));
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn flush_stream<S>(_socket: &S, _write_timeout: Option<Duration>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_dont_fragment(_socket: &std::net::UdpSocket, _dont_fragment: bool) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the don't-fragment flag is not supported on this platform",
+    ))
+}
+
+/// Adopt an inherited file descriptor (under systemd socket activation, say)
+///
+/// # Safety
+///
+/// `fd` must be an open UDP socket, connected to the syslog daemon, that is owned by no one
+/// else; the returned transport will close it when dropped.
+#[cfg(unix)]
+impl FromRawFd for UdpTransport {
+    unsafe fn from_raw_fd(fd: RawFd) -> UdpTransport {
+        UdpTransport::from_socket(std::net::UdpSocket::from_raw_fd(fd))
+    }
 }
 
 impl<F> Transport<F> for UdpTransport
@@ -169,8 +714,10 @@ where
 {
     type Error = Error;
     fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
-        self.socket.send(&buf)?;
-        Ok(())
+        self.send_bytes(&buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.send_bytes(buf))
     }
 }
 
@@ -178,33 +725,102 @@ where
 //                                         TCP Transport                                          //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// How messages are delimited on a stream; see [RFC 6587]
+///
+/// [RFC 6587]: https://datatracker.ietf.org/doc/html/rfc6587#section-3.4
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Non-transparent framing: each message is followed by a trailing newline (10/0x0a)
+    ///
+    /// This is the most widely supported, but a newline within a message will be taken by the
+    /// collector as the end of that message.
+    #[default]
+    NonTransparent,
+    /// Octet-counting: each message is preceded by its length in bytes & a space
+    OctetCounting,
+    /// No framing at all: messages are written back-to-back
+    ///
+    /// This is only sensible for collectors that take the closing of the connection to mark the
+    /// end of the message, i.e. when sending one message per connection.
+    Raw,
+}
+
 /// Sending syslog message via TCP streams
 ///
-/// Note that this implementation, at present, uses non-transparent framing with a trailing
-/// character of 10/0x0a/newline.
+/// By default, this implementation uses non-transparent framing with a trailing character of
+/// 10/0x0a/newline; other [`Framing`]s may be selected via
+/// [`with_framing`](TcpTransport::with_framing).
+///
+/// A write to a TCP socket completes once the kernel has buffered the data, so
+/// [`flush`](Transport::flush) waits for the peer to acknowledge everything written, for no longer
+/// than the socket's write timeout (see [`with_write_timeout`](TcpTransport::with_write_timeout)),
+/// or [`FLUSH_TIMEOUT`] if none has been set. That's only possible on Linux & Android, where the
+/// send queue can be inspected; elsewhere, `flush` does nothing.
+#[derive(Debug)]
 pub struct TcpTransport {
     socket: std::net::TcpStream,
+    framing: Framing,
 }
 
 impl TcpTransport {
     /// Construct a [`Transport`] implementation via TCP at `addr`.
     pub fn new<A: std::net::ToSocketAddrs>(addr: A) -> Result<TcpTransport> {
+        let addrs = resolve(addr)?;
         Ok(TcpTransport {
-            socket: TcpStream::connect(addr)?,
+            socket: TcpStream::connect(&addrs[..]).map_err(Error::connect(describe(&addrs)))?,
+            framing: Framing::default(),
         })
     }
+    /// Adopt a connection established elsewhere (inherited via socket activation, say)
+    pub fn from_stream(socket: TcpStream) -> TcpTransport {
+        TcpTransport {
+            socket,
+            framing: Framing::default(),
+        }
+    }
+    /// Delimit messages according to `framing`
+    pub fn with_framing(mut self, framing: Framing) -> TcpTransport {
+        self.framing = framing;
+        self
+    }
+    /// Give up on writes (and flushes) that take longer than `timeout`
+    pub fn with_write_timeout(self, timeout: Duration) -> Result<TcpTransport> {
+        self.socket.set_write_timeout(Some(timeout))?;
+        Ok(self)
+    }
     /// Construct a [`Transport`] implementation via TCP at localhost:514
     pub fn try_default() -> Result<TcpTransport> {
         TcpTransport::new("localhost:514")
     }
-}
-
-impl<F> Transport<F> for TcpTransport
-where
-    F: SyslogFormatter,
-{
-    type Error = Error;
-    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+    /// Begin building a [`Transport`] implementation via TCP at `addr`, with control over
+    /// socket options
+    ///
+    /// `addr` is resolved immediately, but no connection is made until
+    /// [`build`](TcpTransportBuilder::build) is called.
+    pub fn builder<A: std::net::ToSocketAddrs>(addr: A) -> Result<TcpTransportBuilder> {
+        Ok(TcpTransportBuilder {
+            addrs: resolve(addr)?,
+            nodelay: false,
+            keepalive: None,
+            connect_timeout: None,
+            framing: Framing::default(),
+        })
+    }
+    /// Create an independently owned handle to the same connection
+    pub fn try_clone(&self) -> Result<TcpTransport> {
+        Ok(TcpTransport {
+            socket: self.socket.try_clone()?,
+            framing: self.framing,
+        })
+    }
+    /// The underlying connection (for use with [`Hostname::from_local_addr`], say)
+    ///
+    /// [`Hostname::from_local_addr`]: crate::rfc5424::Hostname::from_local_addr
+    pub fn stream(&self) -> &TcpStream {
+        &self.socket
+    }
+    /// Write `buf` to our stream, framed according to our [`Framing`]
+    fn send_bytes(&self, buf: &[u8]) -> Result<()> {
         use std::io::Write;
         // Trick I learned from tracing-subscriber.
         // <https://docs.rs/tracing-subscriber/0.3.11/src/tracing_subscriber/fmt/fmt_layer.rs.html#867-903>
@@ -223,105 +839,2696 @@ where
         //
         // Reddit discussion here:
         // <https://www.reddit.com/r/rust/comments/v2uxze/getting_a_mutable_reference_to_self_in_a_method/>
-        writer.write_all(&buf)?;
-        writer.write_all(&[10])?;
+        match self.framing {
+            Framing::NonTransparent => {
+                writer.write_all(buf)?;
+                writer.write_all(&[10])?;
+            }
+            Framing::OctetCounting => {
+                write!(writer, "{} ", buf.len())?;
+                writer.write_all(buf)?;
+            }
+            Framing::Raw => writer.write_all(buf)?,
+        }
         writer.flush()?;
 
         Ok(())
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////
-//                                    Unix Domain Sockets/UDP                                     //
-////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Builder for [`TcpTransport`]s requiring socket options; see [`TcpTransport::builder`]
+#[derive(Clone, Debug)]
+pub struct TcpTransportBuilder {
+    addrs: Vec<std::net::SocketAddr>,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    framing: Framing,
+}
 
-/// Sending syslog messages via Unix socket (datagram)
-#[cfg(unix)]
-pub struct UnixSocket {
-    socket: UnixDatagram,
+impl TcpTransportBuilder {
+    /// Disable Nagle's algorithm (i.e. set `TCP_NODELAY`), so that small messages are sent
+    /// immediately rather than being held back for coalescing
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+    /// Enable TCP keepalive, with probes beginning after the connection has been idle for `idle`
+    ///
+    /// This permits a dead peer to be detected on a long-lived, mostly-idle connection.
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+    /// Give up on connecting after `timeout`, rather than waiting on the operating system
+    ///
+    /// The timeout applies to each address to which the destination resolved, in turn.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    /// Delimit messages according to `framing`
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+    /// Connect & apply the requested socket options
+    pub fn build(self) -> Result<TcpTransport> {
+        let dest = describe(&self.addrs);
+        let socket = match self.connect_timeout {
+            Some(timeout) => {
+                // `connect_timeout()` takes but a single address, so try each in turn (as
+                // `connect()` would), reporting the last failure.
+                let mut last_err = None;
+                let mut socket = None;
+                for addr in &self.addrs {
+                    match TcpStream::connect_timeout(addr, timeout) {
+                        Ok(stream) => {
+                            socket = Some(stream);
+                            break;
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                match (socket, last_err) {
+                    (Some(socket), _) => socket,
+                    (None, Some(err)) => return Err(Error::connect(&dest)(err)),
+                    // `resolve()` guarantees at least one address
+                    (None, None) => unreachable!(),
+                }
+            }
+            None => TcpStream::connect(&self.addrs[..]).map_err(Error::connect(&dest))?,
+        };
+        if self.nodelay {
+            socket.set_nodelay(true)?;
+        }
+        if let Some(idle) = self.keepalive {
+            socket2::SockRef::from(&socket)
+                .set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle))?;
+        }
+        Ok(TcpTransport {
+            socket,
+            framing: self.framing,
+        })
+    }
+    /// Open `size` connections (at least one), each with the requested socket options, & pool
+    /// them; see [`PooledTcpTransport`]
+    pub fn build_pool(self, size: usize) -> Result<PooledTcpTransport> {
+        let connections = (0..size.max(1))
+            .map(|_| self.clone().build().map(Mutex::new))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PooledTcpTransport {
+            connections,
+            next: std::sync::atomic::AtomicUsize::new(0),
+        })
+    }
 }
 
+/// Adopt an inherited file descriptor (under systemd socket activation, say)
+///
+/// # Safety
+///
+/// `fd` must be an open TCP socket, connected to the syslog daemon, that is owned by no one
+/// else; the returned transport will close it when dropped.
 #[cfg(unix)]
-impl UnixSocket {
-    /// Construct a [`Transport`] implementation via Unix datagram sockets at `path`.
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<UnixSocket> {
-        let sock = UnixDatagram::unbound()?;
-        sock.connect(path)?;
-        Ok(UnixSocket { socket: sock })
+impl FromRawFd for TcpTransport {
+    unsafe fn from_raw_fd(fd: RawFd) -> TcpTransport {
+        TcpTransport::from_stream(TcpStream::from_raw_fd(fd))
     }
-    pub fn try_default() -> Result<UnixSocket> {
-        UnixSocket::new("/dev/log")
+}
+
+impl<F> Transport<F> for TcpTransport
+where
+    F: SyslogFormatter,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.send_bytes(&buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.send_bytes(buf))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        flush_stream(&self.socket, self.socket.write_timeout()?)
     }
 }
 
-#[cfg(unix)]
-impl<F> Transport<F> for UnixSocket
+/// Sending syslog messages over a pool of TCP connections
+///
+/// A [`TcpTransport`] shared by many threads funnels all their messages through a single stream.
+/// [`PooledTcpTransport`] instead holds several connections to the collector, each a
+/// [`TcpTransport`] (with the same framing & socket options) guarded by its own lock. Each send
+/// starts with the next connection in round-robin order, moving on to the others should that one be
+/// busy, & waits only if they all are. Holding the lock for the duration of the send also ensures
+/// that messages sent concurrently are never interleaved on the wire.
+///
+/// Messages sent from different threads may of course arrive on different connections, so the
+/// collector may not see them in the order in which they were sent.
+#[derive(Debug)]
+pub struct PooledTcpTransport {
+    connections: Vec<Mutex<TcpTransport>>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+impl PooledTcpTransport {
+    /// Open `size` connections (at least one) to `addr`
+    ///
+    /// To set socket options on each connection, use [`TcpTransportBuilder::build_pool`] instead.
+    pub fn new<A: std::net::ToSocketAddrs>(addr: A, size: usize) -> Result<PooledTcpTransport> {
+        TcpTransport::builder(addr)?.build_pool(size)
+    }
+    /// Delimit messages according to `framing`, on every connection
+    pub fn with_framing(mut self, framing: Framing) -> PooledTcpTransport {
+        for connection in &mut self.connections {
+            connection
+                .get_mut()
+                .unwrap_or_else(|err| err.into_inner())
+                .framing = framing;
+        }
+        self
+    }
+    /// The number of connections in the pool
+    pub fn size(&self) -> usize {
+        self.connections.len()
+    }
+    /// Lock a connection, preferring the next in round-robin order but taking any that's free
+    fn connection(&self) -> std::sync::MutexGuard<'_, TcpTransport> {
+        let n = self.connections.len();
+        let first = self.next.fetch_add(1, Ordering::Relaxed) % n;
+        for i in 0..n {
+            if let Ok(guard) = self.connections[(first + i) % n].try_lock() {
+                return guard;
+            }
+        }
+        // A panic mid-send may have left a partial message on the stream, but there's nothing to
+        // be done about that now.
+        self.connections[first]
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<F> Transport<F> for PooledTcpTransport
 where
     F: SyslogFormatter,
 {
     type Error = Error;
     fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
-        self.socket.send(&buf)?;
+        self.connection().send_bytes(&buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.connection().send_bytes(buf))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        for connection in &self.connections {
+            let connection = connection.lock().unwrap_or_else(|err| err.into_inner());
+            Transport::<F>::flush(&*connection)?;
+        }
         Ok(())
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
-//                                    Unix Domain Sockets/TCP                                     //
+//                                         RELP Transport                                         //
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-/// Sending syslog messages via Unix socket (stream)
+/// The largest RELP transaction number; numbers wrap back around to one after this
+#[cfg(feature = "relp")]
+const RELP_MAX_TXNR: u32 = 999_999_999;
+
+/// How long to wait on the peer when closing a RELP session
+#[cfg(feature = "relp")]
+const RELP_CLOSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sending syslog messages via [RELP], the Reliable Event Logging Protocol
 ///
-/// Note that this implementation, at present, uses non-transparent framing with a trailing
-/// character of 10/0x0a/newline.
-#[cfg(unix)]
-pub struct UnixSocketStream {
-    socket: UnixStream,
+/// [RELP]: https://www.rsyslog.com/doc/relp.html
+///
+/// RELP runs over TCP, but unlike [`TcpTransport`] the peer acknowledges each message once it has
+/// actually processed it. [`send`](Transport::send) waits for that acknowledgement before
+/// returning, which gives at-least-once semantics: a message that the peer declines is reported
+/// as [`Error::Rejected`], and one that can't be confirmed at all is reported as an I/O or
+/// protocol error.
+///
+/// The price is that every message costs a round-trip to the syslog daemon, during which the
+/// logging thread is blocked. If that's too much, wrap this transport in a [`BufferedTransport`]
+/// to at least move the wait off the hot path, or set an upper bound on it via
+/// [`with_ack_timeout`](RelpTransport::with_ack_timeout).
+///
+/// After an I/O or protocol error the session can no longer be trusted to be in sync with the
+/// peer, & a new [`RelpTransport`] should be constructed.
+#[cfg(feature = "relp")]
+pub struct RelpTransport {
+    session: Mutex<RelpSession>,
 }
 
-#[cfg(unix)]
-impl UnixSocketStream {
-    /// Construct a [`Transport`] implementation via Unix sockets at `path`.
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<UnixSocketStream> {
-        Ok(UnixSocketStream {
-            socket: UnixStream::connect(path)?,
+#[cfg(feature = "relp")]
+impl std::fmt::Debug for RelpTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RelpTransport").finish_non_exhaustive()
+    }
+}
+
+/// One RELP session; the connection together with the last transaction number used on it
+#[cfg(feature = "relp")]
+struct RelpSession {
+    reader: std::io::BufReader<TcpStream>,
+    txnr: u32,
+    /// Set once an exchange has failed other than by the server's refusal, after which we can no
+    /// longer trust the state of the connection
+    broken: bool,
+}
+
+#[cfg(feature = "relp")]
+impl RelpSession {
+    /// Send `command` with `data` to the peer & wait for its response; return the response data
+    /// (less the status line) on success
+    fn command(&mut self, command: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let rsp = self.exchange(command, data);
+        if matches!(rsp, Err(Error::Io { .. }) | Err(Error::Protocol { .. })) {
+            self.broken = true;
+        }
+        rsp
+    }
+    /// Carry out one command/response exchange with the peer
+    fn exchange(&mut self, command: &str, data: &[u8]) -> Result<Vec<u8>> {
+        use std::io::Write;
+        self.txnr = if self.txnr >= RELP_MAX_TXNR {
+            1
+        } else {
+            self.txnr + 1
+        };
+        let txnr = self.txnr;
+        let mut frame = Vec::with_capacity(data.len() + 32);
+        write!(frame, "{} {} {}", txnr, command, data.len())?;
+        if !data.is_empty() {
+            frame.push(b' ');
+            frame.extend_from_slice(data);
+        }
+        frame.push(b'\n');
+        let mut writer: &TcpStream = self.reader.get_ref();
+        writer.write_all(&frame)?;
+        writer.flush()?;
+
+        let (rsp_txnr, rsp_command, rsp_data) = self.read_frame()?;
+        if rsp_command == "serverclose" {
+            return Err(Error::Protocol {
+                reason: "the server closed the session".to_string(),
+                back: Backtrace::new(),
+            });
+        }
+        if rsp_command != "rsp" || rsp_txnr != txnr {
+            return Err(Error::Protocol {
+                reason: format!(
+                    "expected a response to transaction {}, got \"{} {}\"",
+                    txnr, rsp_txnr, rsp_command
+                ),
+                back: Backtrace::new(),
+            });
+        }
+        // The response data is a three-digit status code, optionally followed by a space & a
+        // human-readable message, optionally followed by a newline & command-specific data.
+        let (status, rest) = match rsp_data.iter().position(|&b| b == b'\n') {
+            Some(n) => (&rsp_data[..n], rsp_data[n + 1..].to_vec()),
+            None => (&rsp_data[..], Vec::new()),
+        };
+        if status.starts_with(b"200") {
+            Ok(rest)
+        } else {
+            Err(Error::Rejected {
+                status: String::from_utf8_lossy(status).into_owned(),
+                back: Backtrace::new(),
+            })
+        }
+    }
+    /// Read one frame from the peer: TXNR SP COMMAND SP DATALEN [SP DATA] TRAILER
+    fn read_frame(&mut self) -> Result<(u32, String, Vec<u8>)> {
+        use std::io::Read;
+        let (txnr, _) = self.read_token()?;
+        let (command, _) = self.read_token()?;
+        let (datalen, delim) = self.read_token()?;
+        let txnr = txnr.parse::<u32>().map_err(|_| Error::Protocol {
+            reason: format!("bad transaction number \"{}\"", txnr),
+            back: Backtrace::new(),
+        })?;
+        let datalen = datalen.parse::<usize>().map_err(|_| Error::Protocol {
+            reason: format!("bad data length \"{}\"", datalen),
+            back: Backtrace::new(),
+        })?;
+        let mut data = vec![0u8; datalen];
+        if delim == b' ' {
+            self.reader.read_exact(&mut data)?;
+            let mut trailer = [0u8; 1];
+            self.reader.read_exact(&mut trailer)?;
+            if trailer[0] != b'\n' {
+                return Err(Error::Protocol {
+                    reason: "missing frame trailer".to_string(),
+                    back: Backtrace::new(),
+                });
+            }
+        } else if datalen != 0 {
+            return Err(Error::Protocol {
+                reason: format!("expected {} bytes of data, got none", datalen),
+                back: Backtrace::new(),
+            });
+        }
+        Ok((txnr, command, data))
+    }
+    /// Read one space- or newline-delimited header token; return it along with its delimiter
+    fn read_token(&mut self) -> Result<(String, u8)> {
+        use std::io::Read;
+        // The longest header field is the command name, which RELP limits to 32 characters
+        const MAX_TOKEN: usize = 32;
+        let mut token = String::new();
+        let mut byte = [0u8; 1];
+        loop {
+            self.reader.read_exact(&mut byte)?;
+            match byte[0] {
+                b' ' | b'\n' => return Ok((token, byte[0])),
+                b if b.is_ascii_alphanumeric() && token.len() < MAX_TOKEN => token.push(b as char),
+                b => {
+                    return Err(Error::Protocol {
+                        reason: format!("unexpected byte {:#04x} in frame header", b),
+                        back: Backtrace::new(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "relp")]
+impl RelpTransport {
+    /// Connect to a RELP server at `addr` & open a session
+    pub fn new<A: std::net::ToSocketAddrs>(addr: A) -> Result<RelpTransport> {
+        let addrs = resolve(addr)?;
+        let socket = TcpStream::connect(&addrs[..]).map_err(Error::connect(describe(&addrs)))?;
+        let mut session = RelpSession {
+            reader: std::io::BufReader::new(socket),
+            txnr: 0,
+            broken: false,
+        };
+        let offers = session.command(
+            "open",
+            concat!(
+                "relp_version=0\nrelp_software=tracing-rfc-5424,",
+                env!("CARGO_PKG_VERSION"),
+                "\ncommands=syslog"
+            )
+            .as_bytes(),
+        )?;
+        // The server replies with the offers it accepts; make sure `syslog` is among them.
+        let syslog_ok = offers.split(|&b| b == b'\n').any(|offer| {
+            offer
+                .strip_prefix(b"commands=")
+                .map(|cmds| cmds.split(|&b| b == b',').any(|cmd| cmd == b"syslog"))
+                .unwrap_or(false)
+        });
+        if !syslog_ok {
+            return Err(Error::Protocol {
+                reason: "the server doesn't support the syslog command".to_string(),
+                back: Backtrace::new(),
+            });
+        }
+        Ok(RelpTransport {
+            session: Mutex::new(session),
         })
     }
-    pub fn try_default() -> Result<UnixSocket> {
-        UnixSocket::new("/dev/log")
+    /// Give up waiting for the server to acknowledge a message after `timeout`
+    ///
+    /// A message whose acknowledgement times-out is reported as an I/O error; it may or may not
+    /// have been processed by the server.
+    pub fn with_ack_timeout(self, timeout: Duration) -> Result<Self> {
+        self.session()
+            .reader
+            .get_ref()
+            .set_read_timeout(Some(timeout))?;
+        Ok(self)
+    }
+    /// Lock our session
+    fn session(&self) -> std::sync::MutexGuard<'_, RelpSession> {
+        // If a thread panicked mid-transaction, the next one will find out soon enough.
+        self.session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 }
 
-#[cfg(unix)]
-impl<F> Transport<F> for UnixSocketStream
+#[cfg(feature = "relp")]
+impl<F> Transport<F> for RelpTransport
 where
     F: SyslogFormatter,
 {
     type Error = Error;
     fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
-        use std::io::Write;
+        self.session().command("syslog", &buf).map(|_| ())
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.session().command("syslog", buf).map(|_| ()))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        // Every message has been acknowledged by the time `send()` returns
+        Ok(())
+    }
+}
 
-        // Trick I learned from tracing-subscriber.
-        // <https://docs.rs/tracing-subscriber/0.3.11/src/tracing_subscriber/fmt/fmt_layer.rs.html#867-903>
-        // The problem is that `std::io::Write()` takes a `&mut self` and we just have a
-        // `&self`. Therefore if I naively call:
-        //
-        //     self.socket.write_all(buf)
-        //
-        // the compiler will complain.
-        //
-        // The workaround depends upon the fact that `Write` is implemented both on `UnixStream` and
-        // `&UnixStream`. So: I declare a mutable variable `writer` whose type is `&UnixStream`...
-        let mut writer: &UnixStream = &self.socket;
-        // and invoke `write_all()` on _that_ receiver, whose type is `&mut &UnixStream`--
-        // i.e. "self" will be `&UnixStream` not `UnixStream`.
+#[cfg(feature = "relp")]
+impl Drop for RelpTransport {
+    fn drop(&mut self) {
+        // Close the session politely, unless it's already broken (in which case the server won't
+        // answer, or worse, we'd mistake a stale response for its answer). There's no one to whom
+        // we can report a failure, though, and we mustn't hang the dropping thread on a server that
+        // won't reply, so bound the wait.
+        let mut session = self.session();
+        if session.broken {
+            return;
+        }
+        let socket = session.reader.get_ref();
+        let timeout = match socket.read_timeout() {
+            Ok(Some(timeout)) => timeout.min(RELP_CLOSE_TIMEOUT),
+            _ => RELP_CLOSE_TIMEOUT,
+        };
+        if socket.set_read_timeout(Some(timeout)).is_ok()
+            && socket.set_write_timeout(Some(RELP_CLOSE_TIMEOUT)).is_ok()
+        {
+            let _ = session.command("close", &[]);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                    Unix Domain Sockets/UDP                                     //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Sending syslog messages via Unix socket (datagram)
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixSocket {
+    socket: UnixDatagram,
+    // Shared between clones, so that the socket file is removed when the last of them is dropped
+    bound: Option<Arc<BoundPath>>,
+}
+
+/// The path to which a client socket was bound; the socket file is removed on drop
+#[cfg(unix)]
+#[derive(Debug)]
+struct BoundPath(std::path::PathBuf);
+
+#[cfg(unix)]
+impl Drop for BoundPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Bind a datagram socket to `path`, replacing any stale socket file left there
+///
+/// A socket file outlives the socket that created it, so a process that exits without cleaning
+/// up will leave `path` occupied. We only remove it if it's a socket to which no one is listening,
+/// however: anything else at `path` is an error.
+#[cfg(unix)]
+fn bind_datagram(path: &Path) -> std::io::Result<UnixDatagram> {
+    use std::os::unix::fs::FileTypeExt;
+    match UnixDatagram::bind(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::AddrInUse => {
+            let is_socket = std::fs::symlink_metadata(path)
+                .map(|md| md.file_type().is_socket())
+                .unwrap_or(false);
+            let stale = is_socket
+                && matches!(
+                    UnixDatagram::unbound().and_then(|sock| sock.connect(path)),
+                    Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused
+                );
+            if !stale {
+                return Err(err);
+            }
+            std::fs::remove_file(path)?;
+            UnixDatagram::bind(path)
+        }
+        res => res,
+    }
+}
+
+#[cfg(unix)]
+impl UnixSocket {
+    /// Construct a [`Transport`] implementation via Unix datagram sockets at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<UnixSocket> {
+        let path = path.as_ref();
+        let sock = UnixDatagram::unbound()?;
+        sock.connect(path).map_err(Error::connect(path.display()))?;
+        Ok(UnixSocket {
+            socket: sock,
+            bound: None,
+        })
+    }
+    /// Construct a [`Transport`] implementation via a Unix datagram socket bound to `bind_path` &
+    /// connected to `dest`
+    ///
+    /// Some daemons will only accept messages from clients with an address (to look-up their
+    /// credentials, e.g.). A stale socket file at `bind_path` will be replaced, but a live one (or
+    /// any other sort of file) is an error. The socket file is removed when this transport (and
+    /// all clones thereof) are dropped.
+    pub fn with_bind<P: AsRef<Path>, Q: AsRef<Path>>(bind_path: P, dest: Q) -> Result<UnixSocket> {
+        let (bind_path, dest) = (bind_path.as_ref(), dest.as_ref());
+        let sock = bind_datagram(bind_path).map_err(Error::bind(bind_path.display()))?;
+        // Take ownership of the socket file now, so it's cleaned-up should the connect fail
+        let bound = Arc::new(BoundPath(bind_path.to_path_buf()));
+        sock.connect(dest).map_err(Error::connect(dest.display()))?;
+        Ok(UnixSocket {
+            socket: sock,
+            bound: Some(bound),
+        })
+    }
+    /// Adopt a socket created & configured elsewhere (inherited via socket activation, say)
+    ///
+    /// `socket` must already be connected to the syslog daemon.
+    pub fn from_datagram(socket: UnixDatagram) -> UnixSocket {
+        UnixSocket {
+            socket,
+            bound: None,
+        }
+    }
+    pub fn try_default() -> Result<UnixSocket> {
+        UnixSocket::new("/dev/log")
+    }
+    /// Create an independently owned handle to the same socket
+    pub fn try_clone(&self) -> Result<UnixSocket> {
+        Ok(UnixSocket {
+            socket: self.socket.try_clone()?,
+            bound: self.bound.clone(),
+        })
+    }
+}
+
+/// Adopt an inherited file descriptor (under systemd socket activation, say)
+///
+/// # Safety
+///
+/// `fd` must be an open Unix datagram socket, connected to the syslog daemon, that is owned by
+/// no one else; the returned transport will close it when dropped.
+#[cfg(unix)]
+impl FromRawFd for UnixSocket {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixSocket {
+        UnixSocket::from_datagram(UnixDatagram::from_raw_fd(fd))
+    }
+}
+
+#[cfg(unix)]
+impl<F> Transport<F> for UnixSocket
+where
+    F: SyslogFormatter,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.socket.send(&buf)?;
+        Ok(())
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.socket.send(buf).map(|_| ()).map_err(Error::from))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                    Unix Domain Sockets/TCP                                     //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Sending syslog messages via Unix socket (stream)
+///
+/// Note that this implementation, at present, uses non-transparent framing with a trailing
+/// character of 10/0x0a/newline.
+///
+/// As with [`TcpTransport`], [`flush`](Transport::flush) waits (on Linux & Android) for the
+/// daemon to read everything written, for no longer than the socket's write timeout (see
+/// [`with_write_timeout`](UnixSocketStream::with_write_timeout)) or [`FLUSH_TIMEOUT`].
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixSocketStream {
+    socket: UnixStream,
+}
+
+#[cfg(unix)]
+impl UnixSocketStream {
+    /// Construct a [`Transport`] implementation via Unix sockets at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<UnixSocketStream> {
+        let path = path.as_ref();
+        Ok(UnixSocketStream {
+            socket: UnixStream::connect(path).map_err(Error::connect(path.display()))?,
+        })
+    }
+    /// Construct a [`Transport`] implementation via Unix sockets at `/dev/log`
+    pub fn try_default() -> Result<UnixSocketStream> {
+        UnixSocketStream::new("/dev/log")
+    }
+    /// Give up on writes (and flushes) that take longer than `timeout`
+    pub fn with_write_timeout(self, timeout: Duration) -> Result<UnixSocketStream> {
+        self.socket.set_write_timeout(Some(timeout))?;
+        Ok(self)
+    }
+    /// Create an independently owned handle to the same connection
+    pub fn try_clone(&self) -> Result<UnixSocketStream> {
+        Ok(UnixSocketStream {
+            socket: self.socket.try_clone()?,
+        })
+    }
+    /// Write `buf` to our stream, followed by the trailing newline
+    fn send_bytes(&self, buf: &[u8]) -> Result<()> {
+        use std::io::Write;
+
+        // Trick I learned from tracing-subscriber.
+        // <https://docs.rs/tracing-subscriber/0.3.11/src/tracing_subscriber/fmt/fmt_layer.rs.html#867-903>
+        // The problem is that `std::io::Write()` takes a `&mut self` and we just have a
+        // `&self`. Therefore if I naively call:
+        //
+        //     self.socket.write_all(buf)
+        //
+        // the compiler will complain.
+        //
+        // The workaround depends upon the fact that `Write` is implemented both on `UnixStream` and
+        // `&UnixStream`. So: I declare a mutable variable `writer` whose type is `&UnixStream`...
+        let mut writer: &UnixStream = &self.socket;
+        // and invoke `write_all()` on _that_ receiver, whose type is `&mut &UnixStream`--
+        // i.e. "self" will be `&UnixStream` not `UnixStream`.
         //
         // Reddit discussion here:
         // <https://www.reddit.com/r/rust/comments/v2uxze/getting_a_mutable_reference_to_self_in_a_method/>
-        writer.write_all(&buf)?;
+        writer.write_all(buf)?;
         writer.write_all(&[10])?;
         writer.flush()?;
 
         Ok(())
     }
 }
+
+#[cfg(unix)]
+impl<F> Transport<F> for UnixSocketStream
+where
+    F: SyslogFormatter,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.send_bytes(&buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.send_bytes(buf))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        flush_stream(&self.socket, self.socket.write_timeout()?)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                       In-memory Transport                                      //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Collecting syslog messages in memory
+///
+/// [`VecTransport`] doesn't send anything anywhere; it just appends each message it's handed to a
+/// buffer. It's intended for testing: clones share the same buffer, so one can give a clone to a
+/// [`Layer`] & hang on to the original in order to inspect what was "sent".
+///
+/// [`Layer`]: crate::layer::Layer
+#[derive(Clone, Default)]
+pub struct VecTransport {
+    messages: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl VecTransport {
+    /// Construct a [`Transport`] implementation that collects messages in memory
+    pub fn new() -> VecTransport {
+        VecTransport::default()
+    }
+    /// Retrieve a copy of all messages sent on this transport (or any of its clones) so far
+    pub fn messages(&self) -> Vec<Vec<u8>> {
+        // A poisoned lock just means some other thread panicked while pushing a message; the
+        // messages collected so far are still perfectly good.
+        self.messages
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+impl<F> Transport<F> for VecTransport
+where
+    F: SyslogFormatter,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(buf.to_vec());
+        Ok(())
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(buf.to_vec());
+        Some(Ok(()))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                       Writer Transport                                         //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writing syslog messages to an arbitrary [`Write`] implementation
+///
+/// Each message is written followed by a newline (the same framing as [`TcpTransport`]), and
+/// optionally preceded by a fixed [prefix](WriterTransport::with_prefix). This permits piping
+/// syslog messages into a file, another process' stdin, or an in-memory buffer. The
+/// writer is held behind a [`Mutex`] (since [`Transport::send`] takes `&self`), which also ensures
+/// that messages sent concurrently won't be interleaved.
+///
+/// Messages are *not* flushed as they're sent; if `W` buffers, messages will reach their
+/// destination when it decides, when [`Transport::flush`] is called, or when the [`Layer`] owning
+/// this transport is dropped.
+///
+/// [`Write`]: std::io::Write
+/// [`Layer`]: crate::layer::Layer
+#[derive(Debug)]
+pub struct WriterTransport<W: std::io::Write + Send> {
+    writer: Mutex<W>,
+    prefix: Option<String>,
+}
+
+impl<W: std::io::Write + Send> WriterTransport<W> {
+    /// Construct a [`Transport`] implementation that writes to `writer`
+    pub fn new(writer: W) -> WriterTransport<W> {
+        WriterTransport {
+            writer: Mutex::new(writer),
+            prefix: None,
+        }
+    }
+    /// Write `prefix` before each message
+    pub fn with_prefix<P: Into<String>>(mut self, prefix: P) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+    /// Consume this transport, returning the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+    fn write_line(&self, buf: &[u8]) -> Result<()> {
+        // If another thread panicked mid-write, there may be a partial message in the writer, but
+        // there's nothing to be done about that now.
+        let mut writer = self.writer.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(prefix) = &self.prefix {
+            writer.write_all(prefix.as_bytes())?;
+        }
+        writer.write_all(buf)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<F, W> Transport<F> for WriterTransport<W>
+where
+    F: SyslogFormatter,
+    W: std::io::Write + Send,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.write_line(&buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        Some(self.write_line(buf))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        self.writer
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .flush()?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                        Debug Transport                                         //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Showing, rather than sending, syslog messages
+///
+/// A [`DebugTransport`] is just a [`WriterTransport`] that writes each message to stderr exactly
+/// as it would have been sent, prefixed with `[would-send] ` & followed by a newline; construct one
+/// via [`WriterTransport::debug`]. It's meant for checking one's configuration without a syslog
+/// daemon to hand; see [`Layer::dry_run`](crate::layer::Layer::dry_run). To show messages
+/// somewhere other than stderr, give a [`WriterTransport`] the same
+/// [prefix](WriterTransport::with_prefix).
+pub type DebugTransport = WriterTransport<std::io::Stderr>;
+
+impl WriterTransport<std::io::Stderr> {
+    /// Construct a [`DebugTransport`]
+    pub fn debug() -> DebugTransport {
+        WriterTransport::new(std::io::stderr()).with_prefix("[would-send] ")
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                         log Transport                                          //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Re-emitting syslog messages via the [`log`] crate's facade
+///
+/// [`log`]: https://docs.rs/log
+///
+/// This is for codebases part-way through a migration, in which some sinks are still configured
+/// through [`log`]. Each message is logged with target `syslog` (see
+/// [`with_target`](LogTransport::with_target)) at the [`log::Level`] corresponding to its
+/// severity:
+///
+/// | severity                      | [`log::Level`] |
+/// |-------------------------------|----------------|
+/// | `LOG_EMERG` through `LOG_ERR` | `Error`        |
+/// | `LOG_WARNING`                 | `Warn`         |
+/// | `LOG_NOTICE` & `LOG_INFO`     | `Info`         |
+/// | `LOG_DEBUG`                   | `Debug`        |
+///
+/// The severity is recovered from the PRI with which both the RFC 3164 & RFC 5424 formats begin;
+/// the remainder of the message is logged verbatim (or as near as UTF-8 permits). Messages with no
+/// PRI are logged in their entirety at `Info`.
+///
+/// Don't combine this with a bridge carrying [`log`] records into [`tracing`] (such as
+/// `tracing-log`'s `LogTracer`): each message would be fed back to the [`Layer`] & logged again.
+///
+/// This requires the `log` feature.
+///
+/// [`tracing`]: https://docs.rs/tracing/latest/tracing/index.html
+/// [`Layer`]: crate::layer::Layer
+#[cfg(feature = "log")]
+#[derive(Clone, Debug)]
+pub struct LogTransport {
+    target: String,
+}
+
+#[cfg(feature = "log")]
+impl std::default::Default for LogTransport {
+    fn default() -> Self {
+        LogTransport {
+            target: "syslog".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl LogTransport {
+    pub fn new() -> LogTransport {
+        LogTransport::default()
+    }
+    /// Log messages with target `target` (rather than `syslog`)
+    pub fn with_target<S: Into<String>>(mut self, target: S) -> LogTransport {
+        self.target = target.into();
+        self
+    }
+    /// Split the PRI off `buf`, returning the corresponding [`log::Level`] & the remainder
+    fn split_pri(buf: &[u8]) -> (log::Level, &[u8]) {
+        let pri = buf
+            .strip_prefix(b"<")
+            .and_then(|rest| {
+                let end = rest.iter().position(|&b| b == b'>')?;
+                let pri = std::str::from_utf8(&rest[..end]).ok()?.parse::<u8>().ok()?;
+                Some((pri, &rest[end + 1..]))
+            })
+            .filter(|(pri, _)| *pri < 192);
+        match pri {
+            Some((pri, rest)) => (
+                match pri % 8 {
+                    0..=3 => log::Level::Error,
+                    4 => log::Level::Warn,
+                    5 | 6 => log::Level::Info,
+                    _ => log::Level::Debug,
+                },
+                rest,
+            ),
+            None => (log::Level::Info, buf),
+        }
+    }
+    fn log(&self, buf: &[u8]) {
+        let (level, msg) = LogTransport::split_pri(buf);
+        log::log!(target: &self.target, level, "{}", String::from_utf8_lossy(msg));
+    }
+}
+
+#[cfg(feature = "log")]
+impl<F> Transport<F> for LogTransport
+where
+    F: SyslogFormatter,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        self.log(&buf);
+        Ok(())
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        self.log(buf);
+        Some(Ok(()))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        log::logger().flush();
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                    Rate-limiting Transport                                     //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Rate-limiting some other [`Transport`]
+///
+/// A tight loop emitting `error!`s can overwhelm a syslog daemon (and the network between here &
+/// there). [`RateLimitedTransport`] wraps another [`Transport`] & enforces a [token bucket] limit of
+/// `max_per_interval` messages per `interval`: the bucket starts full & refills continuously at
+/// that rate, and each message sent consumes a token.
+///
+/// Messages sent while the bucket is empty are **silently discarded**-- `send` returns `Ok(())`.
+/// The only record of them is the counter returned by [`dropped`](RateLimitedTransport::dropped);
+/// callers who care will need to inspect it.
+///
+/// [token bucket]: https://en.wikipedia.org/wiki/Token_bucket
+pub struct RateLimitedTransport<T> {
+    inner: T,
+    max_per_interval: u32,
+    interval: Duration,
+    bucket: Mutex<TokenBucket>,
+    dropped: AtomicU64,
+}
+
+impl<T> RateLimitedTransport<T> {
+    /// Wrap `inner` so that no more than `max_per_interval` messages are sent per `interval`
+    ///
+    /// A zero `interval` disables rate-limiting altogether. A zero `max_per_interval`, on the other
+    /// hand, is a bucket that never fills: *every* message will be discarded (which may be useful
+    /// for muting a transport, but is more likely a configuration error).
+    pub fn new(inner: T, max_per_interval: u32, interval: Duration) -> RateLimitedTransport<T> {
+        RateLimitedTransport {
+            inner,
+            max_per_interval,
+            interval,
+            bucket: Mutex::new(TokenBucket {
+                tokens: max_per_interval as f64,
+                last_refill: Instant::now(),
+            }),
+            dropped: AtomicU64::new(0),
+        }
+    }
+    /// The number of messages discarded so far for exceeding the rate limit
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    /// Attempt to take a token from the bucket, refilling it first; return true on success
+    fn take_token(&self) -> bool {
+        if self.interval.is_zero() {
+            return true;
+        }
+        let mut bucket = self.bucket.lock().unwrap_or_else(|err| err.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill);
+        let max = self.max_per_interval as f64;
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() / self.interval.as_secs_f64() * max).min(max);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+    /// Put back a token taken for a message that wasn't, in the end, sent
+    fn return_token(&self) {
+        if !self.interval.is_zero() {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|err| err.into_inner());
+            bucket.tokens = (bucket.tokens + 1.0).min(self.max_per_interval as f64);
+        }
+    }
+}
+
+impl<F, T> Transport<F> for RateLimitedTransport<T>
+where
+    F: SyslogFormatter,
+    T: Transport<F>,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        if self.take_token() {
+            self.inner.send(buf)
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        if self.take_token() {
+            let rsp = self.inner.send_slice(buf);
+            if rsp.is_none() {
+                // The caller will come back through `send()`, which will take another
+                self.return_token();
+            }
+            rsp
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            Some(Ok(()))
+        }
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                        Retrying Transport                                      //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Retrying some other [`Transport`] with exponential backoff
+///
+/// [`RetryTransport`] wraps another [`Transport`] & re-tries failed sends up to `max_retries` times,
+/// sleeping `base`, then `2 * base`, then `4 * base` & so on between attempts. Only I/O errors are
+/// retried; once the retries are exhausted, the last error is returned.
+///
+/// Since the [`Layer`] is synchronous, the sleeping is done on the thread that emitted the event.
+/// Note also that this can only help when `send` itself fails: a datagram transport (UDP, say) will
+/// generally report success whether or not the message is ever delivered.
+///
+/// Nor will retrying help a stream transport ([`TcpTransport`], say) whose connection has broken:
+/// every subsequent send on that connection fails just the same. To survive a restart of the
+/// daemon, wrap such a transport in a [`LazyTransport`] (which re-connects after a failed send),
+/// & *that* in a [`RetryTransport`]:
+///
+/// ```rust
+/// use tracing_rfc_5424::{
+///     layer::Layer,
+///     transport::{LazyTransport, RetryTransport, TcpTransport},
+/// };
+/// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+/// use std::time::Duration;
+/// let transport = RetryTransport::new(
+///     LazyTransport::new(|| {
+///         TcpTransport::builder("localhost:514")?
+///             .connect_timeout(Duration::from_millis(250))
+///             .build()
+///     }),
+///     3,
+///     Duration::from_millis(10),
+/// );
+/// let subscriber = Registry::default().with(Layer::with_transport(transport));
+/// ```
+///
+/// [`Layer`]: crate::layer::Layer
+pub struct RetryTransport<T> {
+    inner: T,
+    max_retries: u32,
+    base: Duration,
+}
+
+impl<T> RetryTransport<T> {
+    /// Wrap `inner` so that failed sends are retried up to `max_retries` times, backing-off
+    /// exponentially from `base`
+    pub fn new(inner: T, max_retries: u32, base: Duration) -> RetryTransport<T> {
+        RetryTransport {
+            inner,
+            max_retries,
+            base,
+        }
+    }
+}
+
+impl<F, T> Transport<F> for RetryTransport<T>
+where
+    F: SyslogFormatter,
+    F::Output: Clone,
+    T: Transport<F, Error = Error>,
+{
+    type Error = Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        let mut delay = self.base;
+        for _ in 0..self.max_retries {
+            match self.inner.send(buf.clone()) {
+                Err(Error::Io { .. }) => {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                rsp => return rsp,
+            }
+        }
+        self.inner.send(buf)
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        let mut delay = self.base;
+        for _ in 0..self.max_retries {
+            match self.inner.send_slice(buf)? {
+                Err(Error::Io { .. }) => {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                rsp => return Some(rsp),
+            }
+        }
+        self.inner.send_slice(buf)
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                         Lazy Transport                                         //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Deferring construction of some other [`Transport`] until the first send
+///
+/// Connection-oriented transports ([`TcpTransport`], say) connect when they're constructed, so if
+/// the syslog daemon isn't up yet, neither can the [`Layer`] be. [`LazyTransport`] instead holds
+/// onto a function that constructs the inner [`Transport`], & calls it on the first send. Should
+/// that fail, the send fails with its error & the next send will try again, so construction of the
+/// [`LazyTransport`] itself is infallible & the application may start regardless.
+///
+/// Should a send on the inner [`Transport`] subsequently fail, it is discarded, & the next send
+/// will construct a new one; so a connection broken by (say) a restart of the daemon is
+/// re-established. Pair this with a [`RetryTransport`] to have the message whose send failed
+/// go out on the new connection.
+///
+/// `connect` is called on the thread that emitted the event (though not while holding any lock
+/// that would block other sends), so it should bound the time it takes; for TCP, use a
+/// [`TcpTransportBuilder`] with a [`connect_timeout`](TcpTransportBuilder::connect_timeout):
+///
+/// ```rust
+/// use tracing_rfc_5424::{layer::Layer, transport::{LazyTransport, TcpTransport}};
+/// use tracing_subscriber::{layer::SubscriberExt, registry::Registry};
+/// use std::time::Duration;
+/// let subscriber = Registry::default().with(Layer::with_transport(LazyTransport::new(|| {
+///     TcpTransport::builder("localhost:514")?
+///         .connect_timeout(Duration::from_millis(250))
+///         .build()
+/// })));
+/// ```
+///
+/// [`Layer`]: crate::layer::Layer
+pub struct LazyTransport<T, C> {
+    connect: C,
+    inner: std::sync::RwLock<Option<Arc<T>>>,
+}
+
+impl<T, C> LazyTransport<T, C> {
+    /// Construct a [`Transport`] that will invoke `connect` to construct the [`Transport`] to
+    /// which it delegates, on the first send
+    pub fn new(connect: C) -> LazyTransport<T, C> {
+        LazyTransport {
+            connect,
+            inner: std::sync::RwLock::new(None),
+        }
+    }
+    /// Return true if the inner [`Transport`] has been constructed
+    pub fn is_connected(&self) -> bool {
+        self.current().is_some()
+    }
+    /// Return our inner [`Transport`], if we have one
+    fn current(&self) -> Option<Arc<T>> {
+        // A panic while holding the lock can't leave the `Option` half-written.
+        self.inner
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+    /// Return our inner [`Transport`], constructing it first if need be
+    fn connected<E>(&self) -> std::result::Result<Arc<T>, E>
+    where
+        C: Fn() -> std::result::Result<T, E>,
+    {
+        if let Some(inner) = self.current() {
+            return Ok(inner);
+        }
+        // Connect without holding the lock, so that other threads' sends aren't blocked behind
+        // us should the daemon be slow to answer...
+        let inner = Arc::new((self.connect)()?);
+        let mut guard = self
+            .inner
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // but that means another thread may have beaten us to it, in which case we defer to it.
+        Ok(guard.get_or_insert(inner).clone())
+    }
+    /// Discard `inner` (should it still be current) after a failed send, so that the next send
+    /// will re-connect
+    fn disconnect(&self, inner: &Arc<T>) {
+        let mut guard = self
+            .inner
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if matches!(guard.as_ref(), Some(current) if Arc::ptr_eq(current, inner)) {
+            *guard = None;
+        }
+    }
+    /// Note the outcome `rsp` of an operation on `inner`, disconnecting if it failed
+    fn check<R, E>(
+        &self,
+        inner: &Arc<T>,
+        rsp: std::result::Result<R, E>,
+    ) -> std::result::Result<R, E> {
+        if rsp.is_err() {
+            self.disconnect(inner);
+        }
+        rsp
+    }
+}
+
+// The `connect` closure can't be printed, and the inner transport needn't be `Debug`
+impl<T, C> std::fmt::Debug for LazyTransport<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("LazyTransport")
+            .field("connected", &self.is_connected())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, T, C> Transport<F> for LazyTransport<T, C>
+where
+    F: SyslogFormatter,
+    T: Transport<F>,
+    C: Fn() -> std::result::Result<T, T::Error>,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        let inner = self.connected()?;
+        self.check(&inner, inner.send(buf))
+    }
+    fn send_slice(&self, buf: &[u8]) -> Option<std::result::Result<(), Self::Error>> {
+        let inner = match self.connected() {
+            Ok(inner) => inner,
+            Err(err) => return Some(Err(err)),
+        };
+        inner.send_slice(buf).map(|rsp| self.check(&inner, rsp))
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        // Nothing to flush if we're not connected
+        match self.current() {
+            Some(inner) => self.check(&inner, inner.flush()),
+            None => Ok(()),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                       Buffered Transport                                       //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// State shared between a [`BufferedTransport`] & its flush thread (if any)
+struct BufferShared<O, T> {
+    inner: T,
+    // Messages are sent to `inner` while this lock is held, so that they go out in order.
+    pending: Mutex<Vec<O>>,
+    // Set when the flush thread is to exit
+    stop: Mutex<bool>,
+    wake: std::sync::Condvar,
+}
+
+impl<O, T> BufferShared<O, T> {
+    /// Send all pending messages on `inner`
+    ///
+    /// Messages that fail to send are discarded; the first such error is returned once all have
+    /// been attempted.
+    fn flush<F>(&self) -> std::result::Result<(), T::Error>
+    where
+        F: SyslogFormatter<Output = O>,
+        T: Transport<F>,
+    {
+        let mut pending = self.pending.lock().unwrap_or_else(|err| err.into_inner());
+        let mut first_err = None;
+        for buf in pending.drain(..) {
+            if let Err(err) = self.inner.send(buf) {
+                first_err.get_or_insert(err);
+            }
+        }
+        match first_err {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Batching messages up before handing them to some other [`Transport`]
+///
+/// [`BufferedTransport`] wraps another [`Transport`] & holds messages until `max_messages` have
+/// accumulated, at which point they're all sent (in order). Messages are also sent when
+/// [`Transport::flush`] is called, and when the [`BufferedTransport`] is dropped.
+///
+/// On its own, this means that a message logged during a quiet period may sit in the buffer
+/// indefinitely. [`with_flush_interval`](BufferedTransport::with_flush_interval) starts a
+/// background thread that flushes the buffer periodically as well; that thread is stopped (and
+/// joined) when the [`BufferedTransport`] is dropped.
+///
+/// Errors encountered while sending buffered messages are reported by whichever call triggered
+/// the flush (or, in the case of the background thread & [`Drop`], not at all); the failed messages
+/// are discarded either way.
+pub struct BufferedTransport<F: SyslogFormatter, T: Transport<F>> {
+    shared: Arc<BufferShared<F::Output, T>>,
+    max_messages: usize,
+    flusher: Option<std::thread::JoinHandle<()>>,
+}
+
+impl<F: SyslogFormatter, T: Transport<F>> BufferedTransport<F, T> {
+    /// Wrap `inner` so that messages are sent in batches of `max_messages`
+    pub fn new(inner: T, max_messages: usize) -> BufferedTransport<F, T> {
+        BufferedTransport {
+            shared: Arc::new(BufferShared {
+                inner,
+                pending: Mutex::new(Vec::with_capacity(max_messages)),
+                stop: Mutex::new(false),
+                wake: std::sync::Condvar::new(),
+            }),
+            max_messages,
+            flusher: None,
+        }
+    }
+    /// Also flush the buffer every `interval`, on a background thread
+    ///
+    /// If the thread can't be spawned, this [`BufferedTransport`] will simply carry on flushing
+    /// when full (or when asked).
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self
+    where
+        F: 'static,
+        F::Output: Send + 'static,
+        T: Send + Sync + 'static,
+    {
+        self.stop_flusher();
+        let shared = self.shared.clone();
+        self.flusher = std::thread::Builder::new()
+            .name("syslog-flush".to_string())
+            .spawn(move || {
+                let mut stop = shared.stop.lock().unwrap_or_else(|err| err.into_inner());
+                while !*stop {
+                    stop = shared
+                        .wake
+                        .wait_timeout(stop, interval)
+                        .unwrap_or_else(|err| err.into_inner())
+                        .0;
+                    if !*stop {
+                        // There's no one to whom we can report a failure.
+                        let _ = shared.flush::<F>();
+                    }
+                }
+            })
+            .ok();
+        self
+    }
+    /// Signal the flush thread (if any) to exit, and wait for it to do so
+    fn stop_flusher(&mut self) {
+        if let Some(flusher) = self.flusher.take() {
+            *self
+                .shared
+                .stop
+                .lock()
+                .unwrap_or_else(|err| err.into_inner()) = true;
+            self.shared.wake.notify_all();
+            let _ = flusher.join();
+            *self
+                .shared
+                .stop
+                .lock()
+                .unwrap_or_else(|err| err.into_inner()) = false;
+        }
+    }
+}
+
+// Neither the inner transport nor the flush thread's handle need be `Debug`
+impl<F: SyslogFormatter, T: Transport<F>> std::fmt::Debug for BufferedTransport<F, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BufferedTransport")
+            .field("max_messages", &self.max_messages)
+            .field(
+                "pending",
+                &self
+                    .shared
+                    .pending
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .len(),
+            )
+            .field("timed_flush", &self.flusher.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, T> Transport<F> for BufferedTransport<F, T>
+where
+    F: SyslogFormatter,
+    T: Transport<F>,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        let full = {
+            let mut pending = self
+                .shared
+                .pending
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            pending.push(buf);
+            pending.len() >= self.max_messages
+        };
+        if full {
+            self.shared.flush::<F>()
+        } else {
+            Ok(())
+        }
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        self.shared.flush::<F>()?;
+        self.shared.inner.flush()
+    }
+}
+
+impl<F: SyslogFormatter, T: Transport<F>> Drop for BufferedTransport<F, T> {
+    fn drop(&mut self) {
+        self.stop_flusher();
+        // There's no one to whom we can report a failure at this point.
+        let _ = self.shared.flush::<F>();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                        Channel Transport                                       //
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// What a [`ChannelTransport`] does with a message sent while its queue is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait for the background thread to make room
+    Block,
+    /// Discard the message being sent
+    DropNewest,
+    /// Discard the oldest message in the queue to make room for the one being sent
+    DropOldest,
+}
+
+/// The queue shared between a [`ChannelTransport`] & its background thread
+struct ChannelQueue<O> {
+    messages: std::collections::VecDeque<O>,
+    // Set while the background thread is sending a message it has taken off the queue
+    busy: bool,
+    // Set when the background thread is to exit (once it has drained the queue)
+    stop: bool,
+    // Set by the background thread as it exits (whether because it was told to, or because the
+    // inner transport panicked)
+    dead: bool,
+}
+
+/// State shared between a [`ChannelTransport`] & its background thread
+struct ChannelShared<O, T> {
+    inner: T,
+    queue: Mutex<ChannelQueue<O>>,
+    // Signalled whenever `queue` changes
+    wake: std::sync::Condvar,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Clears [`ChannelQueue::busy`] once the background thread is done with a message, or marks it
+/// [`dead`](ChannelQueue::dead) once the thread exits; either way, waiters are woken. Done on drop,
+/// so that it happens even should the inner transport panic.
+struct ChannelGuard<'a, O, T> {
+    shared: &'a ChannelShared<O, T>,
+    exit: bool,
+}
+
+impl<O, T> Drop for ChannelGuard<'_, O, T> {
+    fn drop(&mut self) {
+        {
+            let mut queue = self.shared.queue();
+            queue.busy = false;
+            // Should the inner transport panic, the thread is as good as dead already; say so
+            // before waking anyone, lest they see the queue idle & take it for drained.
+            queue.dead |= self.exit || std::thread::panicking();
+        }
+        self.shared.wake.notify_all();
+    }
+}
+
+impl<O, T> ChannelShared<O, T> {
+    fn queue(&self) -> std::sync::MutexGuard<'_, ChannelQueue<O>> {
+        self.queue.lock().unwrap_or_else(|err| err.into_inner())
+    }
+    /// Send messages on `inner` as they arrive, until told to stop & the queue is empty
+    fn drain<F>(&self)
+    where
+        F: SyslogFormatter<Output = O>,
+        T: Transport<F>,
+    {
+        let _exit = ChannelGuard {
+            shared: self,
+            exit: true,
+        };
+        loop {
+            let buf = {
+                let mut queue = self.queue();
+                loop {
+                    if let Some(buf) = queue.messages.pop_front() {
+                        queue.busy = true;
+                        break buf;
+                    }
+                    if queue.stop {
+                        return;
+                    }
+                    queue = self.wake.wait(queue).unwrap_or_else(|err| err.into_inner());
+                }
+            };
+            self.wake.notify_all();
+            let _busy = ChannelGuard {
+                shared: self,
+                exit: false,
+            };
+            if self.inner.send(buf).is_err() {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// The error returned by a [`ChannelTransport`] whose background thread has exited
+fn channel_dead() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "the syslog-send thread has exited",
+    )
+}
+
+/// Handing messages off to some other [`Transport`] on a background thread
+///
+/// The [`Layer`] is synchronous: each event is formatted & sent on the thread that emitted it. For
+/// transports that may block ([`TcpTransport`] against a slow daemon, say), that puts I/O on the
+/// application's hot path. [`ChannelTransport`] instead pushes each message onto a bounded queue &
+/// returns; a dedicated thread (which owns the inner [`Transport`]) takes them off the queue & sends
+/// them, in order.
+///
+/// What happens when the queue is full is governed by an [`Overflow`] policy. Messages discarded
+/// under [`Overflow::DropNewest`] or [`Overflow::DropOldest`] are counted by
+/// [`dropped`](ChannelTransport::dropped); since the inner transport's errors happen on the
+/// background thread, they can't be returned to the caller & are counted by
+/// [`failed`](ChannelTransport::failed) instead. Note that, as far as the [`Layer`] is concerned, a
+/// message has been sent once it's queued.
+///
+/// [`Transport::flush`] waits for the queue to drain before flushing the inner transport. When the
+/// [`ChannelTransport`] is dropped, any messages still queued are sent before the background
+/// thread is joined.
+///
+/// Should the inner transport panic, the background thread exits; any messages still queued are
+/// lost, and subsequent sends & flushes fail with an I/O error (of kind
+/// [`BrokenPipe`](std::io::ErrorKind::BrokenPipe)) rather than waiting on a thread that's gone.
+///
+/// [`Layer`]: crate::layer::Layer
+pub struct ChannelTransport<F: SyslogFormatter, T: Transport<F>> {
+    shared: Arc<ChannelShared<F::Output, T>>,
+    capacity: usize,
+    overflow: Overflow,
+    sender: Option<std::thread::JoinHandle<()>>,
+}
+
+impl<F: SyslogFormatter, T: Transport<F>> ChannelTransport<F, T> {
+    /// Start a background thread that will send messages on `inner`, queueing at most `capacity`
+    /// of them & handling any more according to `overflow`
+    pub fn new(inner: T, capacity: usize, overflow: Overflow) -> Result<ChannelTransport<F, T>>
+    where
+        F: 'static,
+        F::Output: Send + 'static,
+        T: Send + Sync + 'static,
+    {
+        let capacity = capacity.max(1);
+        let shared = Arc::new(ChannelShared {
+            inner,
+            queue: Mutex::new(ChannelQueue {
+                messages: std::collections::VecDeque::with_capacity(capacity),
+                busy: false,
+                stop: false,
+                dead: false,
+            }),
+            wake: std::sync::Condvar::new(),
+            dropped: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        });
+        let sender = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("syslog-send".to_string())
+                .spawn(move || shared.drain::<F>())?
+        };
+        Ok(ChannelTransport {
+            shared,
+            capacity,
+            overflow,
+            sender: Some(sender),
+        })
+    }
+    /// The number of messages discarded so far because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+    /// The number of messages the inner [`Transport`] has failed to send so far
+    pub fn failed(&self) -> u64 {
+        self.shared.failed.load(Ordering::Relaxed)
+    }
+}
+
+// Neither the inner transport nor the background thread's handle need be `Debug`
+impl<F: SyslogFormatter, T: Transport<F>> std::fmt::Debug for ChannelTransport<F, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ChannelTransport")
+            .field("capacity", &self.capacity)
+            .field("overflow", &self.overflow)
+            .field("queued", &self.shared.queue().messages.len())
+            .field("dropped", &self.dropped())
+            .field("failed", &self.failed())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, T> Transport<F> for ChannelTransport<F, T>
+where
+    F: SyslogFormatter,
+    T: Transport<F>,
+    T::Error: From<std::io::Error>,
+{
+    type Error = T::Error;
+    fn send(&self, buf: F::Output) -> std::result::Result<(), Self::Error> {
+        let mut queue = self.shared.queue();
+        while !queue.dead && queue.messages.len() >= self.capacity {
+            match self.overflow {
+                Overflow::Block => {
+                    queue = self
+                        .shared
+                        .wake
+                        .wait(queue)
+                        .unwrap_or_else(|err| err.into_inner())
+                }
+                Overflow::DropNewest => {
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Overflow::DropOldest => {
+                    queue.messages.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if queue.dead {
+            return Err(channel_dead().into());
+        }
+        queue.messages.push_back(buf);
+        drop(queue);
+        self.shared.wake.notify_all();
+        Ok(())
+    }
+    fn flush(&self) -> std::result::Result<(), Self::Error> {
+        let mut queue = self.shared.queue();
+        while !queue.dead && (!queue.messages.is_empty() || queue.busy) {
+            queue = self
+                .shared
+                .wake
+                .wait(queue)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        if queue.dead {
+            return Err(channel_dead().into());
+        }
+        drop(queue);
+        self.shared.inner.flush()
+    }
+}
+
+impl<F: SyslogFormatter, T: Transport<F>> Drop for ChannelTransport<F, T> {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            self.shared.queue().stop = true;
+            self.shared.wake.notify_all();
+            let _ = sender.join();
+        }
+        // There's no one to whom we can report a failure at this point.
+        let _ = self.shared.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::rfc5424::Rfc5424;
+
+    use std::io::Read;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn tcp_builder() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let transport = TcpTransport::new(addr).unwrap();
+        assert!(!transport.socket.nodelay().unwrap());
+
+        let transport = TcpTransport::builder(addr)
+            .unwrap()
+            .nodelay(true)
+            .keepalive(Duration::from_secs(60))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        assert!(transport.socket.nodelay().unwrap());
+        let sock = socket2::SockRef::from(&transport.socket);
+        assert!(sock.keepalive().unwrap());
+    }
+
+    #[test]
+    fn tcp_flush() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport = TcpTransport::new(listener.local_addr().unwrap()).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+
+        Transport::<Rfc5424>::send(&transport, b"Hello, world!".to_vec()).unwrap();
+        Transport::<Rfc5424>::flush(&transport).unwrap();
+
+        let mut buf = [0u8; 14];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello, world!\n");
+    }
+
+    #[test]
+    fn tcp_pool() {
+        use std::io::BufRead;
+
+        const POOL_SIZE: usize = 3;
+        const THREADS: usize = 8;
+        const MESSAGES: usize = 50;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport =
+            Arc::new(PooledTcpTransport::new(listener.local_addr().unwrap(), POOL_SIZE).unwrap());
+        assert_eq!(transport.size(), POOL_SIZE);
+
+        // Read each connection on its own thread, until the transport hangs up
+        let readers = (0..POOL_SIZE)
+            .map(|_| {
+                let (peer, _) = listener.accept().unwrap();
+                std::thread::spawn(move || {
+                    std::io::BufReader::new(peer)
+                        .lines()
+                        .collect::<std::io::Result<Vec<String>>>()
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let senders = (0..THREADS)
+            .map(|thread| {
+                let transport = transport.clone();
+                std::thread::spawn(move || {
+                    for i in 0..MESSAGES {
+                        Transport::<Rfc5424>::send(
+                            &*transport,
+                            format!("thread {} message {}", thread, i).into_bytes(),
+                        )
+                        .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        drop(transport);
+
+        let mut received = readers
+            .into_iter()
+            .flat_map(|reader| reader.join().unwrap())
+            .collect::<Vec<_>>();
+        received.sort();
+        let mut expected = (0..THREADS)
+            .flat_map(|thread| {
+                (0..MESSAGES).map(move |i| format!("thread {} message {}", thread, i))
+            })
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(received, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_stream_try_default() {
+        // This used to hand back a (datagram) `UnixSocket`
+        let _: fn() -> Result<UnixSocketStream> = UnixSocketStream::try_default;
+        // There may or may not be a daemon listening on /dev/log (let alone for streams), but if
+        // not, we should at least have tried to connect there.
+        if let Err(err) = UnixSocketStream::try_default() {
+            assert!(matches!(err, Error::Connect { ref addr, .. } if addr == "/dev/log"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_stream_flush() {
+        let dir =
+            std::env::temp_dir().join(format!("tracing-rfc-5424-flush-{}", std::process::id()));
+        let _ = std::fs::remove_file(&dir);
+        let listener = std::os::unix::net::UnixListener::bind(&dir).unwrap();
+        let transport = UnixSocketStream::new(&dir)
+            .unwrap()
+            .with_write_timeout(Duration::from_millis(50))
+            .unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+
+        Transport::<Rfc5424>::send(&transport, b"Hello, world!".to_vec()).unwrap();
+        // Until the peer reads the message, the flush can't complete
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert!(matches!(
+            Transport::<Rfc5424>::flush(&transport),
+            Err(Error::Io { ref source, .. }) if source.kind() == std::io::ErrorKind::TimedOut
+        ));
+
+        let mut buf = [0u8; 14];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello, world!\n");
+        Transport::<Rfc5424>::flush(&transport).unwrap();
+
+        let _ = std::fs::remove_file(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket_with_bind() {
+        let dir = std::env::temp_dir();
+        let server = dir.join(format!("tracing-rfc-5424-bind-srv-{}", std::process::id()));
+        let client = dir.join(format!("tracing-rfc-5424-bind-cli-{}", std::process::id()));
+        let _ = std::fs::remove_file(&server);
+        let listener = UnixDatagram::bind(&server).unwrap();
+
+        // Leave a stale socket file at the client path, as a crashed process would
+        drop(UnixDatagram::bind(&client).unwrap());
+        assert!(client.exists());
+
+        let transport = UnixSocket::with_bind(&client, &server).unwrap();
+        // A live socket at the bind path, however, mustn't be clobbered
+        assert!(matches!(
+            UnixSocket::with_bind(&client, &server),
+            Err(Error::Bind { ref addr, .. }) if addr == &client.display().to_string()
+        ));
+
+        Transport::<Rfc5424>::send(&transport, b"Hello, world!".to_vec()).unwrap();
+        let mut buf = [0u8; 32];
+        let (n, peer) = listener.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"Hello, world!");
+        assert_eq!(peer.as_pathname(), Some(client.as_path()));
+
+        // The socket file goes away with the last clone
+        let clone = transport.try_clone().unwrap();
+        drop(transport);
+        assert!(client.exists());
+        drop(clone);
+        assert!(!client.exists());
+
+        let _ = std::fs::remove_file(&server);
+    }
+
+    #[test]
+    fn hostname_from_local_addr() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport = TcpTransport::new(listener.local_addr().unwrap()).unwrap();
+        let hostname = crate::rfc5424::Hostname::from_local_addr(transport.stream()).unwrap();
+        assert_eq!(
+            hostname.to_string(),
+            transport.stream().local_addr().unwrap().ip().to_string()
+        );
+        assert_eq!(hostname.to_string(), "127.0.0.1");
+    }
+
+    #[test]
+    fn connect_error_names_destination() {
+        // Grab a port on which no one is listening
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let err = TcpTransport::new(addr).unwrap_err();
+        assert!(matches!(err, Error::Connect { .. }));
+        assert!(format!("{}", err).contains(&addr.to_string()));
+
+        #[cfg(unix)]
+        {
+            let err = UnixSocketStream::new("/no/such/syslog/socket").unwrap_err();
+            assert!(format!("{}", err).contains("/no/such/syslog/socket"));
+        }
+    }
+
+    #[test]
+    fn bind_error_names_local_address() {
+        // 192.0.2.0/24 is reserved for documentation, so won't be assigned to any local interface
+        let interface = std::net::Ipv4Addr::new(192, 0, 2, 1);
+        let err =
+            UdpTransport::multicast(std::net::Ipv4Addr::new(239, 255, 42, 99), 5514, interface)
+                .unwrap_err();
+        assert!(matches!(err, Error::Bind { ref addr, .. } if addr == "192.0.2.1:0"));
+        let text = format!("{}", err);
+        assert!(text.contains("192.0.2.1:0"));
+        assert!(!text.contains("239.255.42.99"));
+
+        #[cfg(unix)]
+        {
+            let err = UnixSocket::with_bind("/no/such/dir/client.s", "/no/such/syslog/socket")
+                .unwrap_err();
+            assert!(format!("{}", err).contains("/no/such/dir/client.s"));
+        }
+    }
+
+    // Joining a group requires a multicast-capable interface, which isn't a given in every test
+    // environment (containers, e.g.); run with `cargo test -- --ignored` where one is available.
+    #[test]
+    #[ignore = "requires a multicast-capable network interface"]
+    fn udp_multicast() {
+        let group = std::net::Ipv4Addr::new(239, 255, 42, 99);
+        let transport =
+            UdpTransport::multicast(group, 5514, std::net::Ipv4Addr::UNSPECIFIED).unwrap();
+        transport.set_multicast_ttl(4).unwrap();
+        assert_eq!(transport.socket.multicast_ttl_v4().unwrap(), 4);
+        // We can only leave a group of which we're a member
+        transport
+            .socket
+            .leave_multicast_v4(&group, &std::net::Ipv4Addr::UNSPECIFIED)
+            .unwrap();
+    }
+
+    #[test]
+    fn udp_multi() {
+        let peers = [
+            std::net::UdpSocket::bind("127.0.0.1:0").unwrap(),
+            std::net::UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        let transport = UdpTransport::new_multi(&[
+            peers[0].local_addr().unwrap(),
+            peers[1].local_addr().unwrap(),
+        ])
+        .unwrap();
+
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+
+        assert!(UdpTransport::new_multi::<std::net::SocketAddr>(&[]).is_err());
+
+        for peer in &peers {
+            let mut buf = [0u8; 16];
+            let (n, from) = peer.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..n], b"Hello");
+            assert_eq!(from.port(), transport.socket.local_addr().unwrap().port());
+        }
+    }
+
+    #[test]
+    fn udp_try_clone() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transport = UdpTransport::new(peer.local_addr().unwrap()).unwrap();
+        let clone = transport.try_clone().unwrap();
+
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        Transport::<Rfc5424>::send(&clone, b"world".to_vec()).unwrap();
+
+        let mut buf = [0u8; 16];
+        let n = peer.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"Hello");
+        let n = peer.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"world");
+    }
+
+    #[test]
+    fn udp_message_too_large() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transport = UdpTransport::new(peer.local_addr().unwrap())
+            .unwrap()
+            .with_confirmation();
+        match Transport::<Rfc5424>::send(&transport, vec![b'x'; MAX_UDP_PAYLOAD + 1]) {
+            Err(Error::MessageTooLarge { len, max, .. }) => {
+                assert_eq!(len, MAX_UDP_PAYLOAD + 1);
+                assert_eq!(max, MAX_UDP_PAYLOAD);
+            }
+            other => panic!("expected MessageTooLarge, got {:?}", other),
+        }
+        // Nothing should have reached the socket
+        assert_eq!(transport.bytes_sent(), Some(0));
+
+        let transport = transport.with_max_datagram_size(16);
+        assert!(matches!(
+            Transport::<Rfc5424>::send(&transport, vec![b'x'; 17]),
+            Err(Error::MessageTooLarge {
+                len: 17,
+                max: 16,
+                ..
+            })
+        ));
+        Transport::<Rfc5424>::send(&transport, vec![b'x'; 16]).unwrap();
+    }
+
+    /// Read one RELP frame; return the transaction number, command & data
+    #[cfg(feature = "relp")]
+    fn read_frame<R: std::io::BufRead>(reader: &mut R) -> Option<(String, String, Vec<u8>)> {
+        let mut header = Vec::new();
+        for _ in 0..2 {
+            reader.read_until(b' ', &mut header).ok()?;
+        }
+        let header = String::from_utf8(header).ok()?;
+        let mut fields = header.split_whitespace();
+        let (txnr, command) = (fields.next()?.to_string(), fields.next()?.to_string());
+        let mut datalen = String::new();
+        let mut byte = [0u8; 1];
+        loop {
+            reader.read_exact(&mut byte).ok()?;
+            if byte[0] == b' ' || byte[0] == b'\n' {
+                break;
+            }
+            datalen.push(byte[0] as char);
+        }
+        let mut data = vec![0u8; datalen.parse().ok()?];
+        if byte[0] == b' ' {
+            reader.read_exact(&mut data).ok()?;
+            reader.read_exact(&mut byte).ok()?;
+        }
+        Some((txnr, command, data))
+    }
+
+    #[cfg(feature = "relp")]
+    #[test]
+    fn relp() {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = std::io::BufReader::new(stream);
+            let mut frames = Vec::new();
+            while let Some((txnr, command, data)) = read_frame(&mut reader) {
+                let rsp = match command.as_str() {
+                    "open" => "200 OK\nrelp_version=0\ncommands=syslog".to_string(),
+                    "syslog" if data.ends_with(b"reject me") => "500 not today".to_string(),
+                    "syslog" => "200 OK".to_string(),
+                    _ => String::new(),
+                };
+                write!(writer, "{} rsp {}", txnr, rsp.len()).unwrap();
+                if !rsp.is_empty() {
+                    write!(writer, " {}", rsp).unwrap();
+                }
+                writer.write_all(b"\n").unwrap();
+                let close = command == "close";
+                frames.push((txnr, command, data));
+                if close {
+                    writer.write_all(b"0 serverclose 0\n").unwrap();
+                    break;
+                }
+            }
+            frames
+        });
+
+        let transport = RelpTransport::new(addr).unwrap();
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(
+            Transport::<Rfc5424>::send_slice(&transport, b"World").map(|rsp| rsp.is_ok()),
+            Some(true)
+        );
+        match Transport::<Rfc5424>::send(&transport, b"reject me".to_vec()) {
+            Err(Error::Rejected { status, .. }) => assert_eq!(status, "500 not today"),
+            rsp => panic!("expected a rejection, got {:?}", rsp),
+        }
+        drop(transport);
+
+        let frames = server.join().unwrap();
+        let summary = frames
+            .iter()
+            .map(|(txnr, command, _)| format!("{} {}", txnr, command))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            summary,
+            vec!["1 open", "2 syslog", "3 syslog", "4 syslog", "5 close"]
+        );
+        assert!(frames[0].2.starts_with(b"relp_version=0\n"));
+        assert_eq!(frames[1].2, b"Hello");
+        assert_eq!(frames[2].2, b"World");
+    }
+
+    #[cfg(feature = "relp")]
+    #[test]
+    fn relp_close() {
+        use std::io::Write;
+
+        // A server that opens the session, then answers nothing else; return the commands it saw
+        fn serve(listener: std::net::TcpListener) -> std::thread::JoinHandle<Vec<String>> {
+            std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let mut writer = stream.try_clone().unwrap();
+                let mut reader = std::io::BufReader::new(stream);
+                let mut commands = Vec::new();
+                while let Some((txnr, command, _)) = read_frame(&mut reader) {
+                    if command == "open" {
+                        let rsp = "200 OK\nrelp_version=0\ncommands=syslog";
+                        writeln!(writer, "{} rsp {} {}", txnr, rsp.len(), rsp).unwrap();
+                    }
+                    commands.push(command);
+                }
+                commands
+            })
+        }
+
+        // Dropping the transport shouldn't hang waiting on the server to acknowledge the close...
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve(listener);
+        let transport = RelpTransport::new(addr);
+        let start = Instant::now();
+        drop(transport.unwrap());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.join().unwrap(), vec!["open", "close"]);
+
+        // nor attempt the close at all once the session has failed.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve(listener);
+        let transport = RelpTransport::new(addr);
+        let transport = transport
+            .unwrap()
+            .with_ack_timeout(Duration::from_millis(10))
+            .unwrap();
+        assert!(matches!(
+            Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()),
+            Err(Error::Io { .. })
+        ));
+        drop(transport);
+        assert_eq!(server.join().unwrap(), vec!["open", "syslog"]);
+    }
+
+    #[test]
+    fn lazy() {
+        // Find a port on which nothing is listening
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let transport = LazyTransport::new(move || TcpTransport::new(addr));
+        assert!(!transport.is_connected());
+        assert!(format!("{:?}", transport).contains("connected: false"));
+        assert!(Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).is_err());
+        assert!(!transport.is_connected());
+
+        let listener = std::net::TcpListener::bind(addr).unwrap();
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert!(transport.is_connected());
+        assert!(format!("{:?}", transport).contains("connected: true"));
+        assert_eq!(
+            Transport::<Rfc5424>::send_slice(&transport, b"world").map(|rsp| rsp.is_ok()),
+            Some(true)
+        );
+        let (mut peer, _) = listener.accept().unwrap();
+        drop(transport);
+        let mut text = String::new();
+        peer.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Hello\nworld\n");
+    }
+
+    /// A [`Transport`] standing-in for a connection, whose sends fail once it's broken
+    struct FragileTransport {
+        broken: Arc<AtomicBool>,
+    }
+
+    impl Transport<Rfc5424> for FragileTransport {
+        type Error = Error;
+        fn send(&self, _buf: Vec<u8>) -> std::result::Result<(), Self::Error> {
+            if self.broken.load(Ordering::Relaxed) {
+                Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe).into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Return a function that makes [`FragileTransport`]s, a count of the calls to it & a handle
+    /// on the most recent connection
+    #[allow(clippy::type_complexity)]
+    fn fragile_connector() -> (
+        impl Fn() -> Result<FragileTransport>,
+        Arc<AtomicU64>,
+        Arc<Mutex<Arc<AtomicBool>>>,
+    ) {
+        let connects = Arc::new(AtomicU64::new(0));
+        let current = Arc::new(Mutex::new(Arc::new(AtomicBool::new(false))));
+        let (c, cur) = (connects.clone(), current.clone());
+        let connect = move || {
+            c.fetch_add(1, Ordering::Relaxed);
+            let broken = Arc::new(AtomicBool::new(false));
+            *cur.lock().unwrap() = broken.clone();
+            Ok(FragileTransport { broken })
+        };
+        (connect, connects, current)
+    }
+
+    #[test]
+    fn lazy_reconnect() {
+        let (connect, connects, current) = fragile_connector();
+        let transport = LazyTransport::new(connect);
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+
+        // Once the connection breaks, the failed send should discard it...
+        current.lock().unwrap().store(true, Ordering::Relaxed);
+        assert!(Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).is_err());
+        assert!(!transport.is_connected());
+        // & the next send re-connect.
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert!(transport.is_connected());
+        assert_eq!(connects.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn tcp_framing() {
+        for (framing, golden) in [
+            (Framing::NonTransparent, "Hello\nworld!\n"),
+            (Framing::OctetCounting, "5 Hello6 world!"),
+            (Framing::Raw, "Helloworld!"),
+        ] {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let transport = TcpTransport::builder(listener.local_addr().unwrap())
+                .unwrap()
+                .framing(framing)
+                .build()
+                .unwrap();
+            let (mut peer, _) = listener.accept().unwrap();
+            Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+            assert_eq!(
+                Transport::<Rfc5424>::send_slice(&transport, b"world!").map(|rsp| rsp.is_ok()),
+                Some(true)
+            );
+            drop(transport);
+            let mut text = String::new();
+            peer.read_to_string(&mut text).unwrap();
+            assert_eq!(text, golden);
+        }
+
+        // Also selectable on an existing transport
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport =
+            TcpTransport::from_stream(TcpStream::connect(listener.local_addr().unwrap()).unwrap())
+                .with_framing(Framing::OctetCounting);
+        let (mut peer, _) = listener.accept().unwrap();
+        Transport::<Rfc5424>::send(&transport, "你好".as_bytes().to_vec()).unwrap();
+        drop(transport);
+        let mut text = String::new();
+        peer.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "6 你好");
+    }
+
+    #[test]
+    fn adopted_sockets() {
+        let mut buf = [0u8; 64];
+
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(peer.local_addr().unwrap()).unwrap();
+        let transport = UdpTransport::from_socket(socket);
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(peer.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"Hello");
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport =
+            TcpTransport::from_stream(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let (mut peer, _) = listener.accept().unwrap();
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        drop(transport);
+        let mut text = String::new();
+        peer.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Hello\n");
+
+        #[cfg(unix)]
+        {
+            let (local, peer) = UnixDatagram::pair().unwrap();
+            let transport = UnixSocket::from_datagram(local);
+            Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+            assert_eq!(peer.recv(&mut buf).unwrap(), 5);
+            assert_eq!(&buf[..5], b"Hello");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn adopted_fds() {
+        use std::os::unix::io::IntoRawFd;
+        let mut buf = [0u8; 64];
+
+        let (local, peer) = UnixDatagram::pair().unwrap();
+        // Safety: `into_raw_fd` hands us sole ownership of a connected Unix datagram socket
+        let transport = unsafe { UnixSocket::from_raw_fd(local.into_raw_fd()) };
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(peer.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"Hello");
+
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(peer.local_addr().unwrap()).unwrap();
+        // Safety: as above
+        let transport = unsafe { UdpTransport::from_raw_fd(socket.into_raw_fd()) };
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(peer.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"Hello");
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        // Safety: as above
+        let transport = unsafe { TcpTransport::from_raw_fd(stream.into_raw_fd()) };
+        let (mut peer, _) = listener.accept().unwrap();
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        drop(transport);
+        let mut text = String::new();
+        peer.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Hello\n");
+    }
+
+    #[test]
+    fn udp_mtu() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transport = UdpTransport::new(peer.local_addr().unwrap())
+            .unwrap()
+            .with_mtu(576);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let transport = transport.with_dont_fragment(true).unwrap();
+
+        // 576 less 28 bytes of IPv4 & UDP headers
+        Transport::<Rfc5424>::send(&transport, vec![b'x'; 548]).unwrap();
+        let mut buf = [0u8; 2048];
+        assert_eq!(peer.recv(&mut buf).unwrap(), 548);
+        assert!(matches!(
+            Transport::<Rfc5424>::send(&transport, vec![b'x'; 549]),
+            Err(Error::MessageTooLarge { max: 548, .. })
+        ));
+    }
+
+    #[test]
+    fn udp_bytes_sent() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transport = UdpTransport::new(peer.local_addr().unwrap()).unwrap();
+        assert_eq!(transport.bytes_sent(), None);
+
+        let transport = transport.with_confirmation();
+        assert_eq!(transport.bytes_sent(), Some(0));
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(transport.bytes_sent(), Some(5));
+        Transport::<Rfc5424>::send(&transport, b", world!".to_vec()).unwrap();
+        assert_eq!(transport.bytes_sent(), Some(13));
+    }
+
+    #[test]
+    fn writer() {
+        let transport = WriterTransport::new(Vec::new());
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        Transport::<Rfc5424>::send(&transport, b"world".to_vec()).unwrap();
+        Transport::<Rfc5424>::flush(&transport).unwrap();
+        assert_eq!(transport.into_inner(), b"Hello\nworld\n");
+
+        // Messages land in the writer as soon as they're sent, but aren't flushed until asked.
+        let transport = WriterTransport::new(std::io::BufWriter::new(Vec::new()));
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert!(transport.writer.lock().unwrap().get_ref().is_empty());
+        Transport::<Rfc5424>::flush(&transport).unwrap();
+        assert_eq!(transport.into_inner().get_ref(), b"Hello\n");
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn udp_drain() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transport = UdpTransport::new(peer.local_addr().unwrap()).unwrap();
+        for _ in 0..16 {
+            Transport::<Rfc5424>::send(&transport, vec![b'x'; 1024]).unwrap();
+        }
+        assert!(transport.drain(Duration::from_secs(5)).unwrap());
+        assert_eq!(send_queue_len(&transport.socket).unwrap(), 0);
+        // Nothing queued means nothing to wait for
+        let start = Instant::now();
+        assert!(transport.drain(Duration::from_secs(5)).unwrap());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn debug_transport() {
+        let transport = WriterTransport::new(Vec::new()).with_prefix("[would-send] ");
+        Transport::<Rfc5424>::send(&transport, b"<14>1 - - - - - - Hello".to_vec()).unwrap();
+        assert_eq!(
+            Transport::<Rfc5424>::send_slice(&transport, b"<14>1 - - - - - - world")
+                .map(|rsp| rsp.is_ok()),
+            Some(true)
+        );
+        assert_eq!(
+            transport.into_inner(),
+            b"[would-send] <14>1 - - - - - - Hello\n[would-send] <14>1 - - - - - - world\n"
+        );
+        assert_eq!(
+            WriterTransport::debug().prefix.as_deref(),
+            Some("[would-send] ")
+        );
+    }
+
+    #[test]
+    fn rate_limiting() {
+        let inner = VecTransport::new();
+        let transport = RateLimitedTransport::new(inner.clone(), 5, Duration::from_secs(3600));
+        for i in 0..12 {
+            Transport::<Rfc5424>::send(&transport, format!("message {}", i).into_bytes()).unwrap();
+        }
+        assert_eq!(inner.messages().len(), 5);
+        assert_eq!(transport.dropped(), 7);
+        assert_eq!(inner.messages()[4], b"message 4");
+
+        // A zero-length interval disables rate-limiting altogether
+        let inner = VecTransport::new();
+        let transport = RateLimitedTransport::new(inner.clone(), 1, Duration::ZERO);
+        for _ in 0..3 {
+            Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        }
+        assert_eq!(inner.messages().len(), 3);
+        assert_eq!(transport.dropped(), 0);
+
+        // While a limit of zero discards everything
+        let inner = VecTransport::new();
+        let transport = RateLimitedTransport::new(inner.clone(), 0, Duration::from_secs(1));
+        for _ in 0..3 {
+            Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        }
+        assert!(inner.messages().is_empty());
+        assert_eq!(transport.dropped(), 3);
+    }
+
+    /// A [`Transport`] that fails a given number of times before succeeding
+    struct FlakyTransport {
+        failures: AtomicU64,
+        attempts: AtomicU64,
+    }
+
+    impl Transport<Rfc5424> for FlakyTransport {
+        type Error = Error;
+        fn send(&self, _buf: Vec<u8>) -> std::result::Result<(), Self::Error> {
+            self.attempts.fetch_add(1, Ordering::Relaxed);
+            if self.failures.load(Ordering::Relaxed) > 0 {
+                self.failures.fetch_sub(1, Ordering::Relaxed);
+                Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn retry() {
+        let transport = RetryTransport::new(
+            FlakyTransport {
+                failures: AtomicU64::new(2),
+                attempts: AtomicU64::new(0),
+            },
+            3,
+            Duration::from_millis(1),
+        );
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(transport.inner.attempts.load(Ordering::Relaxed), 3);
+
+        // Out of retries: the last error should be returned
+        let transport = RetryTransport::new(
+            FlakyTransport {
+                failures: AtomicU64::new(5),
+                attempts: AtomicU64::new(0),
+            },
+            2,
+            Duration::from_millis(1),
+        );
+        assert!(Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).is_err());
+        assert_eq!(transport.inner.attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn retry_broken_connection() {
+        // Retrying a broken connection on its own gets us nowhere...
+        let broken = Arc::new(AtomicBool::new(true));
+        let transport = RetryTransport::new(
+            FragileTransport {
+                broken: broken.clone(),
+            },
+            3,
+            Duration::from_millis(1),
+        );
+        assert!(Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).is_err());
+
+        // but with a `LazyTransport` in between, the retry goes out on a new connection.
+        let (connect, connects, current) = fragile_connector();
+        let transport =
+            RetryTransport::new(LazyTransport::new(connect), 3, Duration::from_millis(1));
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        current.lock().unwrap().store(true, Ordering::Relaxed);
+        Transport::<Rfc5424>::send(&transport, b"Hello".to_vec()).unwrap();
+        assert_eq!(connects.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn buffered() {
+        // Size-triggered flushing
+        let inner = VecTransport::new();
+        let transport = BufferedTransport::<Rfc5424, _>::new(inner.clone(), 3);
+        transport.send(b"one".to_vec()).unwrap();
+        transport.send(b"two".to_vec()).unwrap();
+        assert!(inner.messages().is_empty());
+        let dbg = format!("{:?}", transport);
+        assert!(dbg.contains("pending: 2"), "{}", dbg);
+        assert!(dbg.contains("timed_flush: false"), "{}", dbg);
+        transport.send(b"three".to_vec()).unwrap();
+        assert_eq!(
+            inner.messages(),
+            vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]
+        );
+
+        // Explicit & on-drop flushing
+        transport.send(b"four".to_vec()).unwrap();
+        transport.flush().unwrap();
+        assert_eq!(inner.messages().len(), 4);
+        transport.send(b"five".to_vec()).unwrap();
+        drop(transport);
+        assert_eq!(inner.messages().len(), 5);
+    }
+
+    #[test]
+    fn buffered_flush_interval() {
+        let inner = VecTransport::new();
+        let transport = BufferedTransport::<Rfc5424, _>::new(inner.clone(), 100)
+            .with_flush_interval(Duration::from_millis(20));
+        transport.send(b"Hello".to_vec()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while inner.messages().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(inner.messages(), vec![b"Hello".to_vec()]);
+        // Dropping the transport must stop (& join) the flush thread
+        drop(transport);
+    }
+
+    #[test]
+    fn channel_burst() {
+        let inner = VecTransport::new();
+        let transport =
+            ChannelTransport::<Rfc5424, _>::new(inner.clone(), 16, Overflow::Block).unwrap();
+        let burst: Vec<Vec<u8>> = (0..1000).map(|i| format!("{}", i).into_bytes()).collect();
+        for buf in &burst {
+            transport.send(buf.clone()).unwrap();
+        }
+        transport.flush().unwrap();
+        assert_eq!(inner.messages(), burst);
+        assert_eq!(transport.dropped(), 0);
+
+        // Messages still queued when the transport is dropped go out, too
+        for buf in &burst {
+            transport.send(buf.clone()).unwrap();
+        }
+        drop(transport);
+        assert_eq!(inner.messages().len(), 2000);
+    }
+
+    #[test]
+    fn channel_overflow() {
+        /// A transport that won't send while the test holds its gate
+        struct Gated {
+            gate: Arc<Mutex<()>>,
+            out: VecTransport,
+        }
+        impl Transport<Rfc5424> for Gated {
+            type Error = Error;
+            fn send(&self, buf: Vec<u8>) -> Result<()> {
+                let _gate = self.gate.lock().unwrap();
+                Transport::<Rfc5424>::send(&self.out, buf)
+            }
+        }
+
+        for (overflow, golden) in [
+            (Overflow::DropNewest, [b"0", b"1", b"2"]),
+            (Overflow::DropOldest, [b"0", b"3", b"4"]),
+        ] {
+            let gate = Arc::new(Mutex::new(()));
+            let out = VecTransport::new();
+            let transport = ChannelTransport::<Rfc5424, _>::new(
+                Gated {
+                    gate: gate.clone(),
+                    out: out.clone(),
+                },
+                2,
+                overflow,
+            )
+            .unwrap();
+            let held = gate.lock().unwrap();
+            // Wait for the background thread to pick up the first message (& block on the gate)
+            transport.send(b"0".to_vec()).unwrap();
+            while !transport.shared.queue().busy {
+                std::thread::yield_now();
+            }
+            // That leaves room for two more
+            for buf in [b"1", b"2", b"3", b"4"] {
+                transport.send(buf.to_vec()).unwrap();
+            }
+            assert_eq!(transport.dropped(), 2);
+            let dbg = format!("{:?}", transport);
+            assert!(dbg.contains("queued: 2, dropped: 2"), "{}", dbg);
+            drop(held);
+            transport.flush().unwrap();
+            assert_eq!(
+                out.messages(),
+                golden.iter().map(|buf| buf.to_vec()).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn channel_panic() {
+        /// A transport that panics on its first send
+        struct Panicky;
+        impl Transport<Rfc5424> for Panicky {
+            type Error = Error;
+            fn send(&self, _buf: Vec<u8>) -> Result<()> {
+                panic!("the inner transport panicked");
+            }
+        }
+
+        for overflow in [Overflow::Block, Overflow::DropNewest] {
+            let transport = ChannelTransport::<Rfc5424, _>::new(Panicky, 1, overflow).unwrap();
+            transport.send(b"0".to_vec()).unwrap();
+            // Neither flushing nor sending should wait on the background thread, once it's gone
+            assert!(matches!(transport.flush(), Err(Error::Io { .. })));
+            for _ in 0..2 {
+                assert!(matches!(
+                    transport.send(b"1".to_vec()),
+                    Err(Error::Io { .. })
+                ));
+            }
+            assert!(!transport.shared.queue().busy);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Count the allocations made per event. This lives in its own test binary (& hence its own
+//! process) since it installs a global allocator, which would otherwise apply to every test.
+
+use tracing_rfc_5424::{
+    layer::Layer,
+    rfc5424::Rfc5424,
+    tracing::TrivialTracingFormatter,
+    transport::{Error, Transport},
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+// Count allocations made on each thread, so that tests running concurrently don't disturb one
+// another
+struct CountingAllocator;
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A transport that accepts slices & discards them
+struct NullTransport;
+
+impl Transport<Rfc5424> for NullTransport {
+    type Error = Error;
+    fn send(&self, _buf: Vec<u8>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn send_slice(&self, _buf: &[u8]) -> Option<Result<(), Self::Error>> {
+        Some(Ok(()))
+    }
+}
+
+#[test]
+fn allocations_per_event() {
+    let subscriber = tracing_subscriber::registry::Registry::default().with(Layer::new(
+        Rfc5424::builder()
+            .hostname_as_string("bree.local".to_string())
+            .unwrap()
+            .appname_as_string("prototyping".to_string())
+            .unwrap()
+            .pid_as_string("123".to_string())
+            .unwrap()
+            .build(),
+        TrivialTracingFormatter::default(),
+        NullTransport,
+    ));
+    tracing::subscriber::with_default(subscriber, || {
+        // Warm-up the scratch buffer
+        tracing::info!("Hello, world!");
+        let before = ALLOCATIONS.with(|n| n.get());
+        for _ in 0..100 {
+            tracing::info!("Hello, world!");
+        }
+        let per_event = (ALLOCATIONS.with(|n| n.get()) - before) as f64 / 100.0;
+        // The one remaining allocation is the message `String` produced by the `TracingFormatter`
+        assert!(per_event <= 1.0, "{} allocations per event", per_event);
+    });
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Exercise `SYSLOG_TRACING_DISABLE`. This lives in its own test binary (& hence its own process)
+//! since setting the variable would otherwise disable every `Layer` constructed by tests running
+//! concurrently.
+
+use tracing_rfc_5424::{
+    layer::{Layer, DISABLE_ENV_VAR},
+    transport::VecTransport,
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn disable_env_var() {
+    let transport = VecTransport::new();
+
+    std::env::set_var(DISABLE_ENV_VAR, "1");
+    let subscriber =
+        tracing_subscriber::Registry::default().with(Layer::with_transport(transport.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("Hello, world!");
+    });
+    assert!(transport.messages().is_empty());
+
+    // "0" means "not disabled"
+    std::env::set_var(DISABLE_ENV_VAR, "0");
+    let subscriber =
+        tracing_subscriber::Registry::default().with(Layer::with_transport(transport.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("Hello, world!");
+    });
+    assert_eq!(transport.messages().len(), 1);
+}
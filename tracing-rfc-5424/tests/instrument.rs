@@ -0,0 +1,63 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Exercise `Rfc5424Builder::with_span_fields` with an `#[instrument]`ed function, whose
+//! arguments are recorded as fields on the span it creates.
+
+use tracing_rfc_5424::{
+    layer::Layer,
+    rfc5424::{parse, Rfc5424, SPAN_FIELDS_SDID},
+    tracing::TrivialTracingFormatter,
+    transport::VecTransport,
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+#[tracing::instrument(fields(user_id))]
+fn handle_request(order_id: u32) {
+    tracing::Span::current().record("user_id", 42);
+    tracing::info!("Handling the request");
+}
+
+#[test]
+fn instrument_args_as_sd() {
+    let transport = VecTransport::new();
+    let subscriber = tracing_subscriber::Registry::default().with(Layer::new(
+        Rfc5424::builder().with_span_fields(true).build(),
+        TrivialTracingFormatter::default(),
+        transport.clone(),
+    ));
+    tracing::subscriber::with_default(subscriber, || {
+        let outer = tracing::info_span!("session", user_id = 7, tenant = "acme");
+        let _guard = outer.enter();
+        handle_request(1138);
+        tracing::info!("Outside the request");
+    });
+
+    let messages = transport.messages();
+    assert_eq!(messages.len(), 2);
+
+    let parsed = parse(&messages[0]).unwrap();
+    assert_eq!(parsed.msg, "Handling the request");
+    let sd = &parsed.structured_data[SPAN_FIELDS_SDID];
+    // The innermost span's `user_id` wins
+    assert_eq!(sd["user_id"], "42");
+    assert_eq!(sd["order_id"], "1138");
+    assert_eq!(sd["tenant"], "acme");
+
+    let parsed = parse(&messages[1]).unwrap();
+    let sd = &parsed.structured_data[SPAN_FIELDS_SDID];
+    assert_eq!(sd["user_id"], "7");
+    assert!(!sd.contains_key("order_id"));
+}
@@ -0,0 +1,79 @@
+// Copyright (C) 2022 Michael Herstine <sp1ff@pobox.com>
+//
+// This file is part of syslog-tracing.
+//
+// syslog-tracing is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// mpdpopm is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General
+// Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with mpdpopm.  If not,
+// see <http://www.gnu.org/licenses/>.
+
+//! Exercise `LogTransport`. This lives in its own test binary (& hence its own process) since the
+//! `log` crate's logger may only be installed once per process.
+#![cfg(feature = "log")]
+
+use tracing_rfc_5424::{layer::Layer, rfc5424::Rfc5424, transport::LogTransport};
+use tracing_subscriber::layer::SubscriberExt;
+
+use std::sync::Mutex;
+
+/// A `log::Log` implementation that just remembers what it was given
+struct Capture(Mutex<Vec<(log::Level, String, String)>>);
+
+impl log::Log for Capture {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record) {
+        self.0.lock().unwrap().push((
+            record.level(),
+            record.target().to_string(),
+            record.args().to_string(),
+        ));
+    }
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+#[test]
+fn log_transport() {
+    log::set_logger(&CAPTURE).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let subscriber = tracing_subscriber::Registry::default().with(Layer::new(
+        Rfc5424::minimal(),
+        tracing_rfc_5424::tracing::TrivialTracingFormatter::default(),
+        LogTransport::new(),
+    ));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::error!("Oh no!");
+        tracing::warn!("Hmm...");
+        tracing::info!("Hello, world!");
+        tracing::trace!("Down in the weeds");
+    });
+
+    let records = CAPTURE.0.lock().unwrap();
+    let levels = records
+        .iter()
+        .map(|(level, _, _)| *level)
+        .collect::<Vec<log::Level>>();
+    assert_eq!(
+        levels,
+        vec![
+            log::Level::Error,
+            log::Level::Warn,
+            log::Level::Info,
+            log::Level::Debug
+        ]
+    );
+    assert!(records.iter().all(|(_, target, _)| target == "syslog"));
+    // The PRI is stripped; the rest of the message is left alone
+    assert!(records[2].2.starts_with("1 "));
+    assert!(records[2].2.ends_with(" - - - - Hello, world!"));
+}